@@ -1,11 +1,36 @@
+// ...oh how i yearn for #![feature(try_blocks)]
+macro_rules! labelled_try {
+    ($label:lifetime, $computation:expr) => {
+        match $computation {
+            Some(x) => x,
+            None => break $label,
+        }
+    }
+}
+
+mod ice;
 mod cmd_build;
+mod cmd_test;
+mod cmd_explain;
+mod cmd_fix;
 mod cmd_debug_lex;
 mod cmd_debug_parse;
 mod cmd_debug_error;
 
-use std::io;
-use clap::{ Parser, Subcommand, Args };
-use libcosyc::{ pretty, pretty::PrettyPrinter };
+use std::{ fs, io };
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{ Parser, Subcommand, Args, ValueEnum };
+use libcosyc::{ msg, locale, pretty, pretty::PrettyPrinter };
+use libcosyc::src::SourceMap;
+use libcosyc::error::{ self, Diagnostic, ErrorCode, IssueManager, Severity, lint::LintLevel };
+
+/// The exit code used when a fatal error was reported.
+const EXIT_ERROR : u8 = 1;
+
+/// The exit code used when a compiler bug was reported. This is the same code
+/// Rust programs exit with when they panic.
+const EXIT_BUG : u8 = 101;
 
 /// The Cosy compiler! /(.@ w @.) b
 #[derive(Parser)]
@@ -15,6 +40,31 @@ struct Cosyc {
     /// Whether to disable printing to the output window in colour.
     #[arg(long="no-colour")]
     use_no_colour : bool,
+    /// How diagnostics should be reported.
+    #[arg(long="error-format", value_enum, default_value_t=ErrorFormat::Human, global=true)]
+    error_format : ErrorFormat,
+    /// Writes diagnostics to this file instead of stderr.
+    #[arg(long="error-output", global=true)]
+    error_output : Option<PathBuf>,
+    /// Ignores a lint. Use `warnings` to ignore every warning.
+    #[arg(short='A', long="allow", value_name="LINT", global=true)]
+    allow : Vec<String>,
+    /// Reports a lint as a warning.
+    #[arg(short='W', long="warn", value_name="LINT", global=true)]
+    warn : Vec<String>,
+    /// Reports a lint as an error, taking priority over `-A` and `-W`. Use
+    /// `warnings` to treat every warning as an error.
+    #[arg(short='D', long="deny", value_name="LINT", global=true)]
+    deny : Vec<String>,
+    /// The maximum number of errors to display. Any more are counted, but
+    /// not shown.
+    #[arg(long="max-errors", value_name="N", global=true)]
+    max_errors : Option<usize>,
+    /// The language to display diagnostics in, e.g. `en`. Defaults to the
+    /// language set by the `LC_ALL`, `LC_MESSAGES`, or `LANG` environment
+    /// variables.
+    #[arg(long="lang", value_name="LANG", global=true)]
+    lang : Option<String>,
     /// The Cosy command to execute.
     #[command(subcommand)]
     command : CosycCommand,
@@ -22,13 +72,124 @@ struct Cosyc {
 
 type PrinterTy = PrettyPrinter<io::Stderr>;
 
+/// The output format of diagnostics.
+#[derive(Clone, Copy, ValueEnum)]
+enum ErrorFormat {
+    /// Human-readable messages, with source code snippets.
+    Human,
+    /// One JSON object per message, for use by editors and other tools.
+    Json,
+    /// A SARIF 2.1.0 log, for use by code scanning tools.
+    Sarif,
+}
+
 struct CommonArgs {
     printer : PrinterTy,
+    error_format : ErrorFormat,
+    error_output : Option<PathBuf>,
+    lint_levels : Vec<(String, LintLevel)>,
+    max_errors : Option<usize>,
+    /// The language passed to `--lang`, if it isn't supported.
+    unknown_lang : Option<String>,
+}
+
+impl CommonArgs {
+    /// Creates an issue manager using the lint levels passed on the command
+    /// line, reporting any unknown lints or languages.
+    fn new_issues(&self) -> IssueManager {
+        let mut issues = IssueManager::default();
+        issues.max_errors = self.max_errors;
+        for (name, level) in &self.lint_levels {
+            issues.set_lint_level(name, *level);
+        }
+        if let Some(lang) = &self.unknown_lang {
+            let known = locale::languages()
+                .map(|name| format!("`{}`", name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Diagnostic::warning()
+                .message(msg!("cli-unknown-language", [
+                    lang.clone().into(), locale::language().into()
+                ]))
+                .note(msg!("cli-known-languages", [known.into()]))
+                .report(&mut issues);
+        }
+        issues
+    }
+
+    /// Reports any diagnostics, then returns the exit code for the most
+    /// severe one. If the error output file can't be written to, that is
+    /// reported as an error and the diagnostics are written to stderr
+    /// instead.
+    fn finish(&mut self, files : &SourceMap, issues : &mut IssueManager) -> ExitCode {
+        let Err(err) = self.write_errors(files, issues) else {
+            return exit_code(issues);
+        };
+        let Some(path) = self.error_output.take() else {
+            panic!("failed to write diagnostics to stderr: {}", err);
+        };
+        let diag = Diagnostic::from(err)
+            .code(ErrorCode::E0086)
+            .message(msg!("cli-error-output-failed", [path.display().into()]))
+            .note(msg!("cli-error-output-failed-note"));
+        // added directly, so it can't be hidden by `--max-errors`
+        issues.errors.insert(0, diag);
+        self.write_errors(files, issues).unwrap();
+        exit_code(issues)
+    }
+
+    /// Reports any diagnostics using the requested error format, either to
+    /// stderr or to the error output file.
+    fn write_errors(
+        &mut self,
+        files : &SourceMap,
+        issues : &IssueManager,
+    ) -> io::Result<()> {
+        if let Some(path) = &self.error_output {
+            let mut out = io::BufWriter::new(fs::File::create(path)?);
+            return match self.error_format {
+                ErrorFormat::Human => {
+                    let mut printer = PrettyPrinter::new(out, false);
+                    error::cli::write_errors(&mut printer, files, issues)
+                },
+                ErrorFormat::Json => {
+                    error::json::write_errors(&mut out, files, issues)
+                },
+                ErrorFormat::Sarif => {
+                    error::sarif::write_errors(&mut out, files, issues)
+                },
+            };
+        }
+        match self.error_format {
+            ErrorFormat::Human => {
+                error::cli::write_errors(&mut self.printer, files, issues)
+            },
+            ErrorFormat::Json => {
+                error::json::write_errors(&mut io::stderr(), files, issues)
+            },
+            ErrorFormat::Sarif => {
+                error::sarif::write_errors(&mut io::stderr(), files, issues)
+            },
+        }
+    }
+}
+
+/// Returns the exit code for the most severe diagnostic in `issues`. Warnings
+/// and other messages don't cause a failure.
+fn exit_code(issues : &IssueManager) -> ExitCode {
+    match issues.error_stats().max_severity {
+        Severity::Bug => ExitCode::from(EXIT_BUG),
+        Severity::Fatal => ExitCode::from(EXIT_ERROR),
+        Severity::Info | Severity::Help | Severity::Warning => ExitCode::SUCCESS,
+    }
 }
 
 #[derive(Subcommand)]
 enum CosycCommand {
     Build(cmd_build::Args),
+    Test(cmd_test::Args),
+    Explain(cmd_explain::Args),
+    Fix(cmd_fix::Args),
     #[command(subcommand)]
     Debug(CosycCommandDebug),
 }
@@ -41,13 +202,38 @@ enum CosycCommandDebug {
     Error(cmd_debug_error::Args),
 }
 
-pub(super) fn execute() {
+pub(super) fn execute() -> ExitCode {
     let cosyc_args = Cosyc::parse();
+    ice::install_hook();
+    let mut lint_levels = Vec::new();
+    for (names, level) in [
+        (cosyc_args.allow, LintLevel::Allow),
+        (cosyc_args.warn, LintLevel::Warn),
+        (cosyc_args.deny, LintLevel::Deny),
+    ] {
+        lint_levels.extend(names.into_iter().map(|name| (name, level)));
+    }
+    let unknown_lang = match cosyc_args.lang {
+        Some(lang) if !locale::set_language(&lang) => Some(lang),
+        Some(_) => None,
+        None => {
+            locale::set_language_from_env();
+            None
+        },
+    };
     let common_args = CommonArgs {
         printer : pretty::from_term(io::stderr(), !cosyc_args.use_no_colour),
+        error_format : cosyc_args.error_format,
+        error_output : cosyc_args.error_output,
+        lint_levels,
+        max_errors : cosyc_args.max_errors,
+        unknown_lang,
     };
     match cosyc_args.command {
         CosycCommand::Build(args) => cmd_build::execute(common_args, args),
+        CosycCommand::Test(args) => cmd_test::execute(common_args, args),
+        CosycCommand::Explain(args) => cmd_explain::execute(common_args, args),
+        CosycCommand::Fix(args) => cmd_fix::execute(common_args, args),
         CosycCommand::Debug(debug_cmd) => match debug_cmd {
             CosycCommandDebug::Lex(args) => cmd_debug_lex::execute(common_args, args),
             CosycCommandDebug::Parse(args) => cmd_debug_parse::execute(common_args, args),
@@ -1,16 +1,29 @@
 use std::path::PathBuf;
 use std::fs;
+use std::process::ExitCode;
 
 use libcosyc::src::{ SourceMap, LoadManifestResult, SaveManifestResult };
-use libcosyc::error::{ cli, IssueManager };
 use libcosyc::build;
 
-/// Builds the package and immediately runs its entrypoint.
+/// Builds the package into an executable, without running it.
+///
+/// The executable is written to `<cache>/<package>/<package>`. Use `--release`
+/// to build with optimisations and without runtime checks, and `--link` or
+/// `-l` to link extra object files or system libraries into the executable.
 #[derive(super::Args)]
 pub(super) struct Args {
     /// The path to the cache directory. Defaults to `build/<config>/cache`.
     #[arg(short, long="cache")]
     cache_dir : Option<PathBuf>,
+    /// Whether to build with optimisations, and without runtime checks.
+    #[arg(long)]
+    release : bool,
+    /// Extra object files or static libraries to link into the executable.
+    #[arg(long="link")]
+    link_files : Vec<PathBuf>,
+    /// Extra system libraries to link into the executable, e.g. `m`.
+    #[arg(short='l', long="lib")]
+    libs : Vec<String>,
     /// Path to the package to build (defaults to the working directory):
     ///  * If the path is a `.cy` file, then that file will act as the entrypoint.
     ///  * If the path is a directory, then a file named `main.cy` will be used as the entrypoint.
@@ -18,55 +31,55 @@ pub(super) struct Args {
     package_path : PathBuf,
 }
 
-// ...oh how i yearn for #![feature(try_blocks)]
-macro_rules! labelled_try {
-    ($label:lifetime, $computation:expr) => {
-        match $computation {
-            Some(x) => x,
-            None => break $label,
-        }
-    }
-}
-
-pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
-    let cache = args.cache_dir.unwrap_or_else(|| {
-        build::default_cache(build::Config::Debug)
-    });
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let config = if args.release {
+        build::Config::Release
+    } else {
+        build::Config::Debug
+    };
+    let cache = args.cache_dir.unwrap_or_else(|| build::default_cache(config));
     fs::create_dir_all(&cache).unwrap();
     let cache_manifest = cache.as_path().join("manifest.bin");
-    let mut issues = IssueManager::default();
+    let mut issues = cargs.new_issues();
     let mut files = match SourceMap::load_from_path(&cache_manifest) {
         LoadManifestResult::Ok(ok) => ok,
         _ => SourceMap::new(),
     };
-    'task: {
+    super::ice::catch(&mut files, &mut issues, &cache, |files, issues| 'task: {
         let (name, root) = labelled_try!('task, build::find_package_root(
-            &mut issues,
+            issues,
             &args.package_path,
         ));
         let cache_package = cache.as_path().join(&name);
-        let _hir = labelled_try!('task, build::build_module(
-            &mut files,
-            &mut issues,
+        let hir = labelled_try!('task, build::build_module(
+            files,
+            issues,
             &cache_package,
             &root,
         ));
-        println!("{:?}", _hir);
+        if issues.has_errors() { break 'task }
         let casm = labelled_try!('task, build::build_package_casm(
-            &mut issues,
-            &cache_package,
+            issues,
+            config,
+            &[hir],
         ));
-        let bc_path = labelled_try!('task, build::build_package_llvm(
-            &mut issues,
+        let o_path = labelled_try!('task, build::build_package_llvm(
+            files,
+            issues,
+            config,
             &cache_package,
+            &name,
             &casm,
         ));
         let cache_bin = cache_package.as_path().join(&name);
+        let mut o_files = vec![o_path];
+        o_files.extend(args.link_files);
         if !build::link_program(
-            &mut issues,
-            &[bc_path],
+            issues,
+            &o_files,
+            &args.libs,
             &cache_bin,
         ) { break 'task }
-    }
-    cli::write_errors(&mut cargs.printer, &mut files, &mut issues).unwrap();
+    });
+    cargs.finish(&files, &mut issues)
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
 
+use libcosyc::msg;
 use libcosyc::src::SourceMap;
-use libcosyc::error::{ cli, Diagnostic, Severity, IssueManager };
+use libcosyc::error::{ Diagnostic, Severity, ErrorCode };
 use libcosyc::ir::ast::parse::lex::{ Lexer, Token };
 
 /// Tokenises a file, reporting each token as an error. Used to test error
@@ -15,15 +17,16 @@ pub(super) struct Args {
     path : PathBuf,
 }
 
-pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
-    let mut issues = IssueManager::default();
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let mut issues = cargs.new_issues();
     let mut files = SourceMap::default();
     'task: {
         let file = match files.load_file(&args.path) {
             Ok(ok) => ok,
             Err(err) => {
                 Diagnostic::from(err)
-                    .message(("failed to open file `{}`", [
+                    .code(ErrorCode::E0001)
+                    .message(msg!("cli-open-file-failed", [
                         args.path.display().into(),
                     ]))
                     .report(&mut issues);
@@ -41,12 +44,12 @@ pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
                 _ => Severity::Warning,
             };
             let mut diag = Diagnostic::new(severity)
-                .message(("token name: {}", [token_name.into()]))
+                .message(msg!("cli-debug-token-name", [token_name.into()]))
                 .label((file.location(&token_span),
-                    ("span: {}", [format!("{:?}", token_span).into()]).into(),
+                    msg!("cli-debug-span", [format!("{:?}", token_span).into()]),
                 ));
             if lexer.peek_linebreak() {
-                diag = diag.note("this token marks the end of a line");
+                diag = diag.note(msg!("cli-debug-end-of-line"));
             }
             diag.report(&mut issues);
             if token.1 == Token::EoF {
@@ -56,14 +59,14 @@ pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
         let span_end = lexer.peek_span();
         let span_full = span_start.join(span_end);
         Diagnostic::warning()
-            .message("full span")
+            .message(msg!("cli-debug-full-span"))
             .label((file.location(&span_full), 
-                    ("span: {}", [format!("{:?}", span_full).into()]).into(),
+                    msg!("cli-debug-span", [format!("{:?}", span_full).into()]),
                 ))
-            .label_other((file.location(&span_start), "starts here".into()))
-            .label_other((file.location(&span_end), "ends here".into()))
-            .note("end-of-file tokens aren't rendered")
+            .label_other((file.location(&span_start), msg!("cli-debug-starts-here")))
+            .label_other((file.location(&span_end), msg!("cli-debug-ends-here")))
+            .note(msg!("cli-debug-eof-note"))
             .report(&mut issues);
     }
-    cli::write_errors(&mut cargs.printer, &mut files, &mut issues).unwrap();
+    cargs.finish(&files, &mut issues)
 }
\ No newline at end of file
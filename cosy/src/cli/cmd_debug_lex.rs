@@ -1,7 +1,9 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
 
+use libcosyc::msg;
 use libcosyc::src::SourceMap;
-use libcosyc::error::{ cli, Diagnostic, IssueManager };
+use libcosyc::error::{ Diagnostic, ErrorCode };
 use libcosyc::ir::ast::parse::lex;
 
 /// Tokenises a file and outputs its lexical info.
@@ -14,15 +16,16 @@ pub(super) struct Args {
     path : PathBuf,
 }
 
-pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
-    let mut issues = IssueManager::default();
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let mut issues = cargs.new_issues();
     let mut files = SourceMap::default();
     'task: {
         let file = match files.load_file(&args.path) {
             Ok(ok) => ok,
             Err(err) => {
                 Diagnostic::from(err)
-                    .message(("failed to open file `{}`", [
+                    .code(ErrorCode::E0001)
+                    .message(msg!("cli-open-file-failed", [
                         args.path.display().into(),
                     ]))
                     .report(&mut issues);
@@ -31,5 +34,5 @@ pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
         };
         lex::debug_write_tokens(&mut cargs.printer, &args.path, file.as_ref()).unwrap();
     }
-    cli::write_errors(&mut cargs.printer, &mut files, &mut issues).unwrap();
+    cargs.finish(&files, &mut issues)
 }
\ No newline at end of file
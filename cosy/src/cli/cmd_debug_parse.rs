@@ -1,8 +1,10 @@
 use std::path::PathBuf;
+use std::process::ExitCode;
 
+use libcosyc::msg;
 use libcosyc::src::SourceMap;
-use libcosyc::error::{ cli, Diagnostic, IssueManager };
-use libcosyc::ir::ast;
+use libcosyc::error::{ Diagnostic, ErrorCode };
+use libcosyc::ir::{ ast, hir };
 
 /// Parses the contents of a file and prints its untyped AST.
 #[derive(super::Args)]
@@ -15,15 +17,16 @@ pub(super) struct Args {
     path : PathBuf,
 }
 
-pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
-    let mut issues = IssueManager::default();
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let mut issues = cargs.new_issues();
     let mut files = SourceMap::default();
     'task: {
         let file = match files.load_file(&args.path) {
             Ok(ok) => ok,
             Err(err) => {
                 Diagnostic::from(err)
-                    .message(("failed to open file `{}`", [
+                    .code(ErrorCode::E0001)
+                    .message(msg!("cli-open-file-failed", [
                         args.path.display().into(),
                     ]))
                     .report(&mut issues);
@@ -32,11 +35,11 @@ pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) {
         };
         let ast = ast::parse::from_file(&mut issues, file.as_ref());
         if args.lower {
-            //let hir = hir::lower::from_ast(&mut issues, &ast);
-            //hir::debug_write_hir(&mut cargs.printer, &files, &hir);
+            let hir = hir::lower::from_ast(&mut issues, &ast);
+            hir::debug_write_hir(&mut cargs.printer, &files, &hir).unwrap();
         } else {
             ast::debug_write_ast(&mut cargs.printer, &files, &ast).unwrap();
         }
     }
-    cli::write_errors(&mut cargs.printer, &mut files, &mut issues).unwrap();
+    cargs.finish(&files, &mut issues)
 }
\ No newline at end of file
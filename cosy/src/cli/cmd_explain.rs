@@ -0,0 +1,30 @@
+use std::process::ExitCode;
+
+use libcosyc::msg;
+use libcosyc::src::SourceMap;
+use libcosyc::error::{ Diagnostic, ErrorCode };
+
+/// Displays a detailed explanation of an error code, e.g. `E0019`.
+#[derive(super::Args)]
+pub(super) struct Args {
+    /// The error code to explain.
+    #[arg()]
+    code : String,
+}
+
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let mut issues = cargs.new_issues();
+    let files = SourceMap::default();
+    match ErrorCode::from_name(&args.code) {
+        Some(code) => print!("{}", code.explanation()),
+        None => {
+            Diagnostic::error()
+                .message(msg!("cli-invalid-error-code", [
+                    args.code.into()
+                ]))
+                .note(msg!("cli-invalid-error-code-note"))
+                .report(&mut issues);
+        },
+    }
+    cargs.finish(&files, &mut issues)
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io;
+use std::process::ExitCode;
+
+use libcosyc::msg;
+use libcosyc::src::SourceMap;
+use libcosyc::error::{ fix, Diagnostic, ErrorCode };
+use libcosyc::pretty::{ Colour, Decoration };
+use libcosyc::build;
+
+/// The maximum number of times the package is checked for new suggestions,
+/// since applying one fix may allow another to be found.
+const MAX_PASSES : usize = 4;
+
+/// Applies every machine-applicable suggestion to the source files of the
+/// package, then reports any remaining diagnostics.
+#[derive(super::Args)]
+pub(super) struct Args {
+    /// The path to the cache directory. Defaults to `build/debug/cache`.
+    #[arg(short, long="cache")]
+    cache_dir : Option<PathBuf>,
+    /// Path to the package to fix (defaults to the working directory):
+    ///  * If the path is a `.cy` file, then that file will act as the entrypoint.
+    ///  * If the path is a directory, then a file named `main.cy` will be used as the entrypoint.
+    #[arg(verbatim_doc_comment)]
+    package_path : PathBuf,
+}
+
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let cache = args.cache_dir
+        .unwrap_or_else(|| build::default_cache(build::Config::Debug));
+    let mut issues = cargs.new_issues();
+    let mut files = SourceMap::new();
+    let mut fixes = BTreeMap::new();
+    for pass in 0.. {
+        // the files are reloaded each pass, since they may have been fixed
+        issues = cargs.new_issues();
+        files = SourceMap::new();
+        let hir = super::ice::catch(&mut files, &mut issues, &cache, |files, issues| {
+            let (name, root) = build::find_package_root(issues, &args.package_path)?;
+            let cache_package = cache.as_path().join(&name);
+            build::check_module(files, issues, &cache_package, &root)
+        });
+        if hir.flatten().is_none() {
+            break;
+        }
+        if pass == MAX_PASSES {
+            break;
+        }
+        let fixed = match fix::apply_suggestions(&files, &issues) {
+            Ok(ok) => ok,
+            Err(err) => {
+                Diagnostic::from(err)
+                    .code(ErrorCode::E0083)
+                    .message(msg!("cli-fix-failed"))
+                    .report(&mut issues);
+                break;
+            },
+        };
+        if fixed.is_empty() {
+            break;
+        }
+        for file in fixed {
+            *fixes.entry(file.path).or_insert(0) += file.fixes;
+        }
+    }
+    write_fixes(&mut cargs.printer, &fixes).unwrap();
+    cargs.finish(&files, &mut issues)
+}
+
+fn write_fixes(
+    printer : &mut super::PrinterTy,
+    fixes : &BTreeMap<PathBuf, usize>,
+) -> io::Result<()> {
+    for (path, count) in fixes {
+        let plural = if *count == 1 { "" } else { "es" };
+        printer.write_style(Colour::BrightGreen.decorated(Decoration::Bold))?;
+        printer.write("fixed")?;
+        printer.clear_style()?;
+        printer.write(&format!(" {} ({} fix{})\n", path.display(), count, plural))?;
+    }
+    Ok(())
+}
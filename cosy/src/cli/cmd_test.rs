@@ -0,0 +1,191 @@
+use std::path::{ Path, PathBuf };
+use std::process::{ Command, ExitCode };
+use std::{ fs, io };
+
+use libcosyc::msg;
+use libcosyc::src::{ SourceMap, LoadManifestResult };
+use libcosyc::error::{ Diagnostic, IssueManager, ErrorCode };
+use libcosyc::pretty::{ Colour, Decoration };
+use libcosyc::build;
+
+/// Builds the package as a test harness, and runs every function marked with
+/// `#[test]`. Each test is run in its own process, so a failing test doesn't
+/// stop the remaining tests from running.
+#[derive(super::Args)]
+pub(super) struct Args {
+    /// The path to the cache directory. Defaults to `build/<config>/cache`.
+    #[arg(short, long="cache")]
+    cache_dir : Option<PathBuf>,
+    /// Whether to build with optimisations, and without runtime checks.
+    #[arg(long)]
+    release : bool,
+    /// Extra object files or static libraries to link into the executable.
+    #[arg(long="link")]
+    link_files : Vec<PathBuf>,
+    /// Extra system libraries to link into the executable, e.g. `m`.
+    #[arg(short='l', long="lib")]
+    libs : Vec<String>,
+    /// Only run tests whose names contain this string.
+    #[arg(long)]
+    filter : Option<String>,
+    /// Path to the package to test (defaults to the working directory):
+    ///  * If the path is a `.cy` file, then that file will act as the entrypoint.
+    ///  * If the path is a directory, then a file named `main.cy` will be used as the entrypoint.
+    #[arg(verbatim_doc_comment)]
+    package_path : PathBuf,
+}
+
+/// The outcome of running a single test.
+struct TestResult {
+    name : String,
+    passed : bool,
+    /// Anything the test wrote to stderr, e.g. the reason an assertion failed.
+    output : String,
+}
+
+pub(super) fn execute(mut cargs : super::CommonArgs, args : Args) -> ExitCode {
+    let config = if args.release {
+        build::Config::Release
+    } else {
+        build::Config::Debug
+    };
+    let cache = args.cache_dir.unwrap_or_else(|| build::default_cache(config));
+    fs::create_dir_all(&cache).unwrap();
+    let cache_manifest = cache.as_path().join("manifest.bin");
+    let mut issues = cargs.new_issues();
+    let mut files = match SourceMap::load_from_path(&cache_manifest) {
+        LoadManifestResult::Ok(ok) => ok,
+        _ => SourceMap::new(),
+    };
+    let mut results = None;
+    super::ice::catch(&mut files, &mut issues, &cache, |files, issues| 'task: {
+        let (name, root) = labelled_try!('task, build::find_package_root(
+            issues,
+            &args.package_path,
+        ));
+        let cache_package = cache.as_path().join(&name);
+        let hir = labelled_try!('task, build::build_module(
+            files,
+            issues,
+            &cache_package,
+            &root,
+        ));
+        if issues.has_errors() { break 'task }
+        let casm = build::build_test_casm(issues, config, &[hir]);
+        if issues.has_errors() { break 'task }
+        let harness_name = format!("{}_test", name);
+        let o_path = labelled_try!('task, build::build_package_llvm(
+            files,
+            issues,
+            config,
+            &cache_package,
+            &harness_name,
+            &casm,
+        ));
+        let cache_bin = cache_package.as_path().join(&harness_name);
+        let mut o_files = vec![o_path];
+        o_files.extend(args.link_files);
+        if !build::link_program(
+            issues,
+            &o_files,
+            &args.libs,
+            &cache_bin,
+        ) { break 'task }
+        let tests = casm.tests.unwrap_or_default();
+        results = Some(labelled_try!('task, run_tests(
+            issues,
+            &cache_bin,
+            &tests,
+            args.filter.as_deref(),
+        )));
+    });
+    let exit_code = cargs.finish(&files, &mut issues);
+    let Some(results) = results else {
+        // the harness couldn't be built or run, which has been reported
+        return exit_code;
+    };
+    write_results(&mut cargs.printer, &results).unwrap();
+    if results.iter().any(|result| !result.passed) {
+        return ExitCode::FAILURE;
+    }
+    exit_code
+}
+
+/// Runs each test in the test harness at `harness_path`, skipping any tests
+/// which don't contain `filter`.
+fn run_tests(
+    issues : &mut IssueManager,
+    harness_path : &Path,
+    tests : &[String],
+    filter : Option<&str>,
+) -> Option<Vec<TestResult>> {
+    let mut results = Vec::new();
+    for (i, name) in tests.iter().enumerate() {
+        if let Some(filter) = filter && !name.contains(filter) {
+            continue;
+        }
+        let output = match Command::new(harness_path).arg(i.to_string()).output() {
+            Ok(output) => output,
+            Err(err) => {
+                Diagnostic::from(err)
+                    .code(ErrorCode::E0010)
+                    .message(msg!("cli-run-harness-failed", [
+                        harness_path.display().into()
+                    ]))
+                    .report(issues);
+                return None;
+            },
+        };
+        results.push(TestResult {
+            name : name.clone(),
+            passed : output.status.success(),
+            output : String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Some(results)
+}
+
+/// Prints the outcome of each test, followed by the output of the tests which
+/// failed and a summary.
+fn write_results(printer : &mut super::PrinterTy, results : &[TestResult]) -> io::Result<()> {
+    let plural = if results.len() == 1 { "" } else { "s" };
+    printer.write(&format!("running {} test{}\n", results.len(), plural))?;
+    for result in results {
+        printer.write(&format!("test {} ... ", result.name))?;
+        write_status(printer, result.passed)?;
+        printer.write("\n")?;
+    }
+    let failures = results.iter()
+        .filter(|result| !result.passed)
+        .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        printer.write("\nfailures:\n")?;
+        for result in &failures {
+            printer.write_style(Decoration::Bold)?;
+            printer.write(&format!("\n---- {} ----\n", result.name))?;
+            printer.clear_style()?;
+            if !result.output.is_empty() {
+                printer.write(&result.output)?;
+                printer.write("\n")?;
+            }
+        }
+    }
+    printer.write("\ntest result: ")?;
+    write_status(printer, failures.is_empty())?;
+    printer.write(&format!(". {} passed; {} failed\n",
+        results.len() - failures.len(),
+        failures.len(),
+    ))?;
+    Ok(())
+}
+
+fn write_status(printer : &mut super::PrinterTy, passed : bool) -> io::Result<()> {
+    if passed {
+        printer.write_style(Colour::Green)?;
+        printer.write("ok")?;
+    } else {
+        printer.write_style(Colour::Red.decorated(Decoration::Bold))?;
+        printer.write("FAILED")?;
+    }
+    printer.clear_style()
+}
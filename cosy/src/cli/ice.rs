@@ -0,0 +1,159 @@
+//! Converts panics inside the compiler into compiler bug diagnostics, and
+//! writes a bug report bundle which can be attached to an issue.
+
+use std::{ env, fs, io, panic };
+use std::panic::AssertUnwindSafe;
+use std::path::{ Component, Path, PathBuf };
+use std::backtrace::Backtrace;
+use std::cell::{ Cell, RefCell };
+use std::time::{ SystemTime, UNIX_EPOCH };
+use std::fmt::Write;
+
+use libcosyc::msg;
+use libcosyc::src::SourceMap;
+use libcosyc::error::{ Diagnostic, IssueManager };
+use libcosyc::build::{ self, Phase };
+
+/// The details of a panic, recorded by the panic hook.
+struct PanicReport {
+    message : String,
+    location : Option<String>,
+    phase : Option<Phase>,
+    backtrace : Backtrace,
+}
+
+thread_local! {
+    /// Whether panics are currently being caught by `catch`.
+    static CATCHING : Cell<bool> = const { Cell::new(false) };
+    static LAST_PANIC : RefCell<Option<PanicReport>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook which records the details of any panic caught by
+/// `catch`, instead of printing them. Other panics are reported by the
+/// default hook.
+pub(super) fn install_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !CATCHING.get() {
+            default_hook(info);
+            return;
+        }
+        let payload = info.payload();
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+        LAST_PANIC.set(Some(PanicReport {
+            message,
+            location : info.location().map(|location| location.to_string()),
+            phase : build::current_phase(),
+            backtrace : Backtrace::force_capture(),
+        }));
+    }));
+}
+
+/// Runs `task`, returning its result. If it panics, a compiler bug is
+/// reported to `issues` instead, and a bug report bundle containing every
+/// source file in `files` is written to `build_dir`.
+pub(super) fn catch<T>(
+    files : &mut SourceMap,
+    issues : &mut IssueManager,
+    build_dir : &Path,
+    task : impl FnOnce(&mut SourceMap, &mut IssueManager) -> T,
+) -> Option<T> {
+    let was_catching = CATCHING.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(|| task(files, issues)));
+    CATCHING.set(was_catching);
+    if let Ok(result) = result {
+        return Some(result);
+    }
+    let report = LAST_PANIC.take().unwrap_or_else(|| PanicReport {
+        message : "unknown panic".to_string(),
+        location : None,
+        phase : None,
+        backtrace : Backtrace::disabled(),
+    });
+    let mut diag = match report.phase {
+        Some(phase) => Diagnostic::bug()
+            .message(msg!("cli-ice-panicked-while", [phase.description().into()])),
+        None => Diagnostic::bug().message(msg!("cli-ice-panicked")),
+    };
+    diag = match &report.location {
+        Some(location) => diag.note(msg!("cli-ice-panicked-at", [
+            location.clone().into(), report.message.clone().into()
+        ])),
+        None => diag.note(msg!("cli-ice-panicked-message", [report.message.clone().into()])),
+    };
+    let bundle_dir = build_dir.join(format!("ice-{}", timestamp()));
+    diag = match write_bundle(files, &bundle_dir, &report) {
+        Ok(()) => diag.note(msg!("cli-ice-report-written", [
+            bundle_dir.display().into()
+        ])),
+        Err(err) => diag.note(msg!("cli-ice-report-failed", [
+            bundle_dir.display().into(), err.into()
+        ])),
+    };
+    diag.report(issues);
+    None
+}
+
+/// Writes a description of the crash to `bundle_dir/report.txt`, along with
+/// a copy of every loaded source file under `bundle_dir/src`.
+fn write_bundle(
+    files : &SourceMap,
+    bundle_dir : &Path,
+    report : &PanicReport,
+) -> io::Result<()> {
+    fs::create_dir_all(bundle_dir)?;
+    let mut text = String::new();
+    let _ = writeln!(text, "cosy {} ({}-{})",
+        env!("CARGO_PKG_VERSION"), env::consts::ARCH, env::consts::OS);
+    let args = env::args().map(|arg| format!("{:?}", arg)).collect::<Vec<_>>();
+    let _ = writeln!(text, "command: {}", args.join(" "));
+    if let Ok(dir) = env::current_dir() {
+        let _ = writeln!(text, "working directory: {}", dir.display());
+    }
+    if let Some(phase) = report.phase {
+        let _ = writeln!(text, "phase: {:?}", phase);
+    }
+    match &report.location {
+        Some(location) => { let _ = writeln!(text, "panicked at {}:", location); },
+        None => text.push_str("panicked:\n"),
+    }
+    let _ = writeln!(text, "{}", report.message);
+    let loaded = files.loaded_files();
+    text.push_str("\nsource files:\n");
+    for (path, _) in &loaded {
+        let _ = writeln!(text, "  {}", path.display());
+    }
+    let _ = write!(text, "\nbacktrace:\n{}", report.backtrace);
+    fs::write(bundle_dir.join("report.txt"), text)?;
+    for (path, file) in &loaded {
+        let copy_path = bundle_dir.join("src").join(bundle_path(path));
+        if let Some(parent) = copy_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(copy_path, &file.src)?;
+    }
+    Ok(())
+}
+
+/// Converts the path of a source file into a relative path inside the
+/// bundle, so absolute paths and `..` can't escape it.
+fn bundle_path(path : &Path) -> PathBuf {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
@@ -1,5 +1,7 @@
 mod cli;
 
-pub fn main() {
-    cli::execute();
+use std::process::ExitCode;
+
+pub fn main() -> ExitCode {
+    cli::execute()
 }
\ No newline at end of file
@@ -0,0 +1,299 @@
+# The default (English) catalogue of diagnostic messages.
+#
+# See `libcosyc/src/locale.rs` for the syntax. Every key used by the
+# compiler must exist in this file, which is checked by
+# `libcosyc/tests/catalogue.rs`.
+
+## Rendering diagnostics
+
+render-displayed = displayed { $0 } message(s)
+render-error-hidden = 1 more error not shown, use `--max-errors` to display more
+render-errors-hidden = { $0 } more errors not shown, use `--max-errors` to display more
+render-explain = for more information about an error, try `cosy explain { $0 }`
+
+## Building packages
+
+build-phase-loading = loading source files
+build-phase-parsing = parsing
+build-phase-lowering-hir = lowering to HIR
+build-phase-lowering-casm = lowering to Cosy ASM
+build-phase-emitting-llvm = emitting LLVM bitcode
+build-phase-linking = linking
+build-write-prelude-failed = failed to write the prelude to `{ $0 }`
+build-open-module-failed = failed to open module at path `{ $0 }`
+build-no-main = package has no `main` function
+build-no-main-help = add a `fn main() do ... end` declaration to the package entrypoint
+build-main-has-params = `main` cannot take any parameters
+build-main-bad-return = `main` must return either `int` or `()`, got `{ $0 }`
+build-create-cache-failed = failed to create cache directory `{ $0 }`
+build-write-runtime-failed = failed to write the runtime library to `{ $0 }`
+build-compile-runtime-failed = failed to compile the runtime library
+build-invoke-clang-failed = failed to invoke `clang`
+build-link-failed = failed to link executable `{ $0 }`
+build-find-package-failed = failed to find package at path `{ $0 }`
+build-package-name-not-utf8 = invalid package name { $0 } is not a valid UTF-8 name
+
+## Lint levels
+
+lint-unknown = unknown lint `{ $0 }`
+lint-level-from-command-line = `{ $0 } { $1 }` was passed on the command line
+lint-level-override-help = to override `{ $0 } { $1 }`, add `#[allow({ $2 })]`
+lint-known-lints = the known lints are { $0 }
+lint-level-default = `#[{ $0 }({ $1 })]` is on by default
+lint-level-set-here = the lint level is set here
+
+## General diagnostics
+
+diag-bug-report =
+    likely caused by a bug in the compiler, please report the issue:
+    https://github.com/katsaii/cosy-lang/issues
+diag-unimplemented = '{ $0 }' is unimplemented
+diag-unreachable = encountered an unreachable compiler state
+
+## Parsing
+
+parse-expected-token = expected { $0 }, got { $1 }
+parse-unexpected-token = { $0 }, got { $1 }
+parse-unexpected-in-decl-scope = unexpected symbol in declaration scope
+parse-expected-decl-after-attrs = expected a declaration after attributes
+parse-expected-type = expected a type
+parse-invalid-radix = invalid base `{ $0 }` for a radix literal
+parse-invalid-radix-label = expected a base between 2 and 36
+parse-invalid-digits = invalid digit(s) for a base { $0 } literal
+parse-invalid-digit-label = `{ $0 }` is not a valid base { $1 } digit
+parse-digit-range = `0` to `{ $0 }`
+parse-digit-range-letters = `0` to `9` and `a` to `{ $0 }` (in either case)
+parse-valid-digits = the digits of a base { $0 } literal are { $1 }
+parse-number-too-large = number too large to fit in target type
+parse-unclosed-string = unclosed string literal
+parse-unclosed-string-label = expected a closing '"' here
+parse-unclosed-string-help = add the closing quote
+parse-unknown-escape = unknown escape sequence in string literal
+parse-valid-escapes = valid escape sequences are `\n`, `\t`, `\r`, `\\`, `\"`, and `\'`
+parse-unknown-escape-help = if you meant to write a backslash, escape it
+parse-unclosed-raw-id = unclosed raw identifier
+parse-unclosed-raw-id-label = expected a closing '`' here
+parse-unclosed-raw-id-help = add the closing backtick
+
+## Lowering to HIR and type checking
+
+hir-attr-target-fn = a function
+hir-attr-target-extern-fn = an extern function
+hir-attr-target-type = a type declaration
+hir-attr-target-const = a constant
+hir-attr-target-local = a local variable
+hir-attr-target-expr = an expression
+hir-use-maybe-uninit = use of possibly uninitialised local `{ $0 }`
+hir-use-uninit = use of uninitialised local `{ $0 }`
+hir-declared-uninit = declared here without an initial value
+hir-assign-twice = cannot assign twice to immutable local `{ $0 }`
+hir-declared-here = declared here
+hir-make-mutable-help = consider declaring it as mutable
+hir-unreachable-pattern = unreachable pattern
+hir-unreachable-pattern-label = this pattern is already covered by earlier cases
+hir-non-exhaustive-more = non-exhaustive patterns: { $0 } and { $1 } more not covered
+hir-non-exhaustive = non-exhaustive patterns: { $0 } not covered
+hir-has-type = this has type `{ $0 }`
+hir-non-exhaustive-help = add a `case` for each missing pattern, or a wildcard `case _`
+hir-local-kind-param = parameter
+hir-local-kind-binding = binding
+hir-local-kind-local = local
+hir-malformed-ast = malformed AST! { $0 }, got { $1 }
+hir-mismatched-type-got = { $0 }, got `{ $1 }`
+hir-expected-type = expected `{ $0 }`
+hir-ast-expected-module = expected module body
+hir-redefinition = redefinition of `{ $0 }`
+hir-previously-defined = previously defined here
+hir-recursive-type = recursive type `{ $0 }` has infinite size
+hir-recursive-type-note = recursive types are not currently supported
+hir-duplicate-param = duplicate parameter `{ $0 }`
+hir-expected-by-return-type = expected because of this return type
+hir-return-type-inferred = return type inferred from this `return`
+hir-mismatched-return = mismatched return type, got `{ $0 }`
+hir-extern-returns-str = extern functions cannot return a `str`
+hir-extern-returns-str-note = strings returned from C aren't reference counted, so can't be managed by Cosy
+hir-redefine-builtin-type = cannot redefine the built-in type `{ $0 }`
+hir-ast-expected-enum = expected enum definition
+hir-mismatched-types = mismatched types, got `{ $0 }`
+hir-expected-by-type = expected because of this type
+hir-unsupported-const-type = constants of type `{ $0 }` are not supported
+hir-unsupported-const-type-note = constants can only have a numeric type, `bool`, or `()`
+hir-ast-expected-decl = expected declaration
+hir-ast-expected-fn = expected function
+hir-nested-type-decl = type declarations are only allowed at the top level of a module
+hir-nested-extern-fn = extern functions are only allowed at the top level of a module
+hir-const-before-definition = cannot use the constant `{ $0 }` before it is defined
+hir-const-before-definition-help = give `{ $0 }` a type to use it before its definition, e.g. `const { $0 } : int = ...`
+hir-unknown-variable = unknown variable `{ $0 }`
+hir-array-index-not-int = array indices must be integers
+hir-index-out-of-bounds = index out of bounds: the length is { $0 } but the index is { $1 }
+hir-array-has-type = this array has type `{ $0 }`
+hir-cannot-index = cannot index into a value of type `{ $0 }`
+hir-expected-array = expected an array
+hir-not-callable = only functions and enum variants can be called
+hir-incompatible-if = `if` and `else` have incompatible types
+hir-expected-got = expected `{ $0 }`, got `{ $1 }`
+hir-then-has-type = `then` branch has type `{ $0 }`
+hir-invalid-unary = cannot apply `{ $0 }` to a value of type `{ $1 }`
+hir-continue-outside-loop = `continue` outside of a loop
+hir-continue-outside-loop-note = `continue` can only be used inside of `while` and `loop` expressions
+hir-ast-expected-expr = expected expression
+hir-return-outside-fn = `return` outside of a function
+hir-break-outside-loop = `break` outside of a loop
+hir-break-outside-loop-note = `break` can only be used inside of `while` and `loop` expressions
+hir-break-value-in-while = `break` with a value can only be used inside of `loop` expressions
+hir-break-value-in-while-label = `while` loops cannot produce a value
+hir-break-value-in-while-help = consider using `loop` and breaking when the condition is false instead
+hir-expected-by-break = expected because of this `break`
+hir-condition-not-bool = expected a `bool` condition
+hir-add-operands-note = both operands must have the same numeric type, e.g. `int` or `u8`, or both must be `str`
+hir-numeric-operands-note = both operands must have the same numeric type, e.g. `int` or `u8`
+hir-eq-operands-note = both operands must have the same type, either a numeric type or `bool`
+hir-bool-operands-note = both operands must be `bool`
+hir-invalid-binary = cannot apply `{ $0 }` to `{ $1 }` and `{ $2 }`
+hir-invalid-assign-target = invalid assignment target
+hir-invalid-assign-target-note = only local variables and the elements of arrays can be assigned to
+hir-assign-immutable = cannot assign to immutable { $0 } `{ $1 }`
+hir-copy-to-mutable-help = consider copying it into a mutable local: `local mut { $0 } = { $1 }`
+hir-result-missing-args = missing type arguments for `Result`
+hir-result-args-note = `Result` expects an ok type and an error type, e.g. `Result[int, bool]`
+hir-unknown-type = unknown type `{ $0 }`
+hir-array-length-not-literal = array lengths must be integer literals
+hir-array-length-too-large = array length `{ $0 }` is too large
+hir-unexpected-type-args = type `{ $0 }` does not take any type arguments
+hir-result-arg-count = `Result` expects 2 type arguments, but { $0 } were given
+hir-ast-expected-type = expected type
+hir-duplicate-variant = duplicate variant `{ $0 }`
+hir-unknown-variant = no variant named `{ $0 }` in `{ $1 }`
+hir-known-variants = the variants of `{ $0 }` are { $1 }
+hir-infer-result = cannot infer the type arguments of `Result`
+hir-infer-result-note = `Result` values can only be created where their type is known, such as the return value of a function with a return type
+hir-unknown-enum = unknown enum type `{ $0 }`
+hir-variant-arg-count = `{ $0 }::{ $1 }` expects { $2 } argument(s), but { $3 } were given
+hir-variant-defined = variant defined here
+hir-mismatched-arg = mismatched argument type
+hir-unknown-fn = unknown function `{ $0 }`
+hir-fn-arg-count = `{ $0 }` expects { $1 } argument(s), but { $2 } were given
+hir-fn-defined = function defined here
+hir-infer-return-type = cannot infer the return type of `{ $0 }`
+hir-infer-return-type-note = functions without a return type can only be called after their definition
+hir-conversion-arg-count = `{ $0 }` expects 1 argument, but { $1 } were given
+hir-conversion-arg-count-note = conversions are written with a single value, e.g. `{ $0 }(x)`
+hir-invalid-conversion = cannot convert a value of type `{ $0 }` to `{ $1 }`
+hir-invalid-conversion-note = only numeric types can be converted
+hir-extern-invalid-type = the type `{ $0 }` has no C representation
+hir-extern-invalid-type-label = cannot be used in an extern function
+hir-extern-unit-param-note = `()` can only be used as the return type of an extern function
+hir-extern-invalid-type-note = extern functions can only use numeric types, `bool`, and `str`
+hir-test-has-params = test functions cannot take any parameters
+hir-test-bad-return = test functions must return `()`, got `{ $0 }`
+hir-test-bad-return-help = use `assert` to check the results of a test
+hir-assert-arg-count = `assert` expects 1 argument, but { $0 } were given
+hir-assert-arg-count-note = assertions are written with a single condition, e.g. `assert(x > 0)`
+hir-literal-out-of-range = literal out of range for `{ $0 }`
+hir-negative-literal-overflow = `{ $0 }{ $1 }` does not fit into the type `{ $2 }`
+hir-valid-range = the valid range for `{ $0 }` is `{ $1 }..={ $2 }`
+hir-invalid-rational-suffix = invalid suffix `{ $0 }` for a rational literal
+hir-invalid-rational-suffix-note = rational literals can only have the suffixes `f32` and `f64`
+hir-literal-overflow = `{ $0 }` does not fit into the type `{ $1 }`
+hir-inexact-float = literal cannot be represented exactly as `{ $0 }`
+hir-inexact-float-label = this will be rounded to `{ $0 }`
+hir-inexact-float-note = floats have a limited number of binary digits, so most decimal fractions such as `0.1` are rounded
+hir-invalid-number-suffix = invalid suffix `{ $0 }` for a number literal
+hir-valid-suffixes = valid suffixes are { $0 }
+hir-try-not-result = `#[try]` can only be applied to a `Result`, got `{ $0 }`
+hir-try-mismatched-error = `#[try]` cannot return an error of type `{ $0 }` from this function
+hir-try-mismatched-error-label = expected an error of type `{ $0 }` because of this return type
+hir-try-outside-result-fn = `#[try]` can only be used in functions which return a `Result`
+hir-fn-returns = this function returns `{ $0 }`
+hir-try-change-return-help = consider changing the return type to `Result[{ $0 }, { $1 }]`
+hir-fn-no-return-type = this function has no return type
+hir-try-add-return-help = consider adding a return type, e.g. `: Result[(), { $0 }]`
+hir-incompatible-arms = `match` arms have incompatible types
+hir-first-arm-has-type = first arm has type `{ $0 }`
+hir-float-pattern = floating-point literals cannot be used as patterns
+hir-expected-variant = expected an enum variant
+hir-expected-pattern = expected a pattern
+hir-expected-pattern-note = patterns can be `_`, variable names, literals, or enum variants
+hir-pattern-field-count = `{ $0 }::{ $1 }` has { $2 } field(s), but this pattern has { $3 }
+hir-mismatched-pattern = mismatched pattern type, got `{ $0 }`
+hir-unknown-attr = unknown attribute `#[{ $0 }]`
+hir-known-attrs = the known attributes are { $0 }
+hir-attr-has-args = attribute `#[{ $0 }]` does not take any arguments
+hir-attr-wrong-target = attribute `#[{ $0 }]` cannot be applied to { $1 }
+hir-attr-wrong-target-note = `#[{ $0 }]` can only be applied to { $1 }
+hir-duplicate-attr = duplicate attribute `#[{ $0 }]`
+hir-duplicate-attr-label = first applied here
+hir-lint-attr-no-args = attribute `#[{ $0 }]` expects a list of lints
+hir-lint-attr-example = for example, `#[{ $0 }({ $1 })]`
+hir-lint-attr-not-name = expected the name of a lint
+hir-unknown-lint = unknown lint `{ $0 }`
+hir-known-lints = the known lints are { $0 }
+hir-infer-local = cannot infer the type of `{ $0 }`
+hir-infer-local-help = consider giving it a type, e.g. `local { $0 } : int`
+hir-infer-empty-array = cannot infer the type of an empty array
+hir-incompatible-elements = array elements must all have the same type
+hir-first-element-has-type = first element has type `{ $0 }`
+
+## Constant evaluation
+
+consteval-evaluating-const = while evaluating the constant `{ $0 }`
+consteval-in-call = in this call to `{ $0 }`
+consteval-repeated-call = the call to `{ $0 }` is repeated { $1 } times
+consteval-not-const = this expression cannot be evaluated at compile time
+consteval-not-const-note = constants can only use numbers, booleans, operators, locals, `if`, loops, and calls to functions which only use these
+consteval-cycle = the value of the constant `{ $0 }` depends on itself
+consteval-too-many-steps = constant evaluation took too long
+consteval-too-many-steps-note = evaluation is limited to { $0 } steps, is there an infinite loop?
+consteval-extern-call = cannot call the extern function `{ $0 }` at compile time
+consteval-extern-call-note = extern functions are defined outside of Cosy, so their results are only known at runtime
+consteval-too-deep = reached the maximum call depth of { $0 } while evaluating a constant
+consteval-assert-failed = assertion failed during constant evaluation
+consteval-divide-by-zero = attempt to divide `{ $0 }` by zero
+consteval-remainder-by-zero = attempt to calculate the remainder of `{ $0 }` with a divisor of zero
+consteval-overflow = attempt to compute `{ $0 }`, which would overflow `{ $1 }`
+consteval-valid-range = the valid range for `{ $0 }` is `{ $1 }..={ $2 }`
+consteval-rational-too-large = the exact result of this `{ $0 }` is too large to store
+consteval-rational-limit = `rational` constants are limited to { $0 } bits of precision
+
+## Lowering to Cosy ASM
+
+casm-unevaluated-const = constant was used without being evaluated
+casm-local-before-definition = local variable was used before its definition
+casm-invalid-assign-target = invalid assignment target
+casm-break-outside-loop = `break` outside of a loop
+casm-continue-outside-loop = `continue` outside of a loop
+casm-unreachable-code = unreachable code
+casm-unreachable-code-label = any code following this expression is unreachable
+
+## Emitting LLVM
+
+llvm-create-target-failed = failed to create a target machine for the host
+llvm-invalid-ir = generated invalid LLVM IR
+llvm-write-bitcode-failed = failed to write LLVM bitcode to path { $0 }
+llvm-write-object-failed = failed to write object file to path { $0 }
+
+## Command line
+
+cli-unknown-language = unknown language `{ $0 }`, using `{ $1 }` instead
+cli-known-languages = the supported languages are { $0 }
+cli-open-file-failed = failed to open file `{ $0 }`
+cli-debug-token-name = token name: { $0 }
+cli-debug-span = span: { $0 }
+cli-debug-end-of-line = this token marks the end of a line
+cli-debug-full-span = full span
+cli-debug-starts-here = starts here
+cli-debug-ends-here = ends here
+cli-debug-eof-note = end-of-file tokens aren't rendered
+cli-invalid-error-code = `{ $0 }` is not a valid error code
+cli-invalid-error-code-note = error codes are written as an `E` followed by four digits, e.g. `E0019`
+cli-fix-failed = failed to apply fixes to the package
+cli-error-output-failed = failed to write diagnostics to `{ $0 }`
+cli-error-output-failed-note = the diagnostics were written to stderr instead
+cli-run-harness-failed = failed to run test harness `{ $0 }`
+cli-ice-panicked-while = the compiler panicked while { $0 }
+cli-ice-panicked = the compiler panicked
+cli-ice-panicked-at = panicked at { $0 }: { $1 }
+cli-ice-panicked-message = panicked: { $0 }
+cli-ice-report-written = a bug report was written to `{ $0 }`, please attach it to the issue
+cli-ice-report-failed = failed to write a bug report to `{ $0 }`: { $1 }
@@ -0,0 +1,226 @@
+// The Cosy runtime library, which is compiled and linked into every Cosy
+// program. These functions are declared as extern functions by the prelude,
+// or called directly by the generated code.
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+// Every heap value is preceded by this header. Its size must match
+// `HEAP_HEADER_SIZE` in the compiler.
+struct cosy_header {
+    // The number of references to the value, or a negative number if the
+    // value is static and should never be freed, e.g. a string literal.
+    int64_t rc;
+    // Where the value was allocated, or `NULL` if it isn't being tracked.
+    // Tracked values are stored in a list so leaks can be reported at exit.
+    const char *site;
+    struct cosy_header *prev;
+    struct cosy_header *next;
+};
+
+_Static_assert(sizeof(struct cosy_header) == 32, "unexpected header size");
+
+static struct cosy_header *cosy_tracked = NULL;
+static bool cosy_exiting = false;
+
+// A source location, stored in the location table generated by the compiler.
+// Locations are referred to by their index into the table.
+struct cosy_location {
+    const char *path;
+    uint32_t line;
+    uint32_t column;
+};
+
+extern const struct cosy_location cosy_locations[];
+
+// The site passed by the compiler when a location isn't known. Must match
+// `NO_SITE` in the compiler.
+#define COSY_NO_SITE UINT32_MAX
+
+// The exit code used when a program panics.
+#define COSY_PANIC_EXIT_CODE 101
+
+// The stack frame of a Cosy function, which is only tracked by debug builds.
+// Must match the frame type generated by the compiler.
+struct cosy_frame {
+    struct cosy_frame *parent;
+    const char *name;
+    // The location of the last call made by this function.
+    uint32_t site;
+};
+
+// The innermost stack frame, or `NULL` if there isn't one.
+struct cosy_frame *cosy_stack = NULL;
+
+static void cosy_print_site(uint32_t site) {
+    const struct cosy_location *loc = &cosy_locations[site];
+    fprintf(stderr, "%s:%u:%u", loc->path, loc->line, loc->column);
+}
+
+// Reports any tracked values which were never freed, grouped by where they
+// were allocated. Skipped if the program exited early, since any values still
+// in use are expected to be leaked.
+static void cosy_report_leaks(void) {
+    if (cosy_exiting || cosy_tracked == NULL) {
+        return;
+    }
+    // values are listed from newest to oldest, so walk backwards to report
+    // them in the order they were allocated
+    size_t total = 0;
+    struct cosy_header *oldest = cosy_tracked;
+    for (struct cosy_header *h = cosy_tracked; h != NULL; h = h->next) {
+        total += 1;
+        oldest = h;
+    }
+    fflush(stdout);
+    fprintf(stderr, "warning: %zu value%s leaked\n", total, total == 1 ? " was" : "s were");
+    for (struct cosy_header *h = oldest; h != NULL; h = h->prev) {
+        bool seen = false;
+        for (struct cosy_header *g = oldest; g != h; g = g->prev) {
+            if (strcmp(g->site, h->site) == 0) {
+                seen = true;
+                break;
+            }
+        }
+        if (seen) {
+            continue;
+        }
+        size_t count = 0;
+        for (struct cosy_header *g = h; g != NULL; g = g->prev) {
+            count += strcmp(g->site, h->site) == 0;
+        }
+        fprintf(stderr, "  %zu allocated at %s\n", count, h->site);
+    }
+}
+
+// Allocates a heap value of `size` bytes with a reference count of 1. If
+// `site` isn't `NULL`, the value is tracked so it is reported if it leaks.
+void *cosy_alloc(size_t size, const char *site) {
+    struct cosy_header *h = malloc(sizeof(struct cosy_header) + size);
+    if (h == NULL) {
+        fflush(stdout);
+        fputs("error: out of memory\n", stderr);
+        abort();
+    }
+    h->rc = 1;
+    h->site = site;
+    h->prev = NULL;
+    h->next = NULL;
+    if (site != NULL) {
+        static bool registered = false;
+        if (!registered) {
+            atexit(cosy_report_leaks);
+            registered = true;
+        }
+        h->next = cosy_tracked;
+        if (cosy_tracked != NULL) {
+            cosy_tracked->prev = h;
+        }
+        cosy_tracked = h;
+    }
+    return h + 1;
+}
+
+void cosy_retain(void *ptr) {
+    if (ptr == NULL) {
+        return;
+    }
+    struct cosy_header *h = (struct cosy_header *) ptr - 1;
+    if (h->rc >= 0) {
+        h->rc += 1;
+    }
+}
+
+void cosy_release(void *ptr) {
+    if (ptr == NULL) {
+        return;
+    }
+    struct cosy_header *h = (struct cosy_header *) ptr - 1;
+    if (h->rc < 0 || --h->rc > 0) {
+        return;
+    }
+    if (h->site != NULL) {
+        if (h->prev != NULL) {
+            h->prev->next = h->next;
+        } else {
+            cosy_tracked = h->next;
+        }
+        if (h->next != NULL) {
+            h->next->prev = h->prev;
+        }
+    }
+    free(h);
+}
+
+char *cosy_str_concat(const char *a, const char *b, const char *site) {
+    size_t a_len = strlen(a);
+    size_t b_len = strlen(b);
+    char *s = cosy_alloc(a_len + b_len + 1, site);
+    memcpy(s, a, a_len);
+    memcpy(s + a_len, b, b_len + 1);
+    return s;
+}
+
+void cosy_print_str(const char *s) {
+    fputs(s, stdout);
+}
+
+void cosy_print_int(int64_t n) {
+    printf("%lld", (long long) n);
+}
+
+void cosy_print_bool(bool b) {
+    fputs(b ? "true" : "false", stdout);
+}
+
+void cosy_exit(int64_t code) {
+    cosy_exiting = true;
+    exit((int) code);
+}
+
+// Reports a runtime error at the location `site`, followed by a backtrace of
+// the Cosy functions on the call stack, and then exits the program.
+void cosy_panic(uint32_t site, const char *format, ...) {
+    fflush(stdout);
+    fputs("panic", stderr);
+    if (site != COSY_NO_SITE) {
+        fputs(" at ", stderr);
+        cosy_print_site(site);
+    }
+    fputs(": ", stderr);
+    va_list args;
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputc('\n', stderr);
+    if (cosy_stack == NULL) {
+        fputs("note: stack backtraces are only available in debug builds\n", stderr);
+    } else {
+        fputs("stack backtrace:\n", stderr);
+        int depth = 0;
+        for (struct cosy_frame *frame = cosy_stack; frame != NULL; frame = frame->parent) {
+            // the innermost frame is at the panic, and every other frame is
+            // at the call to the next frame
+            uint32_t frame_site = depth == 0 && site != COSY_NO_SITE ? site : frame->site;
+            fprintf(stderr, "  %d: %s", depth, frame->name);
+            if (frame_site != COSY_NO_SITE) {
+                fputs(" at ", stderr);
+                cosy_print_site(frame_site);
+            }
+            fputc('\n', stderr);
+            depth += 1;
+        }
+    }
+    // values still in use are expected to be leaked
+    cosy_exiting = true;
+    exit(COSY_PANIC_EXIT_CODE);
+}
+
+void cosy_abort(const char *message) {
+    cosy_panic(COSY_NO_SITE, "%s", message);
+}
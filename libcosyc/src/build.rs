@@ -1,12 +1,120 @@
 use std::path::{ Path, PathBuf };
+use std::process::Command;
+use std::sync::Arc;
+use std::cell::Cell;
 use std::fs;
 
-use crate::src::{ SourceMap, LoadFileResult, GetFileResult };
-use crate::error::{ Diagnostic, IssueManager };
+use crate::src::{ SourceMap, SourceFile, Message, LoadFileResult, GetFileResult };
+use crate::error::{ Diagnostic, IssueManager, ErrorCode };
 use crate::ir::{ ast, hir, casm };
 
+/// The source code of the prelude, which is implicitly included in every
+/// module.
+const PRELUDE_SRC : &str = include_str!("../runtime/prelude.cy");
+
+/// The source code of the runtime library, which is linked into every
+/// program.
+const RUNTIME_SRC : &str = include_str!("../runtime/runtime.c");
+
+/// A phase of compilation. The phase which is currently running is tracked,
+/// so it can be reported if the compiler crashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Loading,
+    Parsing,
+    LoweringHir,
+    LoweringCasm,
+    EmittingLlvm,
+    Linking,
+}
+
+impl Phase {
+    /// Returns a description of this phase, e.g. "parsing".
+    pub fn description(&self) -> Message {
+        match self {
+            Phase::Loading => msg!("build-phase-loading"),
+            Phase::Parsing => msg!("build-phase-parsing"),
+            Phase::LoweringHir => msg!("build-phase-lowering-hir"),
+            Phase::LoweringCasm => msg!("build-phase-lowering-casm"),
+            Phase::EmittingLlvm => msg!("build-phase-emitting-llvm"),
+            Phase::Linking => msg!("build-phase-linking"),
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_PHASE : Cell<Option<Phase>> = const { Cell::new(None) };
+}
+
+/// Returns the phase of compilation currently running on this thread, if
+/// any.
+pub fn current_phase() -> Option<Phase> {
+    CURRENT_PHASE.get()
+}
+
+/// Marks a phase of compilation as running, until the guard is dropped.
+struct PhaseGuard {
+    prev : Option<Phase>,
+}
+
+impl PhaseGuard {
+    fn enter(phase : Phase) -> PhaseGuard {
+        PhaseGuard { prev : CURRENT_PHASE.replace(Some(phase)) }
+    }
+}
+
+impl Drop for PhaseGuard {
+    fn drop(&mut self) {
+        CURRENT_PHASE.set(self.prev);
+    }
+}
+
+/// Writes `src` to the file at `path`, unless the file already contains it.
+fn write_if_changed(path : &Path, src : &str) -> std::io::Result<bool> {
+    if fs::read_to_string(path).is_ok_and(|old| old == src) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, src)?;
+    Ok(true)
+}
+
+/// Writes the prelude to the cache directory so it can be loaded like any
+/// other source file, and so errors can point into it.
+///
+/// Returns the prelude, and whether it changed since the last build.
+fn load_prelude(
+    files : &mut SourceMap,
+    issues : &mut IssueManager,
+    cache_dir : &Path,
+) -> Option<(Arc<SourceFile>, bool)> {
+    let path = cache_dir.join("prelude.cy");
+    let result = write_if_changed(&path, PRELUDE_SRC).and_then(|_| {
+        match files.load_file_if_new_or_modified(&path) {
+            LoadFileResult::Ok(file) => Ok((file, true)),
+            LoadFileResult::OkUnchanged(_) => Ok((files.load_file(&path)?, false)),
+            LoadFileResult::ErrIo(err) => Err(err),
+        }
+    });
+    match result {
+        Ok(ok) => Some(ok),
+        Err(err) => {
+            Diagnostic::from(err)
+                .code(ErrorCode::E0002)
+                .message(msg!("build-write-prelude-failed", [
+                    path.display().into()
+                ]))
+                .report(issues);
+            None
+        },
+    }
+}
+
 /// Parses a module into its HIR representation, inferring the types of
-/// all variables as best it can at this stage.
+/// all variables as best it can at this stage. The declarations of the
+/// prelude are included in the module.
 ///
 /// If the file already exists and was unmodified, then the cached version of
 /// the HIR will be returned if it exists.
@@ -18,12 +126,14 @@ pub fn build_module(
     cache_dir : &Path,
     module_path : &Path,
 ) -> Option<hir::Module> {
+    let _phase = PhaseGuard::enter(Phase::Loading);
+    let (prelude, prelude_changed) = load_prelude(files, issues, cache_dir)?;
     let result = match files.load_file_if_new_or_modified(module_path) {
         LoadFileResult::Ok(ok) => Ok(ok),
         LoadFileResult::OkUnchanged(file_id) => {
-            // load from cache
+            // load from cache, unless it was built using an old prelude
             let cache_path = cache_dir.join(format!("{}.bin", file_id));
-            if let Ok(mut file) = fs::File::open(cache_path) {
+            if !prelude_changed && let Ok(mut file) = fs::File::open(cache_path) {
                 let config = bincode::config::standard();
                 match bincode::decode_from_std_read(&mut file, config) {
                     Ok(ok) => return Some(ok),
@@ -42,15 +152,21 @@ pub fn build_module(
         Ok(ok) => ok,
         Err(err) => {
             Diagnostic::from(err)
-                .message(("failed to open module at path `{}`", [
+                .code(ErrorCode::E0001)
+                .message(msg!("build-open-module-failed", [
                     module_path.display().into(),
                 ]))
                 .report(issues);
             return None;
         },
     };
-    let ast = ast::parse::from_file(issues, file.as_ref());
-    let hir = hir::lower::from_ast(issues, &ast);
+    let reported = issues.errors.len();
+    let hir = lower_module(issues, &prelude, &file);
+    if issues.has_errors() || issues.errors.len() > reported {
+        // don't cache modules with errors or warnings, otherwise they won't
+        // be reported again on the next build
+        return Some(hir);
+    }
     // write to cache
     let cache_path = cache_dir.join(format!("{}.bin", file.id));
     let _ = fs::create_dir_all(cache_dir);
@@ -61,15 +177,106 @@ pub fn build_module(
     Some(hir)
 }
 
+/// Parses a module into its HIR representation like `build_module`, except
+/// the cached HIR is never used or updated. This means every diagnostic for
+/// the module is reported, even if it hasn't changed since the last build.
+///
+/// Reports any errors to `issues`.
+pub fn check_module(
+    files : &mut SourceMap,
+    issues : &mut IssueManager,
+    cache_dir : &Path,
+    module_path : &Path,
+) -> Option<hir::Module> {
+    let _phase = PhaseGuard::enter(Phase::Loading);
+    let (prelude, _) = load_prelude(files, issues, cache_dir)?;
+    let file = match files.load_file(module_path) {
+        Ok(ok) => ok,
+        Err(err) => {
+            Diagnostic::from(err)
+                .code(ErrorCode::E0001)
+                .message(msg!("build-open-module-failed", [
+                    module_path.display().into(),
+                ]))
+                .report(issues);
+            return None;
+        },
+    };
+    Some(lower_module(issues, &prelude, &file))
+}
+
+fn lower_module(
+    issues : &mut IssueManager,
+    prelude : &SourceFile,
+    file : &SourceFile,
+) -> hir::Module {
+    let phase = PhaseGuard::enter(Phase::Parsing);
+    let prelude_ast = ast::parse::from_file(issues, prelude);
+    let ast = ast::parse::from_file(issues, file);
+    drop(phase);
+    let _phase = PhaseGuard::enter(Phase::LoweringHir);
+    hir::lower::from_ast_with_prelude(issues, Some(&prelude_ast), &ast)
+}
+
 /// Takes the HIR modules of a Cosy package, and uses them to compile the
 /// complete Cosy ASM for the package.
 ///
 /// Reports any errors to `issues`.
 pub fn build_package_casm(
-    _issues : &mut IssueManager,
-    _cache_dir : &Path,
+    issues : &mut IssueManager,
+    config : Config,
+    modules : &[hir::Module],
 ) -> Option<casm::Package> {
-    Some(casm::Package)
+    let _phase = PhaseGuard::enter(Phase::LoweringCasm);
+    let main = modules.iter()
+        .flat_map(|module| &module.items)
+        .find_map(|item| match &item.decl {
+            hir::Decl::Fn { name, attrs, params, ret, .. } if name.value == "main"
+                    && !hir::has_attr(attrs, hir::AttrKind::Test) => Some((name, params, ret)),
+            _ => None,
+        });
+    let Some((main_name, main_params, main_ret)) = main else {
+        Diagnostic::error()
+            .code(ErrorCode::E0006)
+            .message(msg!("build-no-main"))
+            .note(msg!("build-no-main-help"))
+            .report(issues);
+        return None;
+    };
+    if let Some(param) = main_params.first() {
+        Diagnostic::error()
+            .code(ErrorCode::E0007)
+            .message(msg!("build-main-has-params"))
+            .label(param.name.loc)
+            .label_other(main_name.loc)
+            .report(issues);
+        return None;
+    }
+    if !matches!(main_ret, hir::Type::Int | hir::Type::Unit | hir::Type::Never) {
+        Diagnostic::error()
+            .code(ErrorCode::E0008)
+            .message(msg!("build-main-bad-return", [
+                main_ret.to_string().into()
+            ]))
+            .label(main_name.loc)
+            .report(issues);
+        return None;
+    }
+    Some(casm::lower::from_hir(issues, config, false, modules))
+}
+
+/// Takes the HIR modules of a Cosy package, and uses them to compile the
+/// Cosy ASM of a test harness which can run any test in the package. Unlike
+/// `build_package_casm`, the package doesn't need a `main` function.
+///
+/// Reports any errors to `issues`.
+pub fn build_test_casm(
+    issues : &mut IssueManager,
+    config : Config,
+    modules : &[hir::Module],
+) -> casm::Package {
+    let _phase = PhaseGuard::enter(Phase::LoweringCasm);
+    casm::lower::from_hir(issues, config, true, modules)
 }
 
 /// Takes the Cosy ASM of a package, and uses it to emit LLVM bitcode. Both
@@ -79,20 +286,124 @@ pub fn build_package_casm(
 ///
 /// Reports any errors to `issues`.
 pub fn build_package_llvm(
+    files : &SourceMap,
     issues : &mut IssueManager,
+    config : Config,
     cache_dir : &Path,
-    _casm : &casm::Package,
+    name : &str,
+    casm : &casm::Package,
 ) -> Option<PathBuf> {
-    None
+    let _phase = PhaseGuard::enter(Phase::EmittingLlvm);
+    let bc_path = cache_dir.join(format!("{}.bc", name));
+    let o_path = cache_dir.join(format!("{}.o", name));
+    if let Err(err) = fs::create_dir_all(cache_dir) {
+        Diagnostic::from(err)
+            .code(ErrorCode::E0002)
+            .message(msg!("build-create-cache-failed", [
+                cache_dir.display().into()
+            ]))
+            .report(issues);
+        return None;
+    }
+    if !casm::emit_llvm(issues, files, config, casm, &bc_path, &o_path) {
+        return None;
+    }
+    Some(o_path)
 }
 
-/// Uses `clang` to link `.o` files into an executable file.
+/// Uses `clang` to compile the runtime library into an object file in
+/// `cache_dir`, returning the path of the object file.
+///
+/// The runtime is only recompiled if it changed since the last build.
+fn build_runtime(issues : &mut IssueManager, cache_dir : &Path) -> Option<PathBuf> {
+    let c_path = cache_dir.join("cosy_runtime.c");
+    let o_path = cache_dir.join("cosy_runtime.o");
+    let changed = match write_if_changed(&c_path, RUNTIME_SRC) {
+        Ok(changed) => changed,
+        Err(err) => {
+            Diagnostic::from(err)
+                .code(ErrorCode::E0002)
+                .message(msg!("build-write-runtime-failed", [
+                    c_path.display().into()
+                ]))
+                .report(issues);
+            return None;
+        },
+    };
+    if !changed && o_path.exists() {
+        return Some(o_path);
+    }
+    let output = Command::new("clang")
+        .arg("-c")
+        .arg("-O2")
+        .arg(&c_path)
+        .arg("-o")
+        .arg(&o_path)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => Some(o_path),
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Diagnostic::bug()
+                .message(msg!("build-compile-runtime-failed"))
+                .note(Message::verbatim(stderr.trim()))
+                .report(issues);
+            None
+        },
+        Err(err) => {
+            Diagnostic::from(err)
+                .code(ErrorCode::E0003)
+                .message(msg!("build-invoke-clang-failed"))
+                .report(issues);
+            None
+        },
+    }
+}
+
+/// Uses `clang` to link `.o` files into an executable file, along with the
+/// runtime library.
+///
+/// `o_files` may also contain static libraries. Each name in `libs` is linked
+/// as a system library, e.g. `m` links `libm`.
 pub fn link_program(
-    _issues : &mut IssueManager,
-    _o_files : &[PathBuf],
-    _out_path : &Path,
+    issues : &mut IssueManager,
+    o_files : &[PathBuf],
+    libs : &[String],
+    out_path : &Path,
 ) -> bool {
-    false
+    let _phase = PhaseGuard::enter(Phase::Linking);
+    let cache_dir = out_path.parent().unwrap_or(Path::new("."));
+    let Some(runtime_path) = build_runtime(issues, cache_dir) else {
+        return false;
+    };
+    let output = Command::new("clang")
+        .args(o_files)
+        .arg(runtime_path)
+        .args(libs.iter().map(|lib| format!("-l{}", lib)))
+        .arg("-o")
+        .arg(out_path)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => true,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            Diagnostic::error()
+                .code(ErrorCode::E0004)
+                .message(msg!("build-link-failed", [
+                    out_path.display().into()
+                ]))
+                .note(Message::verbatim(stderr.trim()))
+                .report(issues);
+            false
+        },
+        Err(err) => {
+            Diagnostic::from(err)
+                .code(ErrorCode::E0003)
+                .message(msg!("build-invoke-clang-failed"))
+                .report(issues);
+            false
+        },
+    }
 }
 
 /// Takes a path, returning the name of the package to compile, and
@@ -101,11 +412,13 @@ pub fn find_package_root(
     issues : &mut IssueManager,
     path : &Path
 ) -> Option<(String, PathBuf)> {
+    let _phase = PhaseGuard::enter(Phase::Loading);
     let is_dir = match fs::metadata(path) {
         Ok(meta) => meta.is_dir(),
         Err(err) => {
             Diagnostic::from(err)
-                .message(("failed to find package at path `{}`", [
+                .code(ErrorCode::E0001)
+                .message(msg!("build-find-package-failed", [
                     path.display().into()
                 ]))
                 .report(issues);
@@ -128,7 +441,8 @@ pub fn find_package_root(
         name.to_owned()
     } else {
         Diagnostic::error()
-            .message(("invalid package name {} is not a valid UTF-8 name", [
+            .code(ErrorCode::E0005)
+            .message(msg!("build-package-name-not-utf8", [
                 format!("{:?}", os_name).into()
             ]))
             .report(issues);
@@ -1,6 +1,14 @@
 pub mod cli;
+pub mod json;
+pub mod sarif;
+pub mod fix;
+pub mod lint;
+mod codes;
+
+pub use codes::ErrorCode;
 
 use std::{ fmt, io };
+use std::collections::{ HashMap, HashSet };
 
 use crate::src::{ Location, Message };
 use crate::pretty::Colour;
@@ -10,9 +18,120 @@ use crate::pretty::Colour;
 pub struct IssueManager {
     /// An unordered list of diagnostic info.
     pub errors : Vec<Diagnostic>,
+    /// The maximum number of fatal errors to keep. Any more are counted by
+    /// `hidden_errors`, but otherwise discarded. Compiler bugs are always
+    /// kept.
+    pub max_errors : Option<usize>,
+    /// The number of fatal errors discarded because of `max_errors`.
+    pub hidden_errors : usize,
+    /// The lint levels set on the command line.
+    lint_args : HashMap<String, lint::LintLevel>,
+    /// The lint levels set by the attributes of the code currently being
+    /// checked, from outermost to innermost.
+    lint_scopes : Vec<Vec<lint::LintAttr>>,
+    /// The lint levels which have already been explained by a note, so each
+    /// explanation is only displayed once.
+    explained_levels : HashSet<(&'static str, &'static str, lint::LevelSource)>,
 }
 
 impl IssueManager {
+    /// Sets the level of a lint or lint group for the whole package, as if by
+    /// `-A`, `-W`, or `-D` on the command line. Reports a warning if the lint
+    /// doesn't exist.
+    pub fn set_lint_level(&mut self, name : &str, level : lint::LintLevel) {
+        if !lint::is_known(name) {
+            Diagnostic::lint(&lint::UNKNOWN_LINTS)
+                .code(ErrorCode::E0084)
+                .message(msg!("lint-unknown", [name.to_string().into()]))
+                .note(msg!("lint-level-from-command-line", [
+                    level.flag().into(), name.to_string().into()
+                ]))
+                .note(msg!("lint-known-lints", [lint::known_names().into()]))
+                .report(self);
+            return;
+        }
+        self.lint_args.insert(name.to_string(), level);
+    }
+
+    /// Overrides the levels of lints until the matching `pop_lint_scope`.
+    /// Used when checking code annotated with `#[allow(..)]` and friends.
+    pub fn push_lint_scope(&mut self, attrs : Vec<lint::LintAttr>) {
+        self.lint_scopes.push(attrs);
+    }
+
+    /// Restores the lint levels from before the last `push_lint_scope`.
+    pub fn pop_lint_scope(&mut self) {
+        self.lint_scopes.pop();
+    }
+
+    /// Finds the configured level of a lint or lint group. Attributes take
+    /// priority over the command line, and inner attributes take priority
+    /// over outer ones.
+    fn find_lint_level(&self, name : &str) -> Option<(lint::LintLevel, lint::LevelSource)> {
+        for scope in self.lint_scopes.iter().rev() {
+            if let Some(attr) = scope.iter().rev().find(|attr| attr.name == name) {
+                return Some((attr.level, lint::LevelSource::Attr(attr.loc)));
+            }
+        }
+        let level = self.lint_args.get(name)?;
+        Some((*level, lint::LevelSource::CommandLine))
+    }
+
+    /// Applies the configured lint levels to a warning, returning `None` if
+    /// it should be ignored. Warnings which are denied become errors.
+    ///
+    /// The first time a lint is reported at a level, this also returns
+    /// messages which explain where the level came from.
+    fn apply_lint_level(
+        &mut self,
+        mut diag : Diagnostic,
+    ) -> Option<(Diagnostic, Vec<Diagnostic>)> {
+        let (mut name, (mut level, mut source)) = match diag.lint {
+            Some(lint) => (lint.name, self.find_lint_level(lint.name)
+                .unwrap_or((lint.default_level, lint::LevelSource::Default))),
+            None => (lint::WARNINGS, (lint::LintLevel::Warn, lint::LevelSource::Default)),
+        };
+        if level == lint::LintLevel::Warn
+                && let Some(group) = self.find_lint_level(lint::WARNINGS) {
+            name = lint::WARNINGS;
+            (level, source) = group;
+        }
+        match level {
+            lint::LintLevel::Allow => return None,
+            lint::LintLevel::Warn => (),
+            lint::LintLevel::Deny => diag.severity = Severity::Fatal,
+        }
+        let lint_name = diag.lint.map_or(lint::WARNINGS, |lint| lint.name);
+        if !self.explained_levels.insert((lint_name, name, source)) {
+            return Some((diag, Vec::new()));
+        }
+        let mut explanations = Vec::new();
+        match source {
+            lint::LevelSource::Default => if diag.lint.is_some() {
+                explanations.push(Diagnostic::info()
+                    .message(msg!("lint-level-default", [
+                        level.name().into(), name.into()
+                    ])));
+            },
+            lint::LevelSource::CommandLine => {
+                explanations.push(Diagnostic::info()
+                    .message(msg!("lint-level-from-command-line", [
+                        level.flag().into(), name.into()
+                    ])));
+                if level == lint::LintLevel::Deny {
+                    explanations.push(Diagnostic::help()
+                        .message(msg!("lint-level-override-help", [
+                            level.flag().into(), name.into(), lint_name.into()
+                        ])));
+                }
+            },
+            lint::LevelSource::Attr(loc) => {
+                diag = diag.label_other((loc, msg!("lint-level-set-here")));
+            },
+        }
+        Some((diag, explanations))
+    }
+
     /// Returns whether any messages occurred, regardless of their error status.
     pub fn has_messages(&self) -> bool {
         !self.errors.is_empty()
@@ -20,6 +139,9 @@ impl IssueManager {
 
     /// Returns whether fatal errors occurred.
     pub fn has_errors(&self) -> bool {
+        if self.hidden_errors > 0 {
+            return true;
+        }
         for error in &self.errors {
             if matches!(error.severity, Severity::Fatal | Severity::Bug) {
                 return true;
@@ -30,24 +152,32 @@ impl IssueManager {
 
     /// Returns the statistics for the number of errors that occurred.
     pub fn error_stats(&self) -> IssueStats {
-        let mut max_severity = Severity::Warning;
-        let mut counts = [0, 0, 0, 0];
+        let mut max_severity = Severity::Info;
+        let mut counts = [0, 0, 0, 0, 0];
         for error in &self.errors {
             let severity = &error.severity;
             match &severity {
-                Severity::Warning => counts[1] += 1,
-                Severity::Fatal => counts[2] += 1,
-                Severity::Bug => counts[3] += 1,
+                Severity::Info => counts[0] += 1,
+                Severity::Help => counts[1] += 1,
+                Severity::Warning => counts[2] += 1,
+                Severity::Fatal => counts[3] += 1,
+                Severity::Bug => counts[4] += 1,
             }
             if *severity > max_severity {
                 max_severity = severity.clone();
             }
         }
+        if self.hidden_errors > 0 && max_severity < Severity::Fatal {
+            max_severity = Severity::Fatal;
+        }
         IssueStats {
             max_severity,
-            warnings : counts[1],
-            errors : counts[2],
-            bugs : counts[3],
+            infos : counts[0],
+            helps : counts[1],
+            warnings : counts[2],
+            errors : counts[3],
+            bugs : counts[4],
+            hidden_errors : self.hidden_errors,
         }
     }
 }
@@ -57,18 +187,26 @@ impl IssueManager {
 pub struct IssueStats {
     /// The max error class reached by the compiler.
     pub max_severity : Severity,
+    /// The number of informational messages encountered.
+    pub infos : usize,
+    /// The number of help messages encountered.
+    pub helps : usize,
     /// The number of warnings encountered.
     pub warnings : usize,
     /// The number of fatal errors encountered.
     pub errors : usize,
     /// The number of compiler bugs encountered.
     pub bugs : usize,
+    /// The number of fatal errors which weren't kept, because there were too
+    /// many.
+    pub hidden_errors : usize,
 }
 
 impl IssueStats {
-    /// Returns the total number of messages that occurred.
+    /// Returns the total number of messages that were kept, excluding any
+    /// hidden errors.
     pub fn total(&self) -> usize {
-        self.warnings + self.errors + self.bugs
+        self.infos + self.helps + self.warnings + self.errors + self.bugs
     }
 }
 
@@ -77,6 +215,10 @@ impl IssueStats {
 pub struct Diagnostic {
     /// The severity of this error.
     pub severity : Severity,
+    /// A stable identifier for this kind of error, if it has one.
+    pub code : Option<ErrorCode>,
+    /// The lint which decides the level of this warning, if it has one.
+    pub lint : Option<&'static lint::Lint>,
     /// A brief description of the error.
     pub message : Option<Message>,
     /// Used to discover the line and column numbers of code which directly
@@ -89,11 +231,14 @@ pub struct Diagnostic {
     /// message. These may describe general fixes, or other reasons why an
     /// error occurred (e.g. "known bug" or "unsupported").
     pub notes : Vec<Note>,
+    /// Edits to the source code which may fix the error, displayed after the
+    /// notes.
+    pub suggestions : Vec<Suggestion>,
 }
 
 impl From<io::Error> for Diagnostic {
     fn from(err : io::Error) -> Diagnostic {
-        Diagnostic::error().note(("{}", [err.into()]))
+        Diagnostic::error().note(Message::verbatim(err))
     }
 }
 
@@ -103,16 +248,25 @@ impl Diagnostic {
     pub fn new(severity : Severity) -> Diagnostic {
         let diag = Self { severity, ..Default::default() };
         match severity {
-            Severity::Bug => diag.note("\
-                likely caused by a bug in the compiler, please report the issue:\n\
-                https://github.com/katsaii/cosy-lang/issues"),
+            Severity::Bug => diag.note(msg!("diag-bug-report")),
             _ => diag,
         }
     }
 
+    /// Creates an empty informational message.
+    pub fn info() -> Diagnostic { Diagnostic::new(Severity::Info) }
+
+    /// Creates an empty help message.
+    pub fn help() -> Diagnostic { Diagnostic::new(Severity::Help) }
+
     /// Creates an empty warning message.
     pub fn warning() -> Diagnostic { Diagnostic::new(Severity::Warning) }
 
+    /// Creates an empty warning message, whose level is decided by `lint`.
+    pub fn lint(lint : &'static lint::Lint) -> Diagnostic {
+        Diagnostic { lint : Some(lint), ..Diagnostic::warning() }
+    }
+
     /// Creates an empty error message.
     pub fn error() -> Diagnostic { Diagnostic::new(Severity::Fatal) }
 
@@ -122,13 +276,19 @@ impl Diagnostic {
     /// Creates an bug message with a note indicating that a feature is
     /// not yet implemented.
     pub fn unimplemented(feature : &str) -> Diagnostic {
-        Self::bug().message(("'{}' is unimplemented", [feature.into()]))
+        Self::bug().message(msg!("diag-unimplemented", [feature.into()]))
     }
 
     /// Creates an bug message with a note indicating that unreachable
     /// code was reached when it shouldn't have been.
     pub fn unreachable() -> Diagnostic {
-        Self::bug().message("encountered an unreachable compiler state")
+        Self::bug().message(msg!("diag-unreachable"))
+    }
+
+    /// Assigns an error code to this error message.
+    pub fn code(mut self, code : ErrorCode) -> Diagnostic {
+        self.code = Some(code);
+        self
     }
 
     /// Inserts a new description for this error message.
@@ -161,9 +321,101 @@ impl Diagnostic {
         self
     }
 
+    /// Adds a suggestion to replace the code at `location` with `replacement`.
+    /// An empty location inserts the replacement instead.
+    pub fn suggest<M : Into<Message>>(
+        mut self,
+        location : Location,
+        replacement : &str,
+        applicability : Applicability,
+        caption : M,
+    ) -> Diagnostic {
+        self.suggestions.push(Suggestion {
+            location,
+            replacement : replacement.to_string(),
+            applicability,
+            caption : caption.into(),
+        });
+        self
+    }
+
     /// Consumes and reports this error message to the target issue manager.
+    ///
+    /// Warnings may be ignored or turned into errors, depending on the lint
+    /// levels configured in `issues`, and are followed by an info message
+    /// explaining the level the first time it applies. Diagnostics identical to one which was
+    /// already reported at the same location are ignored.
     pub fn report(self, issues : &mut IssueManager) {
-        issues.errors.push(self);
+        let (diag, explanations) = if self.severity == Severity::Warning {
+            let Some(result) = issues.apply_lint_level(self) else { return };
+            result
+        } else {
+            (self, Vec::new())
+        };
+        if issues.errors.iter().any(|other| other.is_duplicate_of(&diag)) {
+            return;
+        }
+        if diag.severity == Severity::Fatal && let Some(max) = issues.max_errors {
+            let errors = issues.errors.iter()
+                .filter(|other| other.severity == Severity::Fatal)
+                .count();
+            if errors >= max {
+                issues.hidden_errors += 1;
+                return;
+            }
+        }
+        issues.errors.push(diag);
+        issues.errors.extend(explanations);
+    }
+
+    /// Returns whether this diagnostic has the same severity, code, and
+    /// message as `other`, and labels the same locations.
+    fn is_duplicate_of(&self, other : &Diagnostic) -> bool {
+        let same_locations = |a : &[Label], b : &[Label]| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| a.location == b.location)
+        };
+        self.severity == other.severity
+            && self.code == other.code
+            && self.message == other.message
+            && same_locations(&self.primary_labels, &other.primary_labels)
+    }
+}
+
+/// An edit to the source code which may fix an error.
+pub struct Suggestion {
+    /// The code to replace.
+    pub location : Location,
+    /// The code to replace it with.
+    pub replacement : String,
+    /// How confident the compiler is that this edit is correct.
+    pub applicability : Applicability,
+    /// A description of the edit.
+    pub caption : Message,
+}
+
+/// How confident the compiler is that a suggestion is correct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended, so it can be
+    /// applied automatically by `cosy fix`.
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but it should be
+    /// reviewed before it is applied.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders, such as `...`, which need to be
+    /// filled in by the user.
+    HasPlaceholders,
+}
+
+impl Applicability {
+    /// Returns the string representation of this applicability.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+        }
     }
 }
 
@@ -202,6 +454,8 @@ impl<M : Into<Message>> From<M> for Note {
 /// Affects the highlighting colour of the error in the output window.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Severity {
+    Info,
+    Help,
     Warning,
     Fatal,
     Bug,
@@ -221,6 +475,8 @@ impl Severity {
     /// Returns the string representation of this severity.
     pub fn as_str(&self) -> &'static str {
         match self {
+            Severity::Info => "info",
+            Severity::Help => "help",
             Severity::Warning => "warning",
             Severity::Fatal => "error",
             Severity::Bug => "bug",
@@ -230,6 +486,8 @@ impl Severity {
     /// Returns the style of this severity.
     pub fn as_colour(&self) -> Colour {
         match self {
+            Severity::Info => Colour::BrightGreen,
+            Severity::Help => Colour::BrightCyan,
             Severity::Warning => Colour::Yellow,
             Severity::Fatal => Colour::BrightRed,
             Severity::Bug => Colour::Magenta,
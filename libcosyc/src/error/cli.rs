@@ -1,12 +1,16 @@
 use std::{ io, cmp };
+use std::collections::BTreeSet;
 
-use crate::src::{ SourceMap, Message, Location, GetFileResult };
-use crate::error::{ Diagnostic, IssueManager, Label, Note };
+use crate::src::{ SourceMap, SourceFile, Message, Location, GetFileResult };
+use crate::error::{ Diagnostic, IssueManager, Note, Severity, Suggestion };
 use crate::pretty::{ PrettyPrinter, Colour, Decoration, Style };
 
-fn safe_sub(lhs : usize, rhs : usize) -> usize {
-    if lhs < rhs { 0 } else { lhs - rhs }
-}
+/// The number of columns a tab character is expanded to.
+const TAB_WIDTH : usize = 4;
+
+/// The number of lines shown at the start and end of a multi-line label. Any
+/// lines between them are elided.
+const MULTILINE_CONTEXT : usize = 2;
 
 /// Renders diagnostic information in a pretty format.
 pub fn write_errors<W : io::Write>(
@@ -33,14 +37,41 @@ impl<W : io::Write> RendererCtx<'_, '_, W> {
         }
         let stats = issues.error_stats();
         let stats_total = stats.total();
-        if stats_total > 0 {
+        if stats_total > 0 || stats.hidden_errors > 0 {
             let stats_style = stats.max_severity
                 .as_colour()
                 .decorated(Decoration::Bold);
             self.p.write_style(stats_style)?;
             self.p.write(stats.max_severity.as_str())?;
             self.p.clear_style()?;
-            self.p.write(&format!(": displayed {} message(s)\n", stats_total))?;
+            self.p.write(": ")?;
+            self.write_message(&msg!("render-displayed", [stats_total.into()]))?;
+            self.p.write("\n")?;
+        }
+        if stats.hidden_errors > 0 {
+            let message = if stats.hidden_errors == 1 {
+                msg!("render-error-hidden")
+            } else {
+                msg!("render-errors-hidden", [stats.hidden_errors.into()])
+            };
+            self.p.write_style(Colour::BrightGreen.decorated(Decoration::Bold))?;
+            self.p.write("note")?;
+            self.p.clear_style()?;
+            self.p.write(": ")?;
+            self.write_message(&message)?;
+            self.p.write("\n")?;
+        }
+        // only errors suggest `cosy explain`, warnings alone don't
+        let error_code = issues.errors.iter()
+            .filter(|diag| diag.severity >= Severity::Fatal)
+            .find_map(|diag| diag.code);
+        if let Some(code) = error_code {
+            self.p.write_style(Colour::BrightGreen.decorated(Decoration::Bold))?;
+            self.p.write("note")?;
+            self.p.clear_style()?;
+            self.p.write(": ")?;
+            self.write_message(&msg!("render-explain", [code.into()]))?;
+            self.p.write("\n")?;
         }
         Ok(())
     }
@@ -49,6 +80,9 @@ impl<W : io::Write> RendererCtx<'_, '_, W> {
         let diag_style = diag.severity.as_colour().decorated(Decoration::Bold);
         self.p.write_style(diag_style)?;
         self.p.write(diag.severity.as_str())?;
+        if let Some(code) = diag.code {
+            self.p.write(&format!("[{}]", code))?;
+        }
         self.p.clear_style()?;
         self.p.write(": ")?;
         // render message
@@ -60,89 +94,248 @@ impl<W : io::Write> RendererCtx<'_, '_, W> {
             self.p.indent_pop();
         }
         // render labels
-        for label in &diag.primary_labels {
-            self.write_label(label, diag_style, "^")?;
-        }
-        for label in &diag.secondary_labels {
-            let snd_style = Colour::BrightBlue.decorated(Decoration::Bold);
-            self.write_label(label, snd_style, "-")?;
-        }
+        self.write_labels(diag, diag_style)?;
         // render notes
         for note in &diag.notes {
             self.write_note(note)?;
         }
+        // render suggestions
+        for suggestion in &diag.suggestions {
+            self.write_suggestion(suggestion)?;
+        }
         self.p.write("\n\n")?;
         Ok(())
     }
 
-    fn write_label(
+    /// Renders every label of a diagnostic. Labels in the same file are
+    /// displayed together in a single snippet of source code, in the order
+    /// their files first appear.
+    fn write_labels(&mut self, diag : &Diagnostic, highlight : Style) -> io::Result<()> {
+        let snd_style = Colour::BrightBlue.decorated(Decoration::Bold);
+        let labels = diag.primary_labels.iter()
+            .map(|label| (label, highlight, '^'))
+            .chain(diag.secondary_labels.iter().map(|label| (label, snd_style, '-')))
+            .collect::<Vec<_>>();
+        let mut file_ids = Vec::new();
+        for (label, ..) in &labels {
+            if !file_ids.contains(&label.location.file_id) {
+                file_ids.push(label.location.file_id);
+            }
+        }
+        for file_id in file_ids {
+            let file = match self.files.get_existing_file(file_id) {
+                GetFileResult::Ok((_, file)) => file,
+                GetFileResult::ErrNotInManifest => continue,
+                GetFileResult::ErrIo(err) => return Err(err),
+            };
+            let snippet = labels.iter()
+                .filter(|(label, ..)| label.location.file_id == file_id)
+                .map(|(label, style, underline)| {
+                    let (start, end) = file.find_line_and_col_span(&label.location.span);
+                    SnippetLabel {
+                        caption : label.caption.as_ref(),
+                        style : *style,
+                        underline : *underline,
+                        start : (start.0, display_column(&file, start)),
+                        end : (end.0, display_column(&file, end)),
+                        depth : 0,
+                    }
+                })
+                .collect::<Vec<_>>();
+            let header = labels.iter()
+                .find(|(label, ..)| label.location.file_id == file_id)
+                .unwrap().0.location;
+            self.write_snippet(&file, &header, snippet)?;
+        }
+        Ok(())
+    }
+
+    fn write_snippet(
         &mut self,
-        label : &Label,
-        highlight : Style,
-        highlight_char : &'static str,
+        file : &SourceFile,
+        header : &Location,
+        mut labels : Vec<SnippetLabel>,
     ) -> io::Result<()> {
-        assert!(highlight_char.len() == 1);
-        let file = match self.files.get_existing_file(label.location.file_id) {
-            GetFileResult::Ok((_, file)) => file,
-            GetFileResult::ErrNotInManifest => return Ok(()),
-            GetFileResult::ErrIo(err) => return Err(err),
-        };
-        let file_src = &file.src;
-        let (start, end) = file.find_line_and_col_span(&label.location.span);
-        let start_line_n = &start.0.to_string();
-        let end_line_n = &end.0.to_string();
-        let margin = end_line_n.len();
+        let depths = assign_depths(&mut labels);
+        // leave at least 2 columns for multi-line spans
+        let gutter = 2 * cmp::max(1, depths);
+        // decide which lines to show, eliding the middle of long spans
+        let mut lines = BTreeSet::new();
+        for label in &labels {
+            let (first, last) = (label.start.0, label.end.0);
+            if last - first > 2 * MULTILINE_CONTEXT {
+                lines.extend(first..first + MULTILINE_CONTEXT);
+                lines.extend(last + 1 - MULTILINE_CONTEXT..=last);
+            } else {
+                lines.extend(first..=last);
+            }
+        }
+        // a gap of one line is shown, since eliding it wouldn't save any space
+        let gaps = lines.iter()
+            .zip(lines.iter().skip(1))
+            .filter(|(prev, next)| *next - *prev == 2)
+            .map(|(prev, _)| prev + 1)
+            .collect::<Vec<_>>();
+        lines.extend(gaps);
+        let margin = lines.last().map_or(1, |line_n| line_n.to_string().len());
         // render filename
         self.p.write("\n")?;
         self.p.skip(margin)?;
         self.p.write_style(Colour::BrightCyan)?;
         self.p.write(">>> ")?;
-        self.write_path(&label.location)?;
+        self.write_path(header)?;
         self.p.clear_style()?;
-        self.p.write("\n")?;
-        // render span
-        self.write_label_margin(margin, &start_line_n)?;
-        let start_line = file.find_line_span(start.0).unwrap();
-        self.p.skip(2)?; // leave 2 spaces for multi-line spans
-        self.p.write(start_line.slice(file_src))?;
-        self.p.write("\n")?;
-        if start.0 == end.0 {
-            // render single-line span
-            let offset = safe_sub(start.1, 1);
-            let length = cmp::max(1, safe_sub(end.1, start.1));
-            self.write_label_margin_end(margin)?;
-            self.p.skip(2 + offset)?;
-            self.p.write_style(highlight)?;
-            self.p.repeat(length, highlight_char)?;
-        } else {
-            let end_line = file.find_line_span(end.0).unwrap();
-            // render multi-line span
-            let offset_start = start.1;
-            let offset_end = safe_sub(end.1, 1);
-            self.write_label_margin(margin, ":")?; // start underline
-            self.p.write_style(highlight)?;
-            self.p.skip(1)?;
-            self.p.repeat(offset_start, "_")?;
-            self.p.repeat(1, highlight_char)?;
+        // render lines
+        let mut open = vec![false; labels.len()];
+        let mut prev_line_n = None;
+        for &line_n in &lines {
+            if let Some(prev_line_n) = prev_line_n && line_n > prev_line_n + 1 {
+                self.p.write("\n")?;
+                self.p.write_style(Colour::BrightCyan)?;
+                self.p.write("...")?;
+                self.p.clear_style()?;
+                let row = brackets(&labels, &open);
+                if !row.cells.is_empty() {
+                    self.p.skip(margin)?;
+                    self.write_row(&row)?;
+                }
+            }
+            prev_line_n = Some(line_n);
             self.p.write("\n")?;
-            self.write_label_margin(margin, &end_line_n)?; // end
-            self.p.write_style(highlight)?;
-            self.p.write("| ")?;
-            self.p.clear_style()?;
-            self.p.write(end_line.slice(file_src))?;
-            self.p.write("\n")?;
-            self.write_label_margin_end(margin)?; // end underline
-            self.p.write_style(highlight)?;
-            self.p.write("|")?;
-            self.p.repeat(offset_end, "_")?;
-            self.p.repeat(1, highlight_char)?;
+            self.write_label_margin(margin, &line_n.to_string())?;
+            let row = brackets(&labels, &open);
+            self.write_row(&row)?;
+            self.p.skip(gutter - row.cells.len())?;
+            if let Some(line) = file.find_line_span(line_n) {
+                let line_src = line.slice(&file.src).trim_end_matches('\r');
+                self.p.write(&line_src.replace('\t', &" ".repeat(TAB_WIDTH)))?;
+            }
+            // start multi-line spans
+            for i in 0..labels.len() {
+                let label = &labels[i];
+                if !label.is_multiline() || label.start.0 != line_n {
+                    continue;
+                }
+                let mut row = brackets(&labels, &open);
+                let col = gutter + label.start.1;
+                row.fill(2 * label.depth + 1, col, '_', label.style);
+                row.put(col, label.underline, label.style);
+                self.write_annotation_row(margin, &row)?;
+                open[i] = true;
+            }
+            self.write_line_labels(margin, gutter, &labels, &open, line_n)?;
+            // end multi-line spans, innermost first
+            let mut ending = (0..labels.len())
+                .filter(|&i| labels[i].is_multiline() && labels[i].end.0 == line_n)
+                .collect::<Vec<_>>();
+            ending.sort_by_key(|&i| cmp::Reverse(labels[i].depth));
+            for i in ending {
+                let label = &labels[i];
+                let mut row = brackets(&labels, &open);
+                let col = gutter + label.end.1.saturating_sub(1);
+                row.fill(2 * label.depth + 1, col, '_', label.style);
+                row.put(col, label.underline, label.style);
+                if let Some(caption) = label.caption {
+                    let caption = self.render_message(caption);
+                    row.put_str(col + 2, &caption, label.style);
+                }
+                self.write_annotation_row(margin, &row)?;
+                open[i] = false;
+            }
         }
-        // write caption
-        if let Some(caption) = &label.caption {
-            self.p.write(" ")?;
-            self.write_message(caption)?;
+        Ok(())
+    }
+
+    /// Renders the underlines of the single-line labels on a line, along with
+    /// their captions. The caption of the rightmost label is written after
+    /// its underline, and the others are written on their own rows below.
+    fn write_line_labels(
+        &mut self,
+        margin : usize,
+        gutter : usize,
+        labels : &[SnippetLabel],
+        open : &[bool],
+        line_n : usize,
+    ) -> io::Result<()> {
+        let line_labels = labels.iter()
+            .filter(|label| !label.is_multiline() && label.start.0 == line_n)
+            .collect::<Vec<_>>();
+        if line_labels.is_empty() {
+            return Ok(());
+        }
+        let mut row = brackets(labels, open);
+        // earlier labels are drawn last, so primary labels are drawn on top
+        for label in line_labels.iter().rev() {
+            for col in label.start.1..label.underline_end() {
+                row.put(gutter + col, label.underline, label.style);
+            }
+        }
+        let rightmost = line_labels.iter()
+            .enumerate()
+            .max_by_key(|(i, label)| (label.underline_end(), cmp::Reverse(*i)))
+            .map(|(i, _)| i)
+            .unwrap();
+        if let Some(caption) = line_labels[rightmost].caption {
+            let label = line_labels[rightmost];
+            let caption = self.render_message(caption);
+            row.put_str(gutter + label.underline_end() + 1, &caption, label.style);
+        }
+        let mut captioned = line_labels.iter()
+            .enumerate()
+            .filter(|(i, label)| *i != rightmost && label.caption.is_some())
+            .map(|(_, label)| label)
+            .collect::<Vec<_>>();
+        self.write_annotation_row(margin, &row)?;
+        if captioned.is_empty() {
+            return Ok(());
+        }
+        captioned.sort_by_key(|label| label.start.1);
+        let mut row = brackets(labels, open);
+        for label in &captioned {
+            row.put(gutter + label.start.1, '|', label.style);
+        }
+        self.write_annotation_row(margin, &row)?;
+        for (n, label) in captioned.iter().enumerate().rev() {
+            let mut row = brackets(labels, open);
+            for other in &captioned[..n] {
+                row.put(gutter + other.start.1, '|', other.style);
+            }
+            let caption = self.render_message(label.caption.unwrap());
+            row.put_str(gutter + label.start.1, &caption, label.style);
+            self.write_annotation_row(margin, &row)?;
+        }
+        Ok(())
+    }
+
+    fn write_annotation_row(&mut self, margin : usize, row : &Row) -> io::Result<()> {
+        self.p.write("\n")?;
+        self.write_label_margin_end(margin)?;
+        self.write_row(row)
+    }
+
+    fn write_row(&mut self, row : &Row) -> io::Result<()> {
+        let mut current = None;
+        for cell in &row.cells {
+            match cell {
+                Cell::Empty => {
+                    if current.take().is_some() {
+                        self.p.clear_style()?;
+                    }
+                    self.p.skip(1)?;
+                },
+                Cell::Char(ch, style) => {
+                    if current != Some(*style) {
+                        self.p.write_style(*style)?;
+                        current = Some(*style);
+                    }
+                    self.p.write(ch.encode_utf8(&mut [0; 4]))?;
+                },
+                Cell::Continuation => (),
+            }
+        }
+        if current.is_some() {
+            self.p.clear_style()?;
         }
-        self.p.clear_style()?;
         Ok(())
     }
 
@@ -178,6 +371,73 @@ impl<W : io::Write> RendererCtx<'_, '_, W> {
         Ok(())
     }
 
+    fn write_suggestion(&mut self, suggestion : &Suggestion) -> io::Result<()> {
+        let help_style = Colour::BrightCyan.decorated(Decoration::Bold);
+        self.p.write_style(help_style)?;
+        self.p.write("\nhelp")?;
+        self.p.clear_style()?;
+        self.p.write(": ")?;
+        self.write_message(&suggestion.caption)?;
+        let file = match self.files.get_existing_file(suggestion.location.file_id) {
+            GetFileResult::Ok((_, file)) => file,
+            GetFileResult::ErrNotInManifest => return Ok(()),
+            GetFileResult::ErrIo(err) => return Err(err),
+        };
+        // show every line touched by the edit, before and after
+        let span = &suggestion.location.span;
+        let (start, end) = file.find_line_and_col_span(span);
+        let first_line = file.find_line_span(start.0).unwrap();
+        let last_line = file.find_line_span(end.0).unwrap();
+        let before = &file.src[first_line.start..cmp::max(last_line.end, span.end)];
+        let after = format!("{}{}{}",
+            &file.src[first_line.start..span.start],
+            suggestion.replacement,
+            &file.src[span.end..cmp::max(last_line.end, span.end)],
+        );
+        let margin = (start.0 + cmp::max(before.lines().count(), after.lines().count()))
+            .to_string()
+            .len();
+        self.p.write("\n")?;
+        self.p.skip(margin)?;
+        self.p.write_style(Colour::BrightCyan)?;
+        self.p.write(">>> ")?;
+        self.write_path(&suggestion.location)?;
+        self.p.clear_style()?;
+        for (i, line) in before.lines().enumerate() {
+            self.write_diff_line(margin, start.0 + i, "-", Colour::BrightRed, line)?;
+        }
+        for (i, line) in after.lines().enumerate() {
+            self.write_diff_line(margin, start.0 + i, "+", Colour::BrightGreen, line)?;
+        }
+        Ok(())
+    }
+
+    fn write_diff_line(
+        &mut self,
+        margin : usize,
+        line_n : usize,
+        marker : &str,
+        colour : Colour,
+        line : &str,
+    ) -> io::Result<()> {
+        let line_n = line_n.to_string();
+        self.p.write("\n")?;
+        self.p.skip(margin - line_n.len())?;
+        self.p.write_style(Colour::BrightCyan)?;
+        self.p.write(&line_n)?;
+        self.p.clear_style()?;
+        self.p.write_style(colour)?;
+        self.p.write(" ")?;
+        self.p.write(marker)?;
+        self.p.write(" ")?;
+        // tabs are expanded the same way as in source snippets, so the
+        // lines line up
+        let line = line.trim_end_matches('\r');
+        self.p.write(&line.replace('\t', &" ".repeat(TAB_WIDTH)))?;
+        self.p.clear_style()?;
+        Ok(())
+    }
+
     fn write_path(&mut self, location : &Location) -> io::Result<()> {
         self.p.write(&location.show_path(self.files))
     }
@@ -187,4 +447,160 @@ impl<W : io::Write> RendererCtx<'_, '_, W> {
         message.write_to_string(self.files, &mut self.message_str);
         self.p.write(&self.message_str)
     }
+
+    fn render_message(&self, message : &Message) -> String {
+        let mut message_str = String::new();
+        message.write_to_string(self.files, &mut message_str);
+        message_str
+    }
+}
+
+/// A label which has been positioned within a snippet of source code.
+struct SnippetLabel<'a> {
+    caption : Option<&'a Message>,
+    style : Style,
+    underline : char,
+    /// The line and display column the label starts at.
+    start : (usize, usize),
+    /// The line and display column the label ends at, exclusive.
+    end : (usize, usize),
+    /// The column of the left margin a multi-line label's bracket is drawn
+    /// in, counting from the outside.
+    depth : usize,
+}
+
+impl SnippetLabel<'_> {
+    fn is_multiline(&self) -> bool {
+        self.start.0 != self.end.0
+    }
+
+    /// Returns the display column after the underline of a single-line label.
+    /// Empty labels are underlined with a single character.
+    fn underline_end(&self) -> usize {
+        cmp::max(self.start.1 + 1, self.end.1)
+    }
+}
+
+/// Assigns each multi-line label a column in the left margin, such that
+/// labels whose lines overlap have different columns. Returns the number of
+/// columns used.
+fn assign_depths(labels : &mut [SnippetLabel]) -> usize {
+    let mut order = (0..labels.len())
+        .filter(|&i| labels[i].is_multiline())
+        .collect::<Vec<_>>();
+    order.sort_by_key(|&i| (labels[i].start.0, cmp::Reverse(labels[i].end.0)));
+    // the last line used by each column
+    let mut depth_ends : Vec<usize> = Vec::new();
+    for i in order {
+        let (first, last) = (labels[i].start.0, labels[i].end.0);
+        labels[i].depth = match depth_ends.iter().position(|&end| end < first) {
+            Some(depth) => {
+                depth_ends[depth] = last;
+                depth
+            },
+            None => {
+                depth_ends.push(last);
+                depth_ends.len() - 1
+            },
+        };
+    }
+    depth_ends.len()
+}
+
+/// Returns a row containing the brackets of any open multi-line labels.
+fn brackets(labels : &[SnippetLabel], open : &[bool]) -> Row {
+    let mut row = Row::default();
+    for (label, _) in labels.iter().zip(open).filter(|(_, open)| **open) {
+        row.put(2 * label.depth, '|', label.style);
+    }
+    row
+}
+
+/// A cell of a row of annotations.
+#[derive(Clone, Copy)]
+enum Cell {
+    Empty,
+    Char(char, Style),
+    /// The second half of a wide character.
+    Continuation,
+}
+
+/// A row of annotations below a line of source code, indexed by display
+/// column.
+#[derive(Default)]
+struct Row {
+    cells : Vec<Cell>,
+}
+
+impl Row {
+    /// Writes a character at `col`, replacing anything already there.
+    fn put(&mut self, col : usize, ch : char, style : Style) {
+        let width = char_width(ch);
+        if self.cells.len() < col + width {
+            self.cells.resize(col + width, Cell::Empty);
+        }
+        self.cells[col] = Cell::Char(ch, style);
+        for cell in &mut self.cells[col + 1..col + width] {
+            *cell = Cell::Continuation;
+        }
+    }
+
+    /// Writes a string starting at `col`.
+    fn put_str(&mut self, mut col : usize, text : &str, style : Style) {
+        for ch in text.chars() {
+            let ch = if ch.is_whitespace() { ' ' } else { ch };
+            self.put(col, ch, style);
+            col += char_width(ch);
+        }
+    }
+
+    /// Writes `ch` to every empty cell between `start` and `end`, so it
+    /// doesn't cross out the brackets of other labels.
+    fn fill(&mut self, start : usize, end : usize, ch : char, style : Style) {
+        for col in start..end {
+            if matches!(self.cells.get(col), None | Some(Cell::Empty)) {
+                self.put(col, ch, style);
+            }
+        }
+    }
+}
+
+/// Converts a line and byte column pair into a display column, starting
+/// at 0.
+fn display_column(file : &SourceFile, (line, col) : (usize, usize)) -> usize {
+    let Some(line_span) = file.find_line_span(line) else { return col - 1 };
+    let line_src = line_span.slice(&file.src);
+    let byte = col - 1;
+    match line_src.get(..byte) {
+        Some(prefix) => prefix.chars().map(char_width).sum(),
+        // the column is past the end of the line, e.g. at the newline
+        None => {
+            let width = line_src.chars().map(char_width).sum::<usize>();
+            width + byte.saturating_sub(line_src.len())
+        },
+    }
+}
+
+/// Returns the number of columns `ch` takes up when it's written by the
+/// pretty printer, which escapes any characters that aren't printable.
+fn char_width(ch : char) -> usize {
+    match ch {
+        '\t' => TAB_WIDTH,
+        '\'' | '"' | '\\' => 1,
+        _ if ch.is_whitespace() => 1,
+        _ => match ch.escape_debug().count() {
+            1 if is_wide(ch) => 2,
+            n => n,
+        },
+    }
+}
+
+/// Returns whether `ch` is displayed using two columns, e.g. CJK characters
+/// and emoji.
+fn is_wide(ch : char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x115F | 0x2E80..=0x303E | 0x3041..=0x33FF | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF | 0xA000..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF | 0x20000..=0x2FFFD | 0x30000..=0x3FFFD)
 }
\ No newline at end of file
@@ -0,0 +1,1529 @@
+//! The registry of error codes, along with a long-form explanation of each
+//! one, as displayed by `cosy explain`.
+//!
+//! Codes are never renumbered or reused. If a diagnostic is removed from the
+//! compiler, its code should stay in this list so old references to it still
+//! make sense.
+
+use std::fmt;
+
+macro_rules! register_error_codes {
+    ($($code:ident : $explanation:literal,)*) => {
+        /// A stable identifier for a kind of diagnostic, e.g. `E0019`.
+        ///
+        /// Compiler bugs don't have an error code.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum ErrorCode {
+            $($code,)*
+        }
+
+        impl ErrorCode {
+            /// Every registered error code, in ascending order.
+            pub const ALL : &[ErrorCode] = &[$(ErrorCode::$code,)*];
+
+            /// Returns the name of this error code, e.g. `"E0019"`.
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$code => stringify!($code),)*
+                }
+            }
+
+            /// Returns the long-form explanation of this error code, with
+            /// examples.
+            pub fn explanation(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$code => $explanation,)*
+                }
+            }
+        }
+    };
+}
+
+impl ErrorCode {
+    /// Looks up an error code by its name. The `E` prefix is optional, and
+    /// is case-insensitive.
+    pub fn from_name(name : &str) -> Option<ErrorCode> {
+        let digits = name.strip_prefix(['E', 'e']).unwrap_or(name);
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let n = digits.parse::<usize>().ok()?;
+        ErrorCode::ALL.iter()
+            .find(|code| code.as_str()[1..].parse::<usize>() == Ok(n))
+            .copied()
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "{}", self.as_str())
+    }
+}
+
+register_error_codes! {
+E0001 : r#"A source file could not be read.
+
+This happens when a path passed to the compiler doesn't exist, or can't be
+opened, e.g. because of its permissions.
+
+Erroneous example:
+
+```text
+$ cosy build missing.cy
+```
+
+Check that the path is correct, and that the file is readable. Paths are
+relative to the current working directory.
+"#,
+
+E0002 : r#"A file could not be written to the build directory.
+
+The compiler writes intermediate files, such as the prelude, the runtime
+library, LLVM bitcode, and object files, into the cache directory. This error
+occurs when one of those files can't be created, e.g. because the disk is
+full, or the directory is read-only.
+
+Erroneous example:
+
+```text
+$ cosy build main.cy --cache /read-only/cache
+```
+
+Check that the cache directory is writable, or choose a different one using
+`--cache`.
+"#,
+
+E0003 : r#"The C toolchain could not be run.
+
+Cosy uses `clang` to compile its runtime library and to link executables, so
+it must be installed and available on the `PATH`.
+
+Erroneous example:
+
+```text
+$ PATH= cosy build main.cy
+```
+
+Install `clang`, and check that running `clang --version` works.
+"#,
+
+E0004 : r#"An executable could not be linked.
+
+The object files produced by the compiler are linked together with the
+runtime library and any external objects or libraries. This error occurs when
+the linker fails, most often because of a missing library, or an extern
+function which isn't defined anywhere.
+
+Erroneous code example:
+
+```cosy
+extern fn not_defined_anywhere(x : int) : int
+
+fn main() : int do
+  not_defined_anywhere(1)
+end
+```
+
+Check the output of the linker, which is displayed as a note. Objects and
+libraries that define extern functions can be linked using `--link` and `-l`.
+"#,
+
+E0005 : r#"A package name couldn't be derived from its path.
+
+The name of a package comes from the name of its root file, so it must be
+valid UTF-8.
+
+Rename the file so that its name only contains valid UTF-8 characters.
+"#,
+
+E0006 : r#"A package has no `main` function.
+
+Every package that is built into an executable needs a `main` function, which
+is where the program starts.
+
+Erroneous code example:
+
+```cosy
+fn helper() : int do
+  1
+end
+```
+
+Add a `main` function to the root file of the package:
+
+```cosy
+fn main() do
+  println("hello")
+end
+```
+"#,
+
+E0007 : r#"The `main` function takes parameters.
+
+The `main` function is called by the runtime when the program starts, so it
+can't take any parameters.
+
+Erroneous code example:
+
+```cosy
+fn main(x : int) : int do
+  x
+end
+```
+
+Remove the parameters of `main`.
+"#,
+
+E0008 : r#"The `main` function has an invalid return type.
+
+The value returned by `main` is used as the exit code of the program, so it
+must return either `int` or `()`.
+
+Erroneous code example:
+
+```cosy
+fn main() : bool do
+  true
+end
+```
+
+Return an `int` exit code instead, or nothing at all:
+
+```cosy
+fn main() : int do
+  0
+end
+```
+"#,
+
+E0009 : r#"The compiler couldn't generate code for the host machine.
+
+LLVM doesn't support generating code for the current host, or failed to
+initialise its target information.
+
+This usually means the compiler was built against an LLVM installation which
+was configured without support for the host architecture.
+"#,
+
+E0010 : r#"A compiled test harness could not be run.
+
+`cosy test` builds a test harness containing every `#[test]` function, and
+then runs it. This error occurs when the harness can't be started, e.g.
+because the executable was removed before it could run.
+
+Check that the cache directory is not being modified by another process.
+"#,
+
+E0011 : r#"The parser found an unexpected token.
+
+The source code doesn't follow the syntax of Cosy, e.g. because of a missing
+keyword or a misplaced symbol.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = (1 + 2
+end
+```
+
+Here, the closing `)` is missing. The label shows where the parser stopped,
+and the message describes what it was expecting to find there.
+"#,
+
+E0012 : r#"A radix literal has an invalid base.
+
+Radix literals are written `<base>r<digits>`, and the base must be between
+2 and 36.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = 40r123
+end
+```
+
+Use a base between 2 and 36:
+
+```cosy
+fn main() do
+  local x = 16rFF
+end
+```
+"#,
+
+E0013 : r#"A radix literal contains digits which are invalid for its base.
+
+Each digit of a radix literal must be less than its base, e.g. a base 2
+literal can only contain the digits `0` and `1`.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = 2r1021
+end
+```
+
+Use a larger base, or remove the invalid digits.
+"#,
+
+E0014 : r#"A number literal is too large.
+
+Integer literals must fit into 128 bits, even before their type is known.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = 1000000000000000000000000000000000000000000
+end
+```
+
+Use a smaller value. The exact range depends on the type of the literal; see
+E0055.
+"#,
+
+E0015 : r#"A string literal has no closing quote.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  println("hello)
+end
+```
+
+Add the missing `"` to the end of the string.
+"#,
+
+E0016 : r#"A string literal contains an unknown escape sequence.
+
+The escape sequences Cosy supports are `\n`, `\t`, `\r`, `\\`, `\"`, and
+`\'`.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  println("C:\dir")
+end
+```
+
+Escape the backslash itself if it should appear in the string:
+
+```cosy
+fn main() do
+  println("C:\\dir")
+end
+```
+"#,
+
+E0017 : r#"A raw identifier has no closing backtick.
+
+Raw identifiers are written between backticks, and allow keywords and other
+symbols to be used as names.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local `end = 1
+end
+```
+
+Add the missing backtick:
+
+```cosy
+fn main() do
+  local `end` = 1
+end
+```
+"#,
+
+E0018 : r#"The syntax tree contains a node in an unexpected position.
+
+The parser recovers from syntax errors by inserting placeholder nodes, which
+can't be compiled any further. This error is almost always reported alongside
+a syntax error, such as E0011, and goes away once that error is fixed.
+
+If this error is reported on its own, it's likely a bug in the compiler.
+"#,
+
+E0019 : r#"A value has a different type to the one that was expected.
+
+Types must match exactly, since Cosy doesn't convert between them implicitly.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x : int = true
+end
+```
+
+Either change the expected type, or change the value. Numeric values can be
+converted explicitly using the name of the target type:
+
+```cosy
+fn main() do
+  local small : u8 = 200
+  local x : int = int(small)
+end
+```
+"#,
+
+E0020 : r#"A name was defined more than once in the same scope.
+
+Erroneous code example:
+
+```cosy
+fn helper() do end
+fn helper() do end
+
+fn main() do end
+```
+
+Rename one of the definitions.
+"#,
+
+E0021 : r#"A recursive type has an infinite size.
+
+Enum types are stored inline, so an enum can't contain itself, directly or
+indirectly, since its size would be infinite.
+
+Erroneous code example:
+
+```cosy
+type List = enum {
+  Cons(int, List),
+  Nil,
+}
+
+fn main() do end
+```
+
+Restructure the type so that it doesn't contain itself, e.g. by storing the
+elements of the list in an array.
+"#,
+
+E0022 : r#"A function has two parameters with the same name.
+
+Erroneous code example:
+
+```cosy
+fn add(x : int, x : int) : int do
+  x
+end
+
+fn main() do end
+```
+
+Rename one of the parameters.
+"#,
+
+E0023 : r#"A function returns a value with a different type to its return type.
+
+This applies both to the final expression of a function body, and to values
+returned by `return`.
+
+Erroneous code example:
+
+```cosy
+fn answer() : int do
+  true
+end
+
+fn main() do end
+```
+
+Change either the return type, or the value being returned.
+"#,
+
+E0024 : r#"An extern function returns a `str`.
+
+Strings passed between Cosy functions are reference counted. A string
+returned by a C function isn't, so Cosy can't manage its memory.
+
+Erroneous code example:
+
+```cosy
+extern fn getenv(name : str) : str
+
+fn main() do end
+```
+
+Strings can still be passed to extern functions as parameters.
+"#,
+
+E0025 : r#"A type declaration has the same name as a built-in type.
+
+Erroneous code example:
+
+```cosy
+type int = enum { A, B }
+
+fn main() do end
+```
+
+Choose a different name for the type.
+"#,
+
+E0026 : r#"A constant has a type which isn't supported.
+
+Constants are evaluated at compile time, so they can only have a numeric
+type, `bool`, or `()`.
+
+Erroneous code example:
+
+```cosy
+const GREETING = "hello"
+
+fn main() do end
+```
+
+Use a function instead:
+
+```cosy
+fn greeting() : str do
+  "hello"
+end
+
+fn main() do end
+```
+"#,
+
+E0027 : r#"A declaration was made inside of a function body.
+
+Type declarations and extern functions can only be declared at the top level
+of a module.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  type Colour = enum { Red, Green, Blue }
+end
+```
+
+Move the declaration outside of the function:
+
+```cosy
+type Colour = enum { Red, Green, Blue }
+
+fn main() do end
+```
+"#,
+
+E0028 : r#"A constant without a type was used before it was defined.
+
+The type of a constant without a type annotation is inferred from its value,
+so it isn't known until the constant is defined.
+
+Erroneous code example:
+
+```cosy
+const B = A + 1
+const A = 1
+
+fn main() do end
+```
+
+Give the constant a type, or reorder the definitions:
+
+```cosy
+const B = A + 1
+const A : int = 1
+
+fn main() do end
+```
+"#,
+
+E0029 : r#"A variable was used which doesn't exist.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  count
+end
+```
+
+Check the spelling of the name, and that it's declared before it's used.
+"#,
+
+E0030 : r#"An array was indexed with a constant which is out of bounds.
+
+If the index of an array is a literal, it's checked at compile time.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  local xs = [1, 2, 3]
+  xs[3]
+end
+```
+
+Array indices start at zero, so the last element of an array of length `n`
+has the index `n - 1`.
+"#,
+
+E0031 : r#"A value was indexed which isn't an array.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  local x = 10
+  x[0]
+end
+```
+
+Only values with an array type, such as `[3]int`, can be indexed.
+"#,
+
+E0032 : r#"A value was called which isn't a function.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  local xs = [1, 2]
+  xs[0](1)
+end
+```
+
+Only functions and enum variants can be called.
+"#,
+
+E0033 : r#"The branches of a conditional expression have different types.
+
+When the result of an `if` or `match` expression is used, every branch must
+produce a value of the same type.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  if true then 1 else false end
+end
+```
+
+Change the branches so they all have the same type.
+"#,
+
+E0034 : r#"A unary operator was applied to a value of the wrong type.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = not 1
+end
+```
+
+`not` can only be applied to a `bool`, and `-` can only be applied to a
+number.
+"#,
+
+E0035 : r#"`break` or `continue` was used outside of a loop.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  break
+end
+```
+
+`break` and `continue` can only be used inside of `while` and `loop`
+expressions.
+"#,
+
+E0036 : r#"`return` was used outside of a function.
+
+Erroneous code example:
+
+```cosy
+const X = return 1
+
+fn main() do end
+```
+
+`return` can only be used inside of a function body.
+"#,
+
+E0037 : r#"`break` was given a value inside of a `while` loop.
+
+A `while` loop might never run its body, so it can't produce a value.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local mut i = 0
+  local x = while true do
+    break i
+  end
+end
+```
+
+Use a `loop`, and `break` when the condition is false instead:
+
+```cosy
+fn main() do
+  local mut i = 0
+  local x = loop do
+    break i
+  end
+end
+```
+"#,
+
+E0038 : r#"A binary operator was applied to values of the wrong types.
+
+Both operands of an arithmetic or comparison operator must have the same
+type.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local a : u8 = 1
+  local b : i32 = 2
+  local c = a + b
+end
+```
+
+Convert one of the operands so the types match, e.g. `i32(a) + b`.
+"#,
+
+E0039 : r#"Something was assigned to which can't be assigned to.
+
+Only local variables and the elements of arrays can be assigned to.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  1 = 2
+end
+```
+"#,
+
+E0040 : r#"An immutable variable was assigned to.
+
+Locals are immutable unless they're declared with `mut`. Parameters and the
+bindings of `match` patterns are always immutable. A local declared without
+an initial value can be assigned to exactly once.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = 1
+  x = 2
+end
+```
+
+Declare the local as mutable:
+
+```cosy
+fn main() do
+  local mut x = 1
+  x = 2
+end
+```
+"#,
+
+E0041 : r#"A type was given the wrong number of type arguments.
+
+`Result` expects an ok type and an error type, and every other type takes no
+type arguments.
+
+Erroneous code example:
+
+```cosy
+fn parse() : Result[int] do
+  Result::Ok(1)
+end
+
+fn main() do end
+```
+
+Give `Result` both of its type arguments, e.g. `Result[int, bool]`.
+"#,
+
+E0042 : r#"A type was used which doesn't exist.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x : integer = 1
+end
+```
+
+Check the spelling of the type, and that it's declared.
+"#,
+
+E0043 : r#"The length of an array type isn't an integer literal.
+
+Array lengths must be known when the type is written, so they can't be
+computed.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local xs : [1 + 2]int = [1, 2, 3]
+end
+```
+
+Write the length as a literal, e.g. `[3]int`.
+"#,
+
+E0044 : r#"The length of an array type is too large.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local xs : [100000000000000000000]int
+end
+```
+
+Use a smaller length.
+"#,
+
+E0045 : r#"An enum has two variants with the same name.
+
+Erroneous code example:
+
+```cosy
+type Colour = enum { Red, Red }
+
+fn main() do end
+```
+
+Rename one of the variants.
+"#,
+
+E0046 : r#"An enum variant was used which doesn't exist.
+
+Erroneous code example:
+
+```cosy
+type Colour = enum { Red, Green }
+
+fn main() do
+  local c = Colour::Blue
+end
+```
+
+Check the spelling of the variant, and that it belongs to the right enum.
+"#,
+
+E0047 : r#"The type of a `Result` couldn't be inferred.
+
+A `Result` value can only be created where its ok type and error type are
+already known.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local r = Result::Ok(1)
+end
+```
+
+Add a type annotation:
+
+```cosy
+fn main() do
+  local r : Result[int, bool] = Result::Ok(1)
+end
+```
+"#,
+
+E0048 : r#"A variant was used on a type which isn't an enum.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local c = Colour::Red
+end
+```
+
+Check the spelling of the enum, and that it's declared.
+"#,
+
+E0049 : r#"A function or enum variant was called with the wrong number of
+arguments.
+
+Erroneous code example:
+
+```cosy
+fn add(a : int, b : int) : int do
+  a + b
+end
+
+fn main() : int do
+  add(1)
+end
+```
+
+Pass one argument for each parameter.
+"#,
+
+E0050 : r#"A function was called which doesn't exist.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  prnitln("hello")
+end
+```
+
+Check the spelling of the name, and that the function is declared.
+"#,
+
+E0051 : r#"A function without a return type was called before it was defined.
+
+A function without a return type has its return type inferred from its body,
+so it can only be called after its definition.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = helper()
+end
+
+fn helper() do
+  1
+end
+```
+
+Give the function an explicit return type, or move it before its first use.
+"#,
+
+E0052 : r#"A value was converted to a type it can't be converted to.
+
+Only numeric types can be converted between each other.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  int(true)
+end
+```
+
+Use an `if` expression to convert a `bool` into a number:
+
+```cosy
+fn main() : int do
+  if true then 1 else 0 end
+end
+```
+"#,
+
+E0053 : r#"A type was used in an extern function which can't be passed to C.
+
+Extern functions can only use numeric types, `bool`, and `str`. `()` can only
+be used as a return type.
+
+Erroneous code example:
+
+```cosy
+extern fn sum(xs : [4]int) : int
+
+fn main() do end
+```
+
+Pass the elements of the array separately instead.
+"#,
+
+E0054 : r#"A `#[test]` function has an invalid signature.
+
+Test functions are called by the test harness, so they can't take any
+parameters, and must return `()`.
+
+Erroneous code example:
+
+```cosy
+#[test]
+fn it_works() : bool do
+  1 + 1 == 2
+end
+
+fn main() do end
+```
+
+Use `assert` to check the results of a test instead:
+
+```cosy
+#[test]
+fn it_works() do
+  assert(1 + 1 == 2)
+end
+
+fn main() do end
+```
+"#,
+
+E0055 : r#"A number literal doesn't fit into its type.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x : u8 = 256
+end
+```
+
+Use a larger type, or a value inside of the valid range shown in the note.
+"#,
+
+E0056 : r#"A number literal has an invalid suffix.
+
+The suffix of a number literal must be the name of a numeric type, and
+rational literals can only have the suffixes `f32` and `f64`.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = 1.5i32
+end
+```
+"#,
+
+E0057 : r#"A floating-point literal can't be represented exactly.
+
+Floats have a limited number of binary digits, so most decimal fractions are
+rounded to the nearest value that can be represented. This is a warning,
+since it might not be expected. Its lint name is `inexact_float_literals`.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x : f64 = 0.1
+end
+```
+
+If the rounding is acceptable, this warning can be silenced using
+`#[allow(inexact_float_literals)]`.
+"#,
+
+E0058 : r#"`#[try]` was applied to a value which isn't a `Result`.
+
+Erroneous code example:
+
+```cosy
+fn check() : Result[int, bool] do
+  local x = #[try] 3
+  Result::Ok(x)
+end
+
+fn main() do end
+```
+
+Only apply `#[try]` to expressions which produce a `Result`.
+"#,
+
+E0059 : r#"`#[try]` was used on a `Result` with a different error type to the
+enclosing function.
+
+`#[try]` returns any error from the enclosing function, so the error types
+must match.
+
+Erroneous code example:
+
+```cosy
+fn inner() : Result[int, int] do
+  Result::Err(1)
+end
+
+fn outer() : Result[int, bool] do
+  local x = #[try] inner()
+  Result::Ok(x)
+end
+
+fn main() do end
+```
+
+Change one of the error types so they match.
+"#,
+
+E0060 : r#"`#[try]` was used in a function which doesn't return a `Result`.
+
+Erroneous code example:
+
+```cosy
+fn inner() : Result[int, bool] do
+  Result::Ok(1)
+end
+
+fn outer() : int do
+  #[try] inner()
+end
+
+fn main() do end
+```
+
+Change the return type of the enclosing function to a `Result`, or handle the
+error using `match` instead.
+"#,
+
+E0061 : r#"A floating-point literal was used as a pattern.
+
+Floats can't be compared exactly, so they can't be matched against.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  local x : f64 = 0.5
+  match x
+  case 1f64 then 1
+  case _ then 0
+  end
+end
+```
+
+Use an `if` expression with a comparison instead.
+"#,
+
+E0062 : r#"A `match` arm has an invalid pattern.
+
+Patterns can be literals, `_`, names, or enum variants containing other
+patterns.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  match 1
+  case 1 + 1 then 1
+  case _ then 0
+  end
+end
+```
+"#,
+
+E0063 : r#"An enum variant pattern has the wrong number of fields.
+
+Erroneous code example:
+
+```cosy
+type Shape = enum { Rect(int, int) }
+
+fn main() : int do
+  match Shape::Rect(1, 2)
+  case Rect(_) then 0
+  end
+end
+```
+
+Give the pattern one field for each field of the variant, using `_` for any
+fields which aren't needed.
+"#,
+
+E0064 : r#"A pattern has a different type to the value being matched.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  match 1
+  case true then 1
+  case _ then 0
+  end
+end
+```
+"#,
+
+E0065 : r#"An attribute was used which doesn't exist.
+
+The known attributes are `#[try]`, `#[inline]`, `#[test]`, `#[extern]`,
+`#[allow]`, `#[warn]`, and `#[deny]`.
+
+Erroneous code example:
+
+```cosy
+#[inline_always]
+fn helper() do end
+
+fn main() do end
+```
+"#,
+
+E0066 : r#"An attribute was given arguments it doesn't take.
+
+Erroneous code example:
+
+```cosy
+#[test(slow)]
+fn it_works() do end
+
+fn main() do end
+```
+
+Remove the arguments.
+"#,
+
+E0067 : r#"An attribute was applied to something it can't be applied to.
+
+`#[try]` can only be applied to expressions, and every other attribute can
+only be applied to functions.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x = #[inline] 1
+end
+```
+"#,
+
+E0068 : r#"An attribute was applied more than once.
+
+Applying the same attribute twice has no additional effect, so this is a
+warning. Its lint name is `duplicate_attributes`.
+
+Erroneous code example:
+
+```cosy
+#[inline] #[inline]
+fn helper() do end
+
+fn main() do end
+```
+
+Remove the duplicate attribute.
+"#,
+
+E0069 : r#"The type of a local couldn't be inferred.
+
+A local without a type annotation gets its type from its initial value, or
+from its first assignment. The type of an empty array literal can't be
+inferred either.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local x
+end
+```
+
+Add a type annotation:
+
+```cosy
+fn main() do
+  local x : int
+end
+```
+"#,
+
+E0070 : r#"The elements of an array literal have different types.
+
+Erroneous code example:
+
+```cosy
+fn main() do
+  local xs = [1, true]
+end
+```
+
+Every element of an array must have the same type.
+"#,
+
+E0071 : r#"An expression was used in a constant which can't be evaluated at
+compile time.
+
+Constants can only use numbers, booleans, operators, locals, `if`, loops, and
+calls to functions which only use these.
+
+Erroneous code example:
+
+```cosy
+fn greet() : int do
+  println("hello")
+  1
+end
+
+const X = greet()
+
+fn main() do end
+```
+"#,
+
+E0072 : r#"A constant depends on itself.
+
+Erroneous code example:
+
+```cosy
+const A : int = B + 1
+const B : int = A
+
+fn main() do end
+```
+
+Constants can also depend on themselves through function calls:
+
+```cosy
+fn get() : int do
+  X + 1
+end
+
+const X = get()
+
+fn main() do end
+```
+
+Break the cycle, so the value of the constant can be computed.
+"#,
+
+E0073 : r#"Evaluating a constant took too long.
+
+The compiler stops evaluating a constant after 1000000 steps, since it's
+likely stuck in an infinite loop.
+
+Erroneous code example:
+
+```cosy
+fn spin() : int do
+  loop do end
+end
+
+const X = spin()
+
+fn main() do end
+```
+"#,
+
+E0074 : r#"An extern function was called while evaluating a constant.
+
+Extern functions are defined outside of Cosy, so they can't be run by the
+compiler.
+
+Erroneous code example:
+
+```cosy
+extern fn c_random() : int
+
+const SEED = c_random()
+
+fn main() do end
+```
+
+Call the function at runtime instead.
+"#,
+
+E0075 : r#"Evaluating a constant exceeded the maximum call depth.
+
+The compiler stops evaluating a constant after 128 nested calls, since it's
+likely stuck in infinite recursion.
+
+Erroneous code example:
+
+```cosy
+fn forever(n : int) : int do
+  forever(n + 1)
+end
+
+const X = forever(0)
+
+fn main() do end
+```
+"#,
+
+E0076 : r#"An assertion failed while evaluating a constant.
+
+Erroneous code example:
+
+```cosy
+fn checked(n : int) : int do
+  assert(n > 0)
+  n
+end
+
+const X = checked(0)
+
+fn main() do end
+```
+"#,
+
+E0077 : r#"A constant divides by zero.
+
+Erroneous code example:
+
+```cosy
+const X = 1 / 0
+
+fn main() do end
+```
+"#,
+
+E0078 : r#"A constant overflows its type.
+
+Erroneous code example:
+
+```cosy
+const X : u8 = 200 + 100
+
+fn main() do end
+```
+
+Use a larger type, or change the computation so it stays in range.
+"#,
+
+E0079 : r#"A local was used before it was given a value.
+
+Locals declared without an initial value must be assigned to on every path
+before they're used.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  local x : int
+  if true then
+    x = 1
+  end
+  x
+end
+```
+
+Assign a value to the local on every path, or give it an initial value.
+"#,
+
+E0080 : r#"A `match` arm can never be reached.
+
+Every value the pattern could match is already matched by an earlier arm.
+This is a warning, since the arm is simply ignored. Its lint name is
+`unreachable_patterns`.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  match true
+  case _ then 0
+  case true then 1
+  end
+end
+```
+
+Remove the unreachable arm, or move it before the arm that covers it.
+"#,
+
+E0081 : r#"A `match` expression doesn't cover every possible value.
+
+Erroneous code example:
+
+```cosy
+type Colour = enum { Red, Green, Blue }
+
+fn main() : int do
+  match Colour::Red
+  case Red then 0
+  case Green then 1
+  end
+end
+```
+
+Add arms for the missing values, or use `_` to match everything else.
+"#,
+
+E0082 : r#"Code can never be run.
+
+Code which comes after an expression that never finishes, such as `return`,
+`break`, or an infinite loop, can never be run. This is a warning. Its lint
+name is `unreachable_code`.
+
+Erroneous code example:
+
+```cosy
+fn main() : int do
+  return 1
+  println("unreachable")
+  0
+end
+```
+
+Remove the unreachable code.
+"#,
+
+E0083 : r#"A source file could not be updated by `cosy fix`.
+
+`cosy fix` rewrites source files in place to apply suggested fixes. This
+error occurs when one of those files can't be written, e.g. because it's
+read-only.
+
+Erroneous example:
+
+```text
+$ chmod -w main.cy
+$ cosy fix main.cy
+```
+
+Check that the source files of the package are writable.
+"#,
+
+E0084 : r#"A lint level was set for a lint which doesn't exist.
+
+Lint levels can be set using `-A`, `-W`, and `-D` on the command line, or
+`#[allow(..)]`, `#[warn(..)]`, and `#[deny(..)]` on a function. This is a
+warning, since the unknown lint is ignored. Its lint name is `unknown_lints`.
+
+Erroneous code example:
+
+```cosy
+#[allow(unreachable_cod)]
+fn main() do end
+```
+
+The known lints are `unknown_lints`, `inexact_float_literals`,
+`duplicate_attributes`, `unreachable_patterns`, and `unreachable_code`. The
+`warnings` group contains every warning, so `-D warnings` turns all warnings
+into errors.
+"#,
+
+E0085 : r#"A lint attribute was malformed.
+
+`#[allow(..)]`, `#[warn(..)]`, and `#[deny(..)]` expect a list of lint names.
+
+Erroneous code example:
+
+```cosy
+#[allow]
+fn main() do end
+```
+
+List the lints to configure, e.g. `#[allow(unreachable_code)]`.
+"#,
+
+E0086 : r#"Diagnostics could not be written to the `--error-output` file.
+
+`--error-output` writes diagnostics to a file instead of stderr. This error
+occurs when that file can't be created or written to, e.g. because its
+directory doesn't exist. The diagnostics are written to stderr instead.
+
+Erroneous example:
+
+```text
+$ cosy build --error-output missing/errors.json main.cy
+```
+
+Check that the directory of the file exists, and that it's writable.
+"#,
+}
@@ -0,0 +1,71 @@
+use std::{ fs, io };
+use std::path::PathBuf;
+use std::collections::BTreeMap;
+
+use crate::src::{ SourceMap, FileId, GetFileResult };
+use crate::error::{ IssueManager, Suggestion, Applicability };
+
+/// A source file which was modified by `apply_suggestions`.
+pub struct FixedFile {
+    /// The path of the source file.
+    pub path : PathBuf,
+    /// The number of suggestions applied to the file.
+    pub fixes : usize,
+}
+
+/// Applies every machine-applicable suggestion in `issues` to the source
+/// files they refer to, and returns the files which were modified.
+///
+/// If two suggestions overlap, only the first one is applied. The other may
+/// still be applicable once the source code has been checked again.
+pub fn apply_suggestions(
+    files : &SourceMap,
+    issues : &IssueManager,
+) -> io::Result<Vec<FixedFile>> {
+    let mut edits : BTreeMap<FileId, Vec<&Suggestion>> = BTreeMap::new();
+    for diag in &issues.errors {
+        for suggestion in &diag.suggestions {
+            if suggestion.applicability == Applicability::MachineApplicable {
+                edits.entry(suggestion.location.file_id)
+                    .or_default()
+                    .push(suggestion);
+            }
+        }
+    }
+    let mut fixed = Vec::new();
+    for (file_id, mut suggestions) in edits {
+        let (path, file) = match files.get_existing_file(file_id) {
+            GetFileResult::Ok(ok) => ok,
+            GetFileResult::ErrNotInManifest => continue,
+            GetFileResult::ErrIo(err) => return Err(err),
+        };
+        suggestions.sort_by_key(|suggestion| {
+            let span = &suggestion.location.span;
+            (span.start, span.end)
+        });
+        let mut src = String::new();
+        let mut cursor = 0;
+        let mut fixes = 0;
+        let mut prev : Option<&Suggestion> = None;
+        for suggestion in suggestions {
+            let span = &suggestion.location.span;
+            if span.start < cursor || span.end > file.src.len() {
+                continue;
+            }
+            if let Some(prev) = prev && prev.location.span == *span
+                    && prev.replacement == suggestion.replacement {
+                // the same edit was suggested twice
+                continue;
+            }
+            src.push_str(&file.src[cursor..span.start]);
+            src.push_str(&suggestion.replacement);
+            cursor = span.end;
+            fixes += 1;
+            prev = Some(suggestion);
+        }
+        src.push_str(&file.src[cursor..]);
+        fs::write(path, src)?;
+        fixed.push(FixedFile { path : path.to_path_buf(), fixes });
+    }
+    Ok(fixed)
+}
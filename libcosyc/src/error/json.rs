@@ -0,0 +1,181 @@
+use std::io;
+use std::path::Path;
+use std::fmt::Write;
+
+use crate::src::{ SourceMap, SourceFile, Message, Span, GetFileResult };
+use crate::error::{ Diagnostic, IssueManager, Label, Suggestion };
+
+/// Renders diagnostic information as JSON, for use by editors and other
+/// tools. Each diagnostic is written as a single JSON object on its own line:
+///
+/// ```json
+/// {
+///   "severity": "error",
+///   "code": "E0019",
+///   "message": "...",
+///   "primary_labels": [{
+///     "path": "main.cy",
+///     "span": { "start": 20, "end": 23 },
+///     "start": { "line": 2, "column": 5 },
+///     "end": { "line": 2, "column": 8 },
+///     "caption": null
+///   }],
+///   "secondary_labels": [],
+///   "notes": ["..."],
+///   "suggestions": [{
+///     "path": "main.cy",
+///     "span": { "start": 20, "end": 20 },
+///     "start": { "line": 2, "column": 5 },
+///     "end": { "line": 2, "column": 5 },
+///     "replacement": "mut ",
+///     "applicability": "machine-applicable",
+///     "message": "..."
+///   }]
+/// }
+/// ```
+///
+/// Byte offsets are zero-based, whereas line and column numbers start at 1.
+/// Labels and suggestions whose source file isn't in the manifest are omitted.
+pub fn write_errors<W : io::Write>(
+    out : &mut W,
+    files : &SourceMap,
+    issues : &IssueManager,
+) -> io::Result<()> {
+    let mut ctx = RendererCtx { files, buf : String::new() };
+    for diag in &issues.errors {
+        ctx.buf.clear();
+        ctx.write_diagnostic(diag)?;
+        ctx.buf.push('\n');
+        out.write_all(ctx.buf.as_bytes())?;
+    }
+    out.flush()
+}
+
+struct RendererCtx<'src> {
+    files : &'src SourceMap,
+    buf : String,
+}
+
+impl RendererCtx<'_> {
+    fn write_diagnostic(&mut self, diag : &Diagnostic) -> io::Result<()> {
+        self.buf.push_str("{\"severity\":");
+        self.write_str(diag.severity.as_str());
+        self.buf.push_str(",\"code\":");
+        match diag.code {
+            Some(code) => self.write_str(code.as_str()),
+            None => self.buf.push_str("null"),
+        }
+        self.buf.push_str(",\"message\":");
+        self.write_message(diag.message.as_ref());
+        self.buf.push_str(",\"primary_labels\":");
+        self.write_labels(&diag.primary_labels)?;
+        self.buf.push_str(",\"secondary_labels\":");
+        self.write_labels(&diag.secondary_labels)?;
+        self.buf.push_str(",\"notes\":[");
+        for (i, note) in diag.notes.iter().enumerate() {
+            if i > 0 {
+                self.buf.push(',');
+            }
+            self.write_message(Some(&note.caption));
+        }
+        self.buf.push_str("],\"suggestions\":");
+        self.write_suggestions(&diag.suggestions)?;
+        self.buf.push('}');
+        Ok(())
+    }
+
+    fn write_labels(&mut self, labels : &[Label]) -> io::Result<()> {
+        self.buf.push('[');
+        let mut first = true;
+        for label in labels {
+            let (path, file) = match self.files.get_existing_file(label.location.file_id) {
+                GetFileResult::Ok(ok) => ok,
+                GetFileResult::ErrNotInManifest => continue,
+                GetFileResult::ErrIo(err) => return Err(err),
+            };
+            if !first {
+                self.buf.push(',');
+            }
+            first = false;
+            self.buf.push('{');
+            self.write_location(path, &file, &label.location.span);
+            self.buf.push_str(",\"caption\":");
+            self.write_message(label.caption.as_ref());
+            self.buf.push('}');
+        }
+        self.buf.push(']');
+        Ok(())
+    }
+
+    fn write_suggestions(&mut self, suggestions : &[Suggestion]) -> io::Result<()> {
+        self.buf.push('[');
+        let mut first = true;
+        for suggestion in suggestions {
+            let (path, file) = match self.files.get_existing_file(suggestion.location.file_id) {
+                GetFileResult::Ok(ok) => ok,
+                GetFileResult::ErrNotInManifest => continue,
+                GetFileResult::ErrIo(err) => return Err(err),
+            };
+            if !first {
+                self.buf.push(',');
+            }
+            first = false;
+            self.buf.push('{');
+            self.write_location(path, &file, &suggestion.location.span);
+            self.buf.push_str(",\"replacement\":");
+            self.write_str(&suggestion.replacement);
+            self.buf.push_str(",\"applicability\":");
+            self.write_str(suggestion.applicability.as_str());
+            self.buf.push_str(",\"message\":");
+            self.write_message(Some(&suggestion.caption));
+            self.buf.push('}');
+        }
+        self.buf.push(']');
+        Ok(())
+    }
+
+    fn write_location(&mut self, path : &Path, file : &SourceFile, span : &Span) {
+        let (start, end) = file.find_line_and_col_span(span);
+        self.buf.push_str("\"path\":");
+        self.write_str(&path.to_string_lossy());
+        let _ = write!(self.buf,
+            ",\"span\":{{\"start\":{},\"end\":{}}}\
+            ,\"start\":{{\"line\":{},\"column\":{}}}\
+            ,\"end\":{{\"line\":{},\"column\":{}}}",
+            span.start, span.end, start.0, start.1, end.0, end.1,
+        );
+    }
+
+    fn write_message(&mut self, message : Option<&Message>) {
+        let Some(message) = message else {
+            self.buf.push_str("null");
+            return;
+        };
+        let mut message_str = String::new();
+        message.write_to_string(self.files, &mut message_str);
+        self.write_str(&message_str);
+    }
+
+    fn write_str(&mut self, text : &str) {
+        write_json_str(&mut self.buf, text);
+    }
+}
+
+/// Writes `text` to `buf` as a quoted JSON string.
+pub(crate) fn write_json_str(buf : &mut String, text : &str) {
+    buf.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => buf.push_str("\\\""),
+            '\\' => buf.push_str("\\\\"),
+            '\n' => buf.push_str("\\n"),
+            '\r' => buf.push_str("\\r"),
+            '\t' => buf.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(buf, "\\u{:04x}", ch as u32);
+            },
+            ch => buf.push(ch),
+        }
+    }
+    buf.push('"');
+}
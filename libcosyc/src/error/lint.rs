@@ -0,0 +1,120 @@
+//! The registry of lints, which are warnings whose level can be configured
+//! using `-A`, `-W`, and `-D` on the command line, or `#[allow(..)]`,
+//! `#[warn(..)]`, and `#[deny(..)]` in source code.
+
+use crate::src::Location;
+
+/// The name of the lint group which contains every warning, so that
+/// `-D warnings` treats all warnings as errors.
+pub const WARNINGS : &str = "warnings";
+
+/// Decides how the diagnostics of a lint are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    /// The lint is ignored.
+    Allow,
+    /// The lint is reported as a warning.
+    Warn,
+    /// The lint is reported as an error.
+    Deny,
+}
+
+impl LintLevel {
+    /// Returns the name of this level, as used by its attribute.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "allow",
+            LintLevel::Warn => "warn",
+            LintLevel::Deny => "deny",
+        }
+    }
+
+    /// Returns the command-line flag which sets this level.
+    pub fn flag(&self) -> &'static str {
+        match self {
+            LintLevel::Allow => "-A",
+            LintLevel::Warn => "-W",
+            LintLevel::Deny => "-D",
+        }
+    }
+}
+
+/// A kind of warning which can be configured by name.
+#[derive(Debug)]
+pub struct Lint {
+    /// The name of the lint, e.g. `unreachable_code`.
+    pub name : &'static str,
+    /// The level of the lint when it hasn't been configured.
+    pub default_level : LintLevel,
+}
+
+pub static UNKNOWN_LINTS : Lint = Lint {
+    name : "unknown_lints",
+    default_level : LintLevel::Warn,
+};
+
+pub static INEXACT_FLOAT_LITERALS : Lint = Lint {
+    name : "inexact_float_literals",
+    default_level : LintLevel::Warn,
+};
+
+pub static DUPLICATE_ATTRIBUTES : Lint = Lint {
+    name : "duplicate_attributes",
+    default_level : LintLevel::Warn,
+};
+
+pub static UNREACHABLE_PATTERNS : Lint = Lint {
+    name : "unreachable_patterns",
+    default_level : LintLevel::Warn,
+};
+
+pub static UNREACHABLE_CODE : Lint = Lint {
+    name : "unreachable_code",
+    default_level : LintLevel::Warn,
+};
+
+/// Every lint known to the compiler.
+pub static ALL : &[&Lint] = &[
+    &UNKNOWN_LINTS,
+    &INEXACT_FLOAT_LITERALS,
+    &DUPLICATE_ATTRIBUTES,
+    &UNREACHABLE_PATTERNS,
+    &UNREACHABLE_CODE,
+];
+
+/// Looks up a lint by its name.
+pub fn find(name : &str) -> Option<&'static Lint> {
+    ALL.iter().copied().find(|lint| lint.name == name)
+}
+
+/// Returns whether `name` is a lint or a lint group.
+pub fn is_known(name : &str) -> bool {
+    name == WARNINGS || find(name).is_some()
+}
+
+/// Returns a list of every lint name, for use in error messages.
+pub fn known_names() -> String {
+    let mut names = ALL.iter()
+        .map(|lint| format!("`{}`", lint.name))
+        .collect::<Vec<_>>();
+    names.push(format!("`{}`", WARNINGS));
+    names.join(", ")
+}
+
+/// A lint level set in source code by an attribute, e.g. `#[allow(..)]`.
+#[derive(Debug, Clone)]
+pub struct LintAttr {
+    /// The name of the lint or lint group.
+    pub name : String,
+    pub level : LintLevel,
+    /// The location of the attribute.
+    pub loc : Location,
+}
+
+/// Where the level of a lint was decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum LevelSource {
+    Default,
+    CommandLine,
+    Attr(Location),
+}
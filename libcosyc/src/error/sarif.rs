@@ -0,0 +1,208 @@
+use std::io;
+use std::path::Path;
+use std::fmt::Write;
+
+use crate::src::{ SourceMap, SourceFile, Message, Span, GetFileResult };
+use crate::error::{ Diagnostic, IssueManager, Label, Suggestion, Severity };
+use crate::error::json::write_json_str;
+
+const SARIF_SCHEMA : &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Renders diagnostic information as a SARIF 2.1.0 log, for use by code
+/// scanning tools. Unlike the JSON renderer, this writes a single document
+/// containing every diagnostic.
+///
+/// Each diagnostic becomes a result, whose locations are the primary labels
+/// and whose related locations are the secondary labels. Notes are appended
+/// to the markdown version of the result message, suggestions become fixes,
+/// and error codes are used as rule identifiers. Columns are measured in Unicode code points.
+pub fn write_errors<W : io::Write>(
+    out : &mut W,
+    files : &SourceMap,
+    issues : &IssueManager,
+) -> io::Result<()> {
+    let mut ctx = RendererCtx { files, buf : String::new() };
+    ctx.write(issues)?;
+    ctx.buf.push('\n');
+    out.write_all(ctx.buf.as_bytes())?;
+    out.flush()
+}
+
+struct RendererCtx<'src> {
+    files : &'src SourceMap,
+    buf : String,
+}
+
+impl RendererCtx<'_> {
+    fn write(&mut self, issues : &IssueManager) -> io::Result<()> {
+        self.buf.push_str("{\"$schema\":");
+        self.write_str(SARIF_SCHEMA);
+        self.buf.push_str(",\"version\":\"2.1.0\",\"runs\":[{\"tool\":{\"driver\":{");
+        self.buf.push_str("\"name\":\"cosy\",\"version\":");
+        self.write_str(env!("CARGO_PKG_VERSION"));
+        self.buf.push_str(",\"informationUri\":");
+        self.write_str("https://github.com/katsaii/cosy-lang");
+        self.buf.push_str("}},\"columnKind\":\"unicodeCodePoints\",\"results\":[");
+        for (i, diag) in issues.errors.iter().enumerate() {
+            if i > 0 {
+                self.buf.push(',');
+            }
+            self.write_result(diag)?;
+        }
+        self.buf.push_str("]}]}");
+        Ok(())
+    }
+
+    fn write_result(&mut self, diag : &Diagnostic) -> io::Result<()> {
+        let level = match diag.severity {
+            Severity::Info | Severity::Help => "note",
+            Severity::Warning => "warning",
+            Severity::Fatal | Severity::Bug => "error",
+        };
+        let mut text = match &diag.message {
+            Some(message) => self.render(message),
+            None => diag.severity.as_str().to_string(),
+        };
+        let mut markdown = text.clone();
+        for note in &diag.notes {
+            let caption = self.render(&note.caption);
+            let _ = write!(text, "\nnote: {}", caption);
+            let _ = write!(markdown, "\n\n**note:** {}", caption);
+        }
+        self.buf.push('{');
+        if let Some(code) = diag.code {
+            let _ = write!(self.buf, "\"ruleId\":\"{}\",", code);
+        }
+        let _ = write!(self.buf, "\"level\":\"{}\",\"message\":{{\"text\":", level);
+        self.write_str(&text);
+        self.buf.push_str(",\"markdown\":");
+        self.write_str(&markdown);
+        self.buf.push_str("},\"locations\":");
+        self.write_locations(&diag.primary_labels, false)?;
+        self.buf.push_str(",\"relatedLocations\":");
+        self.write_locations(&diag.secondary_labels, true)?;
+        if !diag.suggestions.is_empty() {
+            self.buf.push_str(",\"fixes\":");
+            self.write_fixes(&diag.suggestions)?;
+        }
+        self.buf.push('}');
+        Ok(())
+    }
+
+    fn write_locations(
+        &mut self,
+        labels : &[Label],
+        with_ids : bool,
+    ) -> io::Result<()> {
+        self.buf.push('[');
+        let mut id = 0;
+        for label in labels {
+            let (path, file) = match self.files.get_existing_file(label.location.file_id) {
+                GetFileResult::Ok(ok) => ok,
+                GetFileResult::ErrNotInManifest => continue,
+                GetFileResult::ErrIo(err) => return Err(err),
+            };
+            if id > 0 {
+                self.buf.push(',');
+            }
+            self.buf.push('{');
+            if with_ids {
+                let _ = write!(self.buf, "\"id\":{},", id);
+            }
+            id += 1;
+            self.buf.push_str("\"physicalLocation\":{\"artifactLocation\":{\"uri\":");
+            self.write_str(&path_to_uri(path));
+            self.buf.push_str("},\"region\":");
+            self.write_region(&file, &label.location.span);
+            self.buf.push('}');
+            if let Some(caption) = &label.caption {
+                let caption = self.render(caption);
+                self.buf.push_str(",\"message\":{\"text\":");
+                self.write_str(&caption);
+                self.buf.push('}');
+            }
+            self.buf.push('}');
+        }
+        self.buf.push(']');
+        Ok(())
+    }
+
+    fn write_fixes(&mut self, suggestions : &[Suggestion]) -> io::Result<()> {
+        self.buf.push('[');
+        let mut first = true;
+        for suggestion in suggestions {
+            let (path, file) = match self.files.get_existing_file(suggestion.location.file_id) {
+                GetFileResult::Ok(ok) => ok,
+                GetFileResult::ErrNotInManifest => continue,
+                GetFileResult::ErrIo(err) => return Err(err),
+            };
+            if !first {
+                self.buf.push(',');
+            }
+            first = false;
+            let caption = self.render(&suggestion.caption);
+            self.buf.push_str("{\"description\":{\"text\":");
+            self.write_str(&caption);
+            self.buf.push_str("},\"artifactChanges\":[{\"artifactLocation\":{\"uri\":");
+            self.write_str(&path_to_uri(path));
+            self.buf.push_str("},\"replacements\":[{\"deletedRegion\":");
+            self.write_region(&file, &suggestion.location.span);
+            self.buf.push_str(",\"insertedContent\":{\"text\":");
+            self.write_str(&suggestion.replacement);
+            self.buf.push_str("}}]}]}");
+        }
+        self.buf.push(']');
+        Ok(())
+    }
+
+    fn write_region(&mut self, file : &SourceFile, span : &Span) {
+        let (start, end) = file.find_line_and_col_span(span);
+        let _ = write!(self.buf,
+            "{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}\
+            ,\"byteOffset\":{},\"byteLength\":{}}}",
+            start.0, code_point_column(file, start),
+            end.0, code_point_column(file, end),
+            span.start, span.len(),
+        );
+    }
+
+    fn render(&self, message : &Message) -> String {
+        let mut message_str = String::new();
+        message.write_to_string(self.files, &mut message_str);
+        message_str
+    }
+
+    fn write_str(&mut self, text : &str) {
+        write_json_str(&mut self.buf, text);
+    }
+}
+
+/// Converts a line and byte column pair into a column measured in code points.
+fn code_point_column(file : &SourceFile, (line, col) : (usize, usize)) -> usize {
+    let Some(line_span) = file.find_line_span(line) else { return col };
+    let line_src = line_span.slice(&file.src);
+    match line_src.get(..col - 1) {
+        Some(prefix) => prefix.chars().count() + 1,
+        // the column is past the end of the line, e.g. at the newline
+        None => line_src.chars().count() + col.saturating_sub(line_src.len()),
+    }
+}
+
+/// Converts a file path into a URI reference, percent-encoding any
+/// characters which aren't allowed.
+fn path_to_uri(path : &Path) -> String {
+    let mut uri = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'\\' => uri.push('/'),
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9'
+                | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char);
+            },
+            _ => {
+                let _ = write!(uri, "%{:02X}", byte);
+            },
+        }
+    }
+    uri
+}
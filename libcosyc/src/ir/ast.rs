@@ -13,10 +13,114 @@ pub enum Visibility {
     Internal,
 }
 
+/// Binary operators, e.g. `+` or `and`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl BinaryOp {
+    /// Returns the precedence of this operator. Operators with a higher
+    /// precedence bind more tightly.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => 0,
+            BinaryOp::And => 1,
+            | BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => 2,
+            BinaryOp::Add | BinaryOp::Sub => 3,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem => 4,
+        }
+    }
+
+    /// Returns the operator as it appears in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "and",
+            BinaryOp::Or => "or",
+        }
+    }
+}
+
+/// Unary operators, e.g. `-` or `not`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+impl UnaryOp {
+    /// Returns the operator as it appears in source code.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+            UnaryOp::Not => "not",
+        }
+    }
+}
+
 /// An owned section of source code, such as a string literal after resolving
 /// escape codes.
 pub type Symbol = String;
 
+/// An attribute applied to a declaration or expression, e.g. `#[inline]`.
+#[derive(Debug)]
+pub struct Attribute {
+    pub name : Located<Symbol>,
+    pub args : Vec<Node>,
+    /// The location of the complete attribute, including the `#[` and `]`.
+    pub loc : Location,
+}
+
+/// A variant of an enum type, e.g. `Circle(int)`.
+#[derive(Debug)]
+pub struct Variant {
+    pub name : Located<Symbol>,
+    pub fields : Vec<Node>,
+}
+
+/// A parameter of a function, e.g. `x : int`.
+#[derive(Debug)]
+pub struct Param {
+    pub name : Located<Symbol>,
+    pub ty : Node,
+}
+
+/// A single `case` of a `match` expression. Patterns are parsed as
+/// expressions, and are only validated during the AST -> HIR lowering step.
+#[derive(Debug)]
+pub struct MatchArm {
+    pub pattern : Node,
+    pub body : Node,
+}
+
 /// All AST nodes available to Cosy.
 ///
 /// Although it's possible to construct them, any malformed ASTs will raise an
@@ -24,27 +128,124 @@ pub type Symbol = String;
 #[derive(Debug)]
 pub enum Node {
     // expressions
-    NumIntegral(Located<u128>),
-    NumRational(Located<Symbol>),
+    NumIntegral {
+        value : Located<u128>,
+        /// The type suffix of the literal, e.g. the `u8` in `255u8`.
+        suffix : Option<Located<Symbol>>,
+    },
+    NumRational {
+        value : Located<Symbol>,
+        suffix : Option<Located<Symbol>>,
+    },
     Bool(Located<bool>),
+    /// A string literal, with any escape sequences already replaced.
+    Str(Located<String>),
     Id(Located<Symbol>),
     Block(Located<Vec<Node>>),
     Parens(Located<Box<Node>>),
+    Array(Located<Vec<Node>>),
+    Index {
+        target : Box<Node>,
+        index : Located<Box<Node>>,
+    },
+    Path {
+        ty : Located<Symbol>,
+        name : Located<Symbol>,
+    },
+    Call {
+        callee : Box<Node>,
+        args : Located<Vec<Node>>,
+    },
+    Match {
+        scrutinee : Box<Node>,
+        arms : Located<Vec<MatchArm>>,
+    },
+    /// The location of `cond` is the location of the `if` keyword.
+    If {
+        cond : Located<Box<Node>>,
+        then_branch : Box<Node>,
+        else_branch : Option<Box<Node>>,
+    },
+    /// The location of `cond` is the location of the `while` keyword.
+    While {
+        cond : Located<Box<Node>>,
+        body : Box<Node>,
+    },
+    /// The location of the body is the location of the `loop` keyword.
+    Loop(Located<Box<Node>>),
+    /// The location of the value is the location of the `break` keyword.
+    Break(Located<Option<Box<Node>>>),
+    Continue(Location),
+    /// The location of the value is the location of the `return` keyword.
+    Return(Located<Option<Box<Node>>>),
+    Binary {
+        op : Located<BinaryOp>,
+        lhs : Box<Node>,
+        rhs : Box<Node>,
+    },
+    Unary {
+        op : Located<UnaryOp>,
+        value : Box<Node>,
+    },
+    // types
+    ArrayType {
+        len : Located<Box<Node>>,
+        elem : Box<Node>,
+    },
+    Enum(Located<Vec<Variant>>),
+    /// A type with type arguments, e.g. `Result[int, bool]`.
+    GenericType {
+        name : Located<Symbol>,
+        args : Located<Vec<Node>>,
+    },
     // statments
+    /// `loc` is the location of the whole statement.
     Local {
         name : Located<Symbol>,
+        mutable : bool,
+        ty : Option<Box<Node>>,
         init : Option<Box<Node>>,
+        loc : Location,
+    },
+    /// The location of `value` is the location of the `=` symbol.
+    Assign {
+        target : Box<Node>,
+        value : Located<Box<Node>>,
     },
     // declarations
     Fn {
         name : Located<Symbol>,
+        /// The location of the parameter list includes its parentheses.
+        params : Located<Vec<Param>>,
+        ret : Option<Box<Node>>,
         body : Box<Node>,
     },
+    /// A function defined outside of Cosy, e.g. in a C library.
+    ExternFn {
+        name : Located<Symbol>,
+        /// The location of the parameter list includes its parentheses.
+        params : Located<Vec<Param>>,
+        ret : Option<Box<Node>>,
+    },
+    Type {
+        name : Located<Symbol>,
+        def : Box<Node>,
+    },
+    /// A constant whose value is computed at compile time.
+    Const {
+        name : Located<Symbol>,
+        ty : Option<Box<Node>>,
+        value : Box<Node>,
+    },
     // misc
     Scope {
         vis : Located<Visibility>,
         node : Box<Node>,
     },
+    Attrs {
+        attrs : Vec<Attribute>,
+        node : Box<Node>,
+    },
 }
 
 impl Node {
@@ -52,29 +253,73 @@ impl Node {
     /// component of the node.
     pub fn primary_location(&self) -> Location {
         match self {
-            Node::NumIntegral(n) => n.loc,
-            Node::NumRational(sym) => sym.loc,
+            Node::NumIntegral { value, .. } => value.loc,
+            Node::NumRational { value, .. } => value.loc,
             Node::Bool(b) => b.loc,
+            Node::Str(s) => s.loc,
             Node::Id(sym) => sym.loc,
             Node::Block(blk) => blk.loc,
             Node::Parens(node) => node.loc,
+            Node::Array(elems) => elems.loc,
+            Node::Index { index, .. } => index.loc,
+            Node::Path { name, .. } => name.loc,
+            Node::Call { args, .. } => args.loc,
+            Node::Match { arms, .. } => arms.loc,
+            Node::ArrayType { len, .. } => len.loc,
+            Node::Enum(variants) => variants.loc,
+            Node::GenericType { name, .. } => name.loc,
+            Node::If { cond, .. } => cond.loc,
+            Node::While { cond, .. } => cond.loc,
+            Node::Loop(body) => body.loc,
+            Node::Break(value) => value.loc,
+            Node::Continue(loc) => *loc,
+            Node::Return(value) => value.loc,
+            Node::Binary { op, .. } => op.loc,
+            Node::Unary { op, .. } => op.loc,
             Node::Local { name, .. } => name.loc,
+            Node::Assign { value, .. } => value.loc,
             Node::Fn { name, .. } => name.loc,
+            Node::ExternFn { name, .. } => name.loc,
+            Node::Type { name, .. } => name.loc,
+            Node::Const { name, .. } => name.loc,
             Node::Scope { vis, .. } => vis.loc,
+            Node::Attrs { node, .. } => node.primary_location(),
         }
     }
     /// Returns the name of this AST node.
     pub fn name(&self) -> &'static str {
         match self {
-            Node::NumIntegral(..) => "num-integral",
-            Node::NumRational(..) => "num-rational",
+            Node::NumIntegral { .. } => "num-integral",
+            Node::NumRational { .. } => "num-rational",
             Node::Bool(..) => "bool",
+            Node::Str(..) => "str",
             Node::Id(..) => "id",
             Node::Block(..) => "block",
             Node::Parens(..) => "parens",
+            Node::Array(..) => "array",
+            Node::Index { .. } => "index",
+            Node::Path { .. } => "path",
+            Node::Call { .. } => "call",
+            Node::Match { .. } => "match",
+            Node::ArrayType { .. } => "array-type",
+            Node::Enum(..) => "enum",
+            Node::GenericType { .. } => "generic-type",
+            Node::If { .. } => "if",
+            Node::While { .. } => "while",
+            Node::Loop(..) => "loop",
+            Node::Break(..) => "break",
+            Node::Continue(..) => "continue",
+            Node::Return(..) => "return",
+            Node::Binary { .. } => "binary",
+            Node::Unary { .. } => "unary",
             Node::Local { .. } => "local",
+            Node::Assign { .. } => "assign",
             Node::Fn { .. } => "fn",
+            Node::ExternFn { .. } => "extern-fn",
+            Node::Type { .. } => "type",
+            Node::Const { .. } => "const",
             Node::Scope { .. } => "scope",
+            Node::Attrs { .. } => "attrs",
         }
     }
 }
@@ -90,18 +335,28 @@ pub fn debug_write_ast<W : io::Write>(
     printer.clear_style()?;
     let indent = 2;
     match ast_node {
-        Node::NumIntegral(n) => {
-            debug_write_located(printer, files, n)?;
+        Node::NumIntegral { value, suffix } => {
+            debug_write_located(printer, files, value)?;
+            if let Some(suffix) = suffix {
+                debug_write_located(printer, files, suffix)?;
+            }
             printer.write("\n")?;
         },
-        Node::NumRational(sym) => {
-            debug_write_located(printer, files, sym)?;
+        Node::NumRational { value, suffix } => {
+            debug_write_located(printer, files, value)?;
+            if let Some(suffix) = suffix {
+                debug_write_located(printer, files, suffix)?;
+            }
             printer.write("\n")?;
         },
         Node::Bool(b) => {
             debug_write_located(printer, files, b)?;
             printer.write("\n")?;
         },
+        Node::Str(s) => {
+            debug_write_located(printer, files, s)?;
+            printer.write("\n")?;
+        },
         Node::Id(sym) => {
             debug_write_located(printer, files, sym)?;
             printer.write("\n")?;
@@ -122,22 +377,223 @@ pub fn debug_write_ast<W : io::Write>(
             debug_write_ast(printer, files, &node.value)?;
             printer.indent_pop();
         },
-        Node::Local { name, init } => {
+        Node::Array(elems) => {
+            debug_write_location(printer, files, &elems.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            for node in &elems.value {
+                debug_write_ast(printer, files, node)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Index { target, index } => {
+            debug_write_location(printer, files, &index.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, target)?;
+            debug_write_ast(printer, files, &index.value)?;
+            printer.indent_pop();
+        },
+        Node::Path { ty, name } => {
+            debug_write_located(printer, files, ty)?;
             debug_write_located(printer, files, name)?;
             printer.write("\n")?;
-            if let Some(node) = init.as_ref() {
+        },
+        Node::Call { callee, args } => {
+            debug_write_location(printer, files, &args.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, callee)?;
+            for node in &args.value {
+                debug_write_ast(printer, files, node)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Match { scrutinee, arms } => {
+            debug_write_location(printer, files, &arms.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, scrutinee)?;
+            for arm in &arms.value {
+                printer.write_style(Decoration::Bold)?;
+                printer.write("case")?;
+                printer.clear_style()?;
+                printer.write("\n")?;
                 printer.indent_push_relative(indent);
-                debug_write_ast(printer, files, &node)?;
+                debug_write_ast(printer, files, &arm.pattern)?;
+                debug_write_ast(printer, files, &arm.body)?;
                 printer.indent_pop();
             }
+            printer.indent_pop();
+        },
+        Node::If { cond, then_branch, else_branch } => {
+            debug_write_location(printer, files, &cond.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, &cond.value)?;
+            debug_write_ast(printer, files, then_branch)?;
+            if let Some(else_branch) = else_branch {
+                debug_write_ast(printer, files, else_branch)?;
+            }
+            printer.indent_pop();
         },
-        Node::Fn { name, body } => {
+        Node::While { cond, body } => {
+            debug_write_location(printer, files, &cond.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, &cond.value)?;
+            debug_write_ast(printer, files, body)?;
+            printer.indent_pop();
+        },
+        Node::Loop(body) => {
+            debug_write_location(printer, files, &body.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, &body.value)?;
+            printer.indent_pop();
+        },
+        Node::Break(value) | Node::Return(value) => {
+            debug_write_location(printer, files, &value.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            if let Some(node) = value.value.as_ref() {
+                debug_write_ast(printer, files, node)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Continue(loc) => {
+            debug_write_location(printer, files, loc)?;
+            printer.write("\n")?;
+        },
+        Node::Binary { op, lhs, rhs } => {
+            debug_write_located(printer, files, op)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, lhs)?;
+            debug_write_ast(printer, files, rhs)?;
+            printer.indent_pop();
+        },
+        Node::Unary { op, value } => {
+            debug_write_located(printer, files, op)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, value)?;
+            printer.indent_pop();
+        },
+        Node::ArrayType { len, elem } => {
+            debug_write_location(printer, files, &len.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, &len.value)?;
+            debug_write_ast(printer, files, elem)?;
+            printer.indent_pop();
+        },
+        Node::GenericType { name, args } => {
+            debug_write_located(printer, files, name)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            for arg in &args.value {
+                debug_write_ast(printer, files, arg)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Enum(variants) => {
+            debug_write_location(printer, files, &variants.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            for variant in &variants.value {
+                printer.write_style(Decoration::Bold)?;
+                printer.write("variant")?;
+                printer.clear_style()?;
+                debug_write_located(printer, files, &variant.name)?;
+                printer.write("\n")?;
+                printer.indent_push_relative(indent);
+                for field in &variant.fields {
+                    debug_write_ast(printer, files, field)?;
+                }
+                printer.indent_pop();
+            }
+            printer.indent_pop();
+        },
+        Node::Local { name, mutable, ty, init, .. } => {
+            debug_write_located(printer, files, name)?;
+            if *mutable {
+                printer.write(" mut")?;
+            }
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            if let Some(node) = ty.as_ref() {
+                debug_write_ast(printer, files, node)?;
+            }
+            if let Some(node) = init.as_ref() {
+                debug_write_ast(printer, files, node)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Assign { target, value } => {
+            debug_write_location(printer, files, &value.loc)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, target)?;
+            debug_write_ast(printer, files, &value.value)?;
+            printer.indent_pop();
+        },
+        Node::Fn { name, params, ret, body } => {
             debug_write_located(printer, files, name)?;
             printer.write("\n")?;
             printer.indent_push_relative(indent);
+            for param in &params.value {
+                printer.write_style(Decoration::Bold)?;
+                printer.write("param")?;
+                printer.clear_style()?;
+                debug_write_located(printer, files, &param.name)?;
+                printer.write("\n")?;
+                printer.indent_push_relative(indent);
+                debug_write_ast(printer, files, &param.ty)?;
+                printer.indent_pop();
+            }
+            if let Some(ret) = ret {
+                debug_write_ast(printer, files, ret)?;
+            }
             debug_write_ast(printer, files, &body)?;
             printer.indent_pop();
         },
+        Node::ExternFn { name, params, ret } => {
+            debug_write_located(printer, files, name)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            for param in &params.value {
+                printer.write_style(Decoration::Bold)?;
+                printer.write("param")?;
+                printer.clear_style()?;
+                debug_write_located(printer, files, &param.name)?;
+                printer.write("\n")?;
+                printer.indent_push_relative(indent);
+                debug_write_ast(printer, files, &param.ty)?;
+                printer.indent_pop();
+            }
+            if let Some(ret) = ret {
+                debug_write_ast(printer, files, ret)?;
+            }
+            printer.indent_pop();
+        },
+        Node::Type { name, def } => {
+            debug_write_located(printer, files, name)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            debug_write_ast(printer, files, def)?;
+            printer.indent_pop();
+        },
+        Node::Const { name, ty, value } => {
+            debug_write_located(printer, files, name)?;
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            if let Some(node) = ty.as_ref() {
+                debug_write_ast(printer, files, node)?;
+            }
+            debug_write_ast(printer, files, value)?;
+            printer.indent_pop();
+        },
         Node::Scope { vis, node } => {
             debug_write_located(printer, files, vis)?;
             printer.write("\n")?;
@@ -145,6 +601,24 @@ pub fn debug_write_ast<W : io::Write>(
             debug_write_ast(printer, files, &node)?;
             printer.indent_pop();
         },
+        Node::Attrs { attrs, node } => {
+            printer.write("\n")?;
+            printer.indent_push_relative(indent);
+            for attr in attrs {
+                printer.write_style(Decoration::Bold)?;
+                printer.write("attr")?;
+                printer.clear_style()?;
+                debug_write_located(printer, files, &attr.name)?;
+                printer.write("\n")?;
+                printer.indent_push_relative(indent);
+                for arg in &attr.args {
+                    debug_write_ast(printer, files, arg)?;
+                }
+                printer.indent_pop();
+            }
+            debug_write_ast(printer, files, node)?;
+            printer.indent_pop();
+        },
     }
     Ok(())
 }
@@ -1,10 +1,12 @@
 pub mod lex;
 
+use std::num::IntErrorKind;
+
 use lex::Token;
 
 use crate::ir::ast;
-use crate::src::{ Span, Located, SourceFile };
-use crate::error::{ IssueManager, Diagnostic };
+use crate::src::{ Span, Located, SourceFile, Message };
+use crate::error::{ IssueManager, Diagnostic, ErrorCode, Applicability };
 
 /// Parses the contents of a Cosy source file into an untyped AST.
 ///
@@ -34,8 +36,13 @@ impl<'a> Parser<'a> {
             | Token::End
             | Token::Else
             | Token::Local
+            | Token::Const
             | Token::Fn
+            | Token::Extern
             | Token::Mod
+            | Token::Type
+            | Token::Case
+            | Token::Hash
             | Token::EoF
         ) {
             self.lexer.next();
@@ -48,16 +55,18 @@ impl<'a> Parser<'a> {
             return Some((span, got));
         }
         Diagnostic::error()
-            .message(("expected {}, got {}", [expected.into(), got.into()]))
+            .code(ErrorCode::E0011)
+            .message(msg!("parse-expected-token", [expected.into(), got.into()]))
             .label(self.file.location(&span))
             .report(self.issues);
         None
     }
 
-    fn assert(&mut self, message : &str) -> Option<()> {
+    fn assert(&mut self, message : Message) -> Option<()> {
         let (span, got) = self.lexer.next();
         Diagnostic::error()
-            .message(("{}, got {}", [message.into(), got.into()]))
+            .code(ErrorCode::E0011)
+            .message(msg!("parse-unexpected-token", [message.into(), got.into()]))
             .label(self.file.location(&span))
             .report(self.issues);
         None
@@ -75,7 +84,7 @@ impl<'a> Parser<'a> {
                     continue;
                 }
             } else {
-                self.assert("unexpected symbol in declaration scope");
+                self.assert(msg!("parse-unexpected-in-decl-scope"));
                 continue;
             };
             items.push(decl);
@@ -85,38 +94,223 @@ impl<'a> Parser<'a> {
     }
 
     fn try_parse_decl(&mut self) -> Option<Option<ast::Node>> {
-        let node = if let Token::Fn = self.lexer.peek() {
+        let node = if let Token::Hash = self.lexer.peek() {
+            let Some(attrs) = self.parse_attrs() else { return Some(None) };
+            let node = match self.try_parse_decl() {
+                Some(Some(node)) => node,
+                Some(None) => return Some(None),
+                None => {
+                    self.assert(msg!("parse-expected-decl-after-attrs"));
+                    return Some(None);
+                },
+            };
+            Some(ast::Node::Attrs { attrs, node : Box::new(node) })
+        } else if let Token::Fn = self.lexer.peek() {
             self.lexer.next();
             // get function signature
             let name = self.parse_id()?;
-            self.assert_token(Token::LParen)?;
-            self.assert_token(Token::RParen)?;
+            let params = self.parse_params()?;
+            let ret = if let Token::Colon = self.lexer.peek() {
+                self.lexer.next();
+                Some(Box::new(self.parse_type()?))
+            } else {
+                None
+            };
             // get function body
             self.assert_token(Token::Do)?;
             let body = Box::new(self.parse_expr_block()?);
             self.assert_token(Token::End)?;
-            Some(ast::Node::Fn { name, body })
+            Some(ast::Node::Fn { name, params, ret, body })
+        } else if let Token::Extern = self.lexer.peek() {
+            self.lexer.next();
+            self.assert_token(Token::Fn)?;
+            // extern functions only have a signature
+            let name = self.parse_id()?;
+            let params = self.parse_params()?;
+            let ret = if let Token::Colon = self.lexer.peek() {
+                self.lexer.next();
+                Some(Box::new(self.parse_type()?))
+            } else {
+                None
+            };
+            Some(ast::Node::ExternFn { name, params, ret })
+        } else if let Token::Type = self.lexer.peek() {
+            self.lexer.next();
+            let name = self.parse_id()?;
+            self.assert_token(Token::Equal)?;
+            let def = Box::new(self.parse_type_def()?);
+            Some(ast::Node::Type { name, def })
+        } else if let Token::Const = self.lexer.peek() {
+            self.lexer.next();
+            let name = self.parse_id()?;
+            let ty = if let Token::Colon = self.lexer.peek() {
+                self.lexer.next();
+                Some(Box::new(self.parse_type()?))
+            } else {
+                None
+            };
+            self.assert_token(Token::Equal)?;
+            let value = Box::new(self.parse_expr()?);
+            Some(ast::Node::Const { name, ty, value })
         } else {
             return None;
         };
         Some(node)
     }
 
+    /// Parses the parameter list of a function, e.g. `(x : int, y : bool)`.
+    fn parse_params(&mut self) -> Option<Located<Vec<ast::Param>>> {
+        let (span_start, _) = self.assert_token(Token::LParen)?;
+        let mut params = Vec::new();
+        while !matches!(self.lexer.peek(), Token::RParen) {
+            let name = self.parse_id()?;
+            self.assert_token(Token::Colon)?;
+            let ty = self.parse_type()?;
+            params.push(ast::Param { name, ty });
+            if let Token::Comma = self.lexer.peek() {
+                self.lexer.next();
+            } else {
+                break;
+            }
+        }
+        let (span_end, _) = self.assert_token(Token::RParen)?;
+        let span = span_start.join(&span_end);
+        Some(self.make_dbg(&span, params))
+    }
+
+    fn parse_type_def(&mut self) -> Option<ast::Node> {
+        if let Token::Enum = self.lexer.peek() {
+            let (span_start, _) = self.lexer.next();
+            self.assert_token(Token::LBrace)?;
+            let mut variants = Vec::new();
+            while !matches!(self.lexer.peek(), Token::RBrace) {
+                let name = self.parse_id()?;
+                let fields = if let Token::LParen = self.lexer.peek() {
+                    self.lexer.next();
+                    self.parse_type_list(Token::RParen)?.0
+                } else {
+                    Vec::new()
+                };
+                variants.push(ast::Variant { name, fields });
+                if let Token::Comma = self.lexer.peek() {
+                    self.lexer.next();
+                } else if !self.lexer.peek_linebreak() {
+                    break;
+                }
+            }
+            let (span_end, _) = self.assert_token(Token::RBrace)?;
+            let span = span_start.join(&span_end);
+            Some(ast::Node::Enum(self.make_dbg(&span, variants)))
+        } else {
+            self.parse_type()
+        }
+    }
+
+    fn parse_type(&mut self) -> Option<ast::Node> {
+        if let Token::LBox = self.lexer.peek() {
+            let (span_start, _) = self.lexer.next();
+            let len = self.parse_expr()?;
+            let (span_end, _) = self.assert_token(Token::RBox)?;
+            let span = span_start.join(&span_end);
+            let elem = self.parse_type()?;
+            Some(ast::Node::ArrayType {
+                len : self.make_dbg(&span, Box::new(len)),
+                elem : Box::new(elem),
+            })
+        } else if let Token::Id | Token::IdRaw { .. } = self.lexer.peek() {
+            let name = self.parse_id()?;
+            if let Token::LBox = self.lexer.peek() {
+                let (span_start, _) = self.lexer.next();
+                let (args, span_end) = self.parse_type_list(Token::RBox)?;
+                let span = span_start.join(&span_end);
+                let args = self.make_dbg(&span, args);
+                Some(ast::Node::GenericType { name, args })
+            } else {
+                Some(ast::Node::Id(name))
+            }
+        } else {
+            self.assert(msg!("parse-expected-type"))?;
+            None
+        }
+    }
+
+    /// Parses a comma-separated list of types, up to and including the closing
+    /// token `close`. Returns the types and the span of `close`.
+    fn parse_type_list(&mut self, close : Token) -> Option<(Vec<ast::Node>, Span)> {
+        let mut types = Vec::new();
+        while *self.lexer.peek() != close {
+            types.push(self.parse_type()?);
+            if let Token::Comma = self.lexer.peek() {
+                self.lexer.next();
+            } else {
+                break;
+            }
+        }
+        let (span_end, _) = self.assert_token(close)?;
+        Some((types, span_end))
+    }
+
+    fn parse_attrs(&mut self) -> Option<Vec<ast::Attribute>> {
+        let mut attrs = Vec::new();
+        while let Token::Hash = self.lexer.peek() {
+            let (span_start, _) = self.lexer.next();
+            self.assert_token(Token::LBox)?;
+            let name = self.parse_id()?;
+            let args = if let Token::LParen = self.lexer.peek() {
+                self.lexer.next();
+                self.parse_expr_list(Token::RParen)?.0
+            } else {
+                Vec::new()
+            };
+            let (span_end, _) = self.assert_token(Token::RBox)?;
+            let loc = self.file.location(&span_start.join(&span_end));
+            attrs.push(ast::Attribute { name, args, loc });
+        }
+        Some(attrs)
+    }
+
     fn parse_stmt(&mut self) -> Option<ast::Node> {
-        if let Some(decl) = self.try_parse_decl() {
+        if let Token::Hash = self.lexer.peek() {
+            let attrs = self.parse_attrs()?;
+            let node = self.parse_stmt()?;
+            Some(ast::Node::Attrs { attrs, node : Box::new(node) })
+        } else if let Some(decl) = self.try_parse_decl() {
             decl
         } else if let Token::Local = self.lexer.peek() {
-            self.lexer.next();
+            let (span_start, _) = self.lexer.next();
+            let mutable = if let Token::Mut = self.lexer.peek() {
+                self.lexer.next();
+                true
+            } else {
+                false
+            };
             let name = self.parse_id()?;
+            let ty = if let Token::Colon = self.lexer.peek() {
+                self.lexer.next();
+                Some(Box::new(self.parse_type()?))
+            } else {
+                None
+            };
             let init = if let Token::Equal = self.lexer.peek() {
                 self.lexer.next();
                 Some(Box::new(self.parse_expr()?))
             } else {
                 None
             };
-            Some(ast::Node::Local { name, init })
+            let loc = self.file.location(&span_start.join(self.lexer.prev_span()));
+            Some(ast::Node::Local { name, mutable, ty, init, loc })
         } else {
-            self.parse_expr()
+            let expr = self.parse_expr()?;
+            if let Token::Equal = self.lexer.peek() && !self.lexer.peek_linebreak() {
+                let (span, _) = self.lexer.next();
+                let value = self.parse_expr()?;
+                Some(ast::Node::Assign {
+                    target : Box::new(expr),
+                    value : self.make_dbg(&span, Box::new(value)),
+                })
+            } else {
+                Some(expr)
+            }
         }
     }
 
@@ -130,8 +324,142 @@ impl<'a> Parser<'a> {
             let expr = self.parse_expr_block()?;
             self.assert_token(Token::End)?;
             Some(expr)
+        } else if let Token::Match = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            let scrutinee = Box::new(self.parse_expr()?);
+            let mut arms = Vec::new();
+            while let Token::Case = self.lexer.peek() {
+                self.lexer.next();
+                let pattern = self.parse_expr()?;
+                self.assert_token(Token::Then)?;
+                let body = self.parse_expr_block()?;
+                arms.push(ast::MatchArm { pattern, body });
+            }
+            self.assert_token(Token::End)?;
+            let arms = self.make_dbg(&span, arms);
+            Some(ast::Node::Match { scrutinee, arms })
+        } else if let Token::If = self.lexer.peek() {
+            let expr = self.parse_if_chain()?;
+            self.assert_token(Token::End)?;
+            Some(expr)
+        } else if let Token::Loop = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            self.assert_token(Token::Do)?;
+            let body = self.parse_expr_block()?;
+            self.assert_token(Token::End)?;
+            Some(ast::Node::Loop(self.make_dbg(&span, Box::new(body))))
+        } else if let Token::Break = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            let value = self.parse_expr_opt()?;
+            Some(ast::Node::Break(self.make_dbg(&span, value)))
+        } else if let Token::Continue = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            Some(ast::Node::Continue(self.file.location(&span)))
+        } else if let Token::Return = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            let value = self.parse_expr_opt()?;
+            Some(ast::Node::Return(self.make_dbg(&span, value)))
+        } else if let Token::While = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            let cond = self.parse_expr()?;
+            self.assert_token(Token::Do)?;
+            let body = self.parse_expr_block()?;
+            self.assert_token(Token::End)?;
+            Some(ast::Node::While {
+                cond : self.make_dbg(&span, Box::new(cond)),
+                body : Box::new(body),
+            })
+        } else {
+            self.parse_expr_binary(0)
+        }
+    }
+
+    /// Parses the optional value of a `break` or `return` expression, which
+    /// must start on the same line as the keyword.
+    fn parse_expr_opt(&mut self) -> Option<Option<Box<ast::Node>>> {
+        if self.lexer.peek_linebreak() || matches!(self.lexer.peek(),
+            | Token::End
+            | Token::Else
+            | Token::Case
+            | Token::Then
+            | Token::Do
+            | Token::RParen
+            | Token::RBox
+            | Token::Comma
+            | Token::EoF
+        ) {
+            return Some(None);
+        }
+        Some(Some(Box::new(self.parse_expr()?)))
+    }
+
+    /// Parses an `if` expression, and any `else if` branches which follow
+    /// it. The chain shares a single closing `end`, which isn't consumed.
+    fn parse_if_chain(&mut self) -> Option<ast::Node> {
+        let (span, _) = self.assert_token(Token::If)?;
+        let cond = self.parse_expr()?;
+        self.assert_token(Token::Then)?;
+        let then_branch = self.parse_expr_block()?;
+        let else_branch = if let Token::Else = self.lexer.peek() {
+            self.lexer.next();
+            if let Token::If = self.lexer.peek() {
+                Some(Box::new(self.parse_if_chain()?))
+            } else {
+                Some(Box::new(self.parse_expr_block()?))
+            }
+        } else {
+            None
+        };
+        Some(ast::Node::If {
+            cond : self.make_dbg(&span, Box::new(cond)),
+            then_branch : Box::new(then_branch),
+            else_branch,
+        })
+    }
+
+    /// Parses binary operators using precedence climbing, only accepting
+    /// operators whose precedence is at least `min_prec`.
+    fn parse_expr_binary(&mut self, min_prec : u8) -> Option<ast::Node> {
+        let mut lhs = self.parse_expr_unary()?;
+        while let Some(op) = binary_op(self.lexer.peek()) {
+            // operators at the start of a line continue the expression on
+            // the previous line, except for `-`, which could be a negation
+            if op.precedence() < min_prec
+                    || (op == ast::BinaryOp::Sub && self.lexer.peek_linebreak()) {
+                break;
+            }
+            let (span, _) = self.lexer.next();
+            let rhs = self.parse_expr_binary(op.precedence() + 1)?;
+            lhs = ast::Node::Binary {
+                op : self.make_dbg(&span, op),
+                lhs : Box::new(lhs),
+                rhs : Box::new(rhs),
+            };
+        }
+        Some(lhs)
+    }
+
+    fn parse_expr_unary(&mut self) -> Option<ast::Node> {
+        let op = match self.lexer.peek() {
+            Token::Minus => ast::UnaryOp::Neg,
+            Token::Not => ast::UnaryOp::Not,
+            _ => return self.parse_expr_attrs(),
+        };
+        let (span, _) = self.lexer.next();
+        let value = self.parse_expr_unary()?;
+        Some(ast::Node::Unary {
+            op : self.make_dbg(&span, op),
+            value : Box::new(value),
+        })
+    }
+
+    fn parse_expr_attrs(&mut self) -> Option<ast::Node> {
+        if let Token::Hash = self.lexer.peek() {
+            let attrs = self.parse_attrs()?;
+            let node = self.parse_expr_attrs()?;
+            Some(ast::Node::Attrs { attrs, node : Box::new(node) })
         } else {
-            self.parse_expr_terminal()
+            self.parse_expr_postfix()
         }
     }
 
@@ -141,6 +469,7 @@ impl<'a> Parser<'a> {
         while !matches!(self.lexer.peek(),
             | Token::End
             | Token::Else
+            | Token::Case
             | Token::EoF
         ) {
             if let Some(stmt) = self.parse_stmt() {
@@ -153,24 +482,185 @@ impl<'a> Parser<'a> {
         Some(ast::Node::Block(self.make_dbg(&span, stmts)))
     }
 
+    fn parse_expr_postfix(&mut self) -> Option<ast::Node> {
+        let mut expr = self.parse_expr_terminal()?;
+        loop {
+            // a `[` on a new line starts a new array, not an index
+            if self.lexer.peek_linebreak() {
+                break;
+            }
+            if let Token::LBox = self.lexer.peek() {
+                let (span_start, _) = self.lexer.next();
+                let index = self.parse_expr()?;
+                let (span_end, _) = self.assert_token(Token::RBox)?;
+                let span = span_start.join(&span_end);
+                expr = ast::Node::Index {
+                    target : Box::new(expr),
+                    index : self.make_dbg(&span, Box::new(index)),
+                };
+            } else if let Token::LParen = self.lexer.peek() {
+                let (span_start, _) = self.lexer.next();
+                let (args, span_end) = self.parse_expr_list(Token::RParen)?;
+                let span = span_start.join(&span_end);
+                expr = ast::Node::Call {
+                    callee : Box::new(expr),
+                    args : self.make_dbg(&span, args),
+                };
+            } else {
+                break;
+            }
+        }
+        Some(expr)
+    }
+
+    /// Splits a number token into the span of its digits and its type suffix,
+    /// if one exists.
+    fn split_num_suffix(&self, span : &Span) -> (Span, Option<Located<ast::Symbol>>) {
+        let src = span.slice(&self.file.src);
+        let mut chars = src.char_indices().peekable();
+        let offset = loop {
+            match chars.next() {
+                // skip the exponent of numbers in scientific notation
+                Some((_, 'e' | 'E')) if chars.peek().is_some_and(|(_, x)| {
+                    matches!(x, '+' | '-') || lex::is_digit(*x)
+                }) => (),
+                Some((offset, x)) if lex::is_alpha(x) => break offset,
+                Some(_) => (),
+                None => return (*span, None),
+            }
+        };
+        let digits = Span::new(span.start..span.start + offset);
+        let suffix = Span::new(span.start + offset..span.end);
+        let suffix_string = suffix.slice(&self.file.src).to_string();
+        (digits, Some(self.make_dbg(&suffix, suffix_string)))
+    }
+
+    /// Parses a radix literal of the form `<base>r<digits>`, e.g. `16rFF`,
+    /// with an optional type suffix, e.g. `16rFFu8`. The suffix starts at the
+    /// first letter which isn't a digit in the base.
+    ///
+    /// If the literal is invalid, an error is reported and `0` is used in its
+    /// place, so the rest of the expression can still be parsed.
+    fn parse_radix(&mut self, span : &Span) -> ast::Node {
+        let src = span.slice(&self.file.src);
+        let r = src.find(['r', 'R']).unwrap_or(src.len());
+        let base = src[..r].replace("_", "").parse::<u32>().ok()
+            .filter(|base| (2..=36).contains(base));
+        let suffix_start = base.and_then(|base| {
+            src.get(r + 1..)?.char_indices()
+                .find(|(_, x)| lex::is_alpha(*x) && x.to_digit(base).is_none())
+                .map(|(i, _)| span.start + r + 1 + i)
+        });
+        let (digits, suffix) = match suffix_start {
+            Some(offset) => {
+                let suffix = Span::new(offset..span.end);
+                let suffix_string = suffix.slice(&self.file.src).to_string();
+                (Span::new(span.start..offset), Some(self.make_dbg(&suffix, suffix_string)))
+            },
+            None => (*span, None),
+        };
+        let value = match base {
+            Some(base) => self.parse_radix_digits(&digits, r, base),
+            None => {
+                let base_span = Span::new(span.start..span.start + r);
+                Diagnostic::error()
+                    .code(ErrorCode::E0012)
+                    .message(msg!("parse-invalid-radix", [src[..r].into()]))
+                    .label((self.file.location(&base_span), msg!("parse-invalid-radix-label")))
+                    .report(self.issues);
+                None
+            },
+        };
+        ast::Node::NumIntegral { value : self.make_dbg(&digits, value.unwrap_or(0)), suffix }
+    }
+
+    /// Returns the value of the digits of a radix literal, which come after
+    /// the `r` at offset `r`. Reports an error if any digits are invalid or
+    /// the value is too large.
+    fn parse_radix_digits(&mut self, span : &Span, r : usize, base : u32) -> Option<u128> {
+        let src = span.slice(&self.file.src);
+        let mut value = Some(0u128);
+        let mut invalid = Vec::new();
+        for (i, x) in src[r + 1..].char_indices() {
+            if x == '_' {
+                continue;
+            }
+            let Some(digit) = x.to_digit(base) else {
+                let offset = span.start + r + 1 + i;
+                invalid.push((Span::new(offset..offset + x.len_utf8()), x));
+                continue;
+            };
+            value = value.and_then(|n| n.checked_mul(u128::from(base))?.checked_add(u128::from(digit)));
+        }
+        if !invalid.is_empty() {
+            let mut diagnostic = Diagnostic::error()
+                .code(ErrorCode::E0013)
+                .message(msg!("parse-invalid-digits", [base.into()]));
+            for (digit_span, x) in invalid {
+                diagnostic = diagnostic.label((self.file.location(&digit_span), msg!("parse-invalid-digit-label", [
+                    x.into(), base.into()
+                ])));
+            }
+            let digits = if base <= 10 {
+                msg!("parse-digit-range", [(base - 1).into()])
+            } else {
+                let last = char::from_digit(base - 1, base).unwrap_or('z');
+                msg!("parse-digit-range-letters", [last.into()])
+            };
+            diagnostic
+                .note(msg!("parse-valid-digits", [base.into(), digits.into()]))
+                .report(self.issues);
+            return None;
+        }
+        if value.is_none() {
+            Diagnostic::error()
+                .code(ErrorCode::E0014)
+                .message(msg!("parse-number-too-large"))
+                .label(self.file.location(span))
+                .report(self.issues);
+        }
+        value
+    }
+
     fn parse_expr_terminal(&mut self) -> Option<ast::Node> {
         if let Token::NumIntegral = self.lexer.peek() {
             let (span, _) = self.lexer.next();
+            let (span, suffix) = self.split_num_suffix(&span);
             let n_string = span.slice(&self.file.src).replace("_", "");
             match n_string.parse::<u128>() {
-                Ok(n) => Some(ast::Node::NumIntegral(self.make_dbg(&span, n))),
-                Err(err) => {
+                Ok(n) => Some(ast::Node::NumIntegral {
+                    value : self.make_dbg(&span, n),
+                    suffix,
+                }),
+                Err(err) if *err.kind() == IntErrorKind::PosOverflow => {
                     Diagnostic::error()
-                        .message(("{}", [err.into()]))
+                        .code(ErrorCode::E0014)
+                        .message(msg!("parse-number-too-large"))
+                        .label(self.file.location(&span))
+                        .report(self.issues);
+                    // continue parsing using a placeholder value
+                    Some(ast::Node::NumIntegral { value : self.make_dbg(&span, 0), suffix })
+                },
+                Err(_) => {
+                    // the lexer only produces digits and `_`
+                    Diagnostic::unreachable()
                         .label(self.file.location(&span))
                         .report(self.issues);
                     None
-                }
+                },
             }
         } else if let Token::NumRational = self.lexer.peek() {
             let (span, _) = self.lexer.next();
+            let (span, suffix) = self.split_num_suffix(&span);
             let n_string = span.slice(&self.file.src).replace("_", "");
-            Some(ast::Node::NumRational(self.make_dbg(&span, n_string)))
+            Some(ast::Node::NumRational { value : self.make_dbg(&span, n_string), suffix })
+        } else if let Token::NumRadix = self.lexer.peek() {
+            let (span, _) = self.lexer.next();
+            Some(self.parse_radix(&span))
+        } else if let Token::Str { unclosed } = self.lexer.peek() {
+            let unclosed = *unclosed;
+            let (span, _) = self.lexer.next();
+            Some(self.parse_str(&span, unclosed))
         } else if let Token::Bool(b) = self.lexer.peek() {
             let b = *b;
             let (span, _) = self.lexer.next();
@@ -181,23 +671,116 @@ impl<'a> Parser<'a> {
             let (span_end, _) = self.assert_token(Token::RParen)?;
             let span = span_start.join(&span_end);
             Some(ast::Node::Parens(self.make_dbg(&span, Box::new(expr))))
+        } else if let Token::LBox = self.lexer.peek() {
+            let (span_start, _) = self.lexer.next();
+            let (elems, span_end) = self.parse_expr_list(Token::RBox)?;
+            let span = span_start.join(&span_end);
+            Some(ast::Node::Array(self.make_dbg(&span, elems)))
         } else {
             let name = self.parse_id()?;
-            Some(ast::Node::Id(name))
+            if let Token::ColonColon = self.lexer.peek() {
+                self.lexer.next();
+                let ty = name;
+                let name = self.parse_id()?;
+                Some(ast::Node::Path { ty, name })
+            } else {
+                Some(ast::Node::Id(name))
+            }
         }
     }
 
+    /// Parses a comma-separated list of expressions, up to and including the
+    /// closing token `close`. Returns the expressions and the span of `close`.
+    fn parse_expr_list(&mut self, close : Token) -> Option<(Vec<ast::Node>, Span)> {
+        let mut exprs = Vec::new();
+        while *self.lexer.peek() != close {
+            exprs.push(self.parse_expr()?);
+            if let Token::Comma = self.lexer.peek() {
+                self.lexer.next();
+            } else {
+                break;
+            }
+        }
+        let (span_end, _) = self.assert_token(close)?;
+        Some((exprs, span_end))
+    }
+
+    /// Parses a string literal, replacing any escape sequences with the
+    /// characters they represent.
+    fn parse_str(&mut self, span : &Span, unclosed : bool) -> ast::Node {
+        let span_inner = if unclosed {
+            Diagnostic::error()
+                .code(ErrorCode::E0015)
+                .message(msg!("parse-unclosed-string"))
+                .label((
+                    self.file.location(span),
+                    msg!("parse-unclosed-string-label")
+                ))
+                .suggest(
+                    self.file.location(&Span::new(span.end..span.end)),
+                    "\"",
+                    Applicability::MachineApplicable,
+                    msg!("parse-unclosed-string-help"),
+                )
+                .report(self.issues);
+            span.shrink(1, 0)
+        } else {
+            span.shrink(1, 1)
+        };
+        let mut value = String::new();
+        let mut chars = self.lexer.slice(&span_inner).char_indices();
+        while let Some((i, x)) = chars.next() {
+            if x != '\\' {
+                value.push(x);
+                continue;
+            }
+            let escaped = chars.next().map(|(_, x)| x);
+            let unescaped = match escaped {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some(x @ ('\\' | '"' | '\'')) => x,
+                _ => {
+                    let start = span_inner.start + i;
+                    let end = start + 1 + escaped.map_or(0, char::len_utf8);
+                    Diagnostic::error()
+                        .code(ErrorCode::E0016)
+                        .message(msg!("parse-unknown-escape"))
+                        .label(self.file.location(&Span::new(start..end)))
+                        .note(msg!("parse-valid-escapes"))
+                        .suggest(
+                            self.file.location(&Span::new(start..start + 1)),
+                            "\\\\",
+                            Applicability::MaybeIncorrect,
+                            msg!("parse-unknown-escape-help"),
+                        )
+                        .report(self.issues);
+                    continue;
+                },
+            };
+            value.push(unescaped);
+        }
+        ast::Node::Str(self.make_dbg(span, value))
+    }
+
     fn parse_id(&mut self) -> Option<Located<ast::Symbol>> {
         let srcloc = if let Token::IdRaw { unclosed } = self.lexer.peek() {
             let unclosed = *unclosed;
             let (span, _) = self.lexer.next();
             let span_inner = if unclosed {
                 Diagnostic::error()
-                    .message("unclosed raw identifier")
+                    .code(ErrorCode::E0017)
+                    .message(msg!("parse-unclosed-raw-id"))
                     .label((
                         self.file.location(&span),
-                        "expected a closing '`' here".into()
+                        msg!("parse-unclosed-raw-id-label")
                     ))
+                    .suggest(
+                        self.file.location(&Span::new(span.end..span.end)),
+                        "`",
+                        Applicability::MachineApplicable,
+                        msg!("parse-unclosed-raw-id-help"),
+                    )
                     .report(self.issues);
                 span.shrink(1, 0)
             } else {
@@ -210,4 +793,25 @@ impl<'a> Parser<'a> {
         };
         Some(srcloc)
     }
-}
\ No newline at end of file
+}
+
+/// Returns the binary operator represented by a token, if one exists.
+fn binary_op(token : &Token) -> Option<ast::BinaryOp> {
+    let op = match token {
+        Token::Plus => ast::BinaryOp::Add,
+        Token::Minus => ast::BinaryOp::Sub,
+        Token::Star => ast::BinaryOp::Mul,
+        Token::Slash => ast::BinaryOp::Div,
+        Token::Percent => ast::BinaryOp::Rem,
+        Token::EqualEqual => ast::BinaryOp::Eq,
+        Token::BangEqual => ast::BinaryOp::Ne,
+        Token::Less => ast::BinaryOp::Lt,
+        Token::LessEqual => ast::BinaryOp::Le,
+        Token::Greater => ast::BinaryOp::Gt,
+        Token::GreaterEqual => ast::BinaryOp::Ge,
+        Token::And => ast::BinaryOp::And,
+        Token::Or => ast::BinaryOp::Or,
+        _ => return None,
+    };
+    Some(op)
+}
@@ -18,6 +18,7 @@ pub struct Lexer<'a> {
     cursor : Cursor<'a>,
     peeked : TokenSpan,
     peeked_linebreak : bool,
+    prev_span : Span,
 }
 
 impl<'a> Lexer<'a> {
@@ -25,14 +26,17 @@ impl<'a> Lexer<'a> {
     pub fn new(src : &'a str) -> Lexer<'a> {
         let mut cursor = Cursor::new(src);
         let (peeked, peeked_linebreak) = Self::read_token_no_whitespace(&mut cursor);
-        Self { cursor, peeked, peeked_linebreak }
+        let prev_span = Span::new(0..0);
+        Self { cursor, peeked, peeked_linebreak, prev_span }
     }
 
     /// Advances the lexer, returning the most recently peeked token.
     pub fn next(&mut self) -> TokenSpan {
         let (peeked, is_break) = Self::read_token_no_whitespace(&mut self.cursor);
         self.peeked_linebreak = is_break;
-        mem::replace(&mut self.peeked, peeked)
+        let token = mem::replace(&mut self.peeked, peeked);
+        self.prev_span = token.0;
+        token
     }
 
     fn read_token_no_whitespace(cursor : &mut Cursor<'a>) -> (TokenSpan, bool) {
@@ -68,6 +72,9 @@ impl<'a> Lexer<'a> {
     /// Returns the span of the currently peeked token.
     pub fn peek_span(&self) -> &Span { &self.peeked.0 }
 
+    /// Returns the span of the most recently consumed token.
+    pub fn prev_span(&self) -> &Span { &self.prev_span }
+
     /// Returns whether the there is a line break before the peeked token.
     ///
     /// This is Cosy's way of handling automatic semicolon insertion.
@@ -123,7 +130,38 @@ impl<'a> Cursor<'a> {
             ']' => Token::RBox,
             '{' => Token::LBrace,
             '}' => Token::RBrace,
-            '=' => Token::Equal,
+            '=' => {
+                if self.peek_1.1 == '=' {
+                    self.next();
+                    Token::EqualEqual
+                } else {
+                    Token::Equal
+                }
+            },
+            '!' if self.peek_1.1 == '=' => {
+                self.next();
+                Token::BangEqual
+            },
+            '<' => {
+                if self.peek_1.1 == '=' {
+                    self.next();
+                    Token::LessEqual
+                } else {
+                    Token::Less
+                }
+            },
+            '>' => {
+                if self.peek_1.1 == '=' {
+                    self.next();
+                    Token::GreaterEqual
+                } else {
+                    Token::Greater
+                }
+            },
+            '+' => Token::Plus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '%' => Token::Percent,
             ':' => {
                 if self.peek_1.1 == ':' {
                     self.next();
@@ -142,6 +180,7 @@ impl<'a> Cursor<'a> {
                 }
             },
             ',' => Token::Comma,
+            '#' => Token::Hash,
             ';' if self.peek_1.1 == ';' => {
                 self.next();
                 Token::LineBreak { implicit : false }
@@ -151,6 +190,7 @@ impl<'a> Cursor<'a> {
                 self.next_while(|x| !(is_eol(x) || is_eof(x)));
                 Token::Comment
             },
+            '-' => Token::Minus,
             // identifiers
             x if x == '_' || is_alpha(x) => {
                 self.next_while(|x| x == '_' || is_alpha(x) || is_digit(x));
@@ -160,8 +200,25 @@ impl<'a> Cursor<'a> {
             '`' => {
                 self.next_while(|x| !(x == '`' || is_eol(x)));
                 let unclosed = self.peek_1.1 != '`';
+                if !unclosed {
+                    self.next();
+                }
                 Token::IdRaw { unclosed }
             },
+            // strings
+            '"' => {
+                while !(self.peek_1.1 == '"' || is_eol(self.peek_1.1) || is_eof(self.peek_1.1)) {
+                    if self.next().1 == '\\' && !(is_eol(self.peek_1.1) || is_eof(self.peek_1.1)) {
+                        // skip the escaped character, so `\"` doesn't end the string
+                        self.next();
+                    }
+                }
+                let unclosed = self.peek_1.1 != '"';
+                if !unclosed {
+                    self.next();
+                }
+                Token::Str { unclosed }
+            },
             // numbers
             x if is_digit(x) => {
                 let last_char = self.next_while(|x| x == '_' || is_digit(x));
@@ -172,14 +229,20 @@ impl<'a> Cursor<'a> {
                         '.' if is_digit(self.peek_2.1) => {
                             self.next();
                             self.next_while(|x| x == '_' || is_digit(x));
+                            self.read_num_exponent();
+                            self.read_num_suffix();
                             Token::NumRational
                         },
-                        'r' | 'R' if is_digit(self.peek_2.1) => {
+                        'r' | 'R' if is_digit_36(self.peek_2.1) => {
                             self.next();
                             self.next_while(|x| x == '_' || is_digit_36(x));
                             Token::NumRadix
                         },
-                        _ => Token::NumIntegral,
+                        _ => {
+                            let rational = self.read_num_exponent();
+                            self.read_num_suffix();
+                            if rational { Token::NumRational } else { Token::NumIntegral }
+                        },
                     }
                 }
             },
@@ -195,6 +258,33 @@ impl<'a> Cursor<'a> {
         let offset_end = self.peek_1.0;
         return (Span::new(offset_start..offset_end), token);
     }
+
+    /// Reads the exponent of a number literal in scientific notation, e.g.
+    /// the `e-3` in `1.5e-3`. Returns whether there was an exponent.
+    fn read_num_exponent(&mut self) -> bool {
+        if !matches!(self.peek_1.1, 'e' | 'E') {
+            return false;
+        }
+        let has_digits = match self.peek_2.1 {
+            '+' | '-' => self.chars.clone().next().is_some_and(|(_, x)| is_digit(x)),
+            x => is_digit(x),
+        };
+        if !has_digits {
+            return false;
+        }
+        self.next();
+        self.next();
+        self.next_while(|x| x == '_' || is_digit(x));
+        true
+    }
+
+    /// Reads the type suffix of a number literal, e.g. the `u8` in `255u8`.
+    /// The suffix is included in the span of the number token.
+    fn read_num_suffix(&mut self) {
+        if is_alpha(self.peek_1.1) {
+            self.next_while(|x| x == '_' || is_alpha(x) || is_digit(x));
+        }
+    }
 }
 
 pub(super) fn is_eof(x : char) -> bool { x == '\0' }
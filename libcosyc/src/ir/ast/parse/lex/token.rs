@@ -12,6 +12,7 @@ pub enum Token {
     NumIntegral,
     NumRational,
     NumRadix,
+    Str { unclosed : bool },
     Bool(bool),
     // symbols
     LParen,
@@ -25,15 +26,44 @@ pub enum Token {
     ColonColon,
     Dot,
     Comma,
+    Hash,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    EqualEqual,
+    BangEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
     // keywords
     Do,
     End,
     Else,
     Local,
+    Const,
     Fn,
+    Extern,
     Mod,
     Pub,
     Where,
+    Type,
+    Enum,
+    Match,
+    Case,
+    Then,
+    Mut,
+    If,
+    While,
+    And,
+    Or,
+    Not,
+    Loop,
+    Break,
+    Continue,
+    Return,
     // miscellaneous
     Comment,
     LineBreak { implicit : bool },
@@ -50,6 +80,7 @@ impl Token {
             | Token::NumIntegral
             | Token::NumRational
             | Token::NumRadix => "number",
+            Token::Str { .. } => "string",
             Token::Bool(..) => "bool",
             Token::LParen => "`(`",
             Token::RParen => "`)`",
@@ -62,14 +93,43 @@ impl Token {
             Token::ColonColon => "`::`",
             Token::Dot => "`.`",
             Token::Comma => "`,`",
+            Token::Hash => "`#`",
+            Token::Plus => "`+`",
+            Token::Minus => "`-`",
+            Token::Star => "`*`",
+            Token::Slash => "`/`",
+            Token::Percent => "`%`",
+            Token::EqualEqual => "`==`",
+            Token::BangEqual => "`!=`",
+            Token::Less => "`<`",
+            Token::LessEqual => "`<=`",
+            Token::Greater => "`>`",
+            Token::GreaterEqual => "`>=`",
             Token::Do => "`do`",
             Token::End  => "`end`",
             Token::Else  => "`else`",
             Token::Local => "`local`",
+            Token::Const => "`const`",
             Token::Fn => "`fn`",
+            Token::Extern => "`extern`",
             Token::Mod => "`mod`",
             Token::Pub => "`pub`",
             Token::Where => "`where`",
+            Token::Type => "`type`",
+            Token::Enum => "`enum`",
+            Token::Match => "`match`",
+            Token::Case => "`case`",
+            Token::Then => "`then`",
+            Token::Mut => "`mut`",
+            Token::If => "`if`",
+            Token::While => "`while`",
+            Token::And => "`and`",
+            Token::Or => "`or`",
+            Token::Not => "`not`",
+            Token::Loop => "`loop`",
+            Token::Break => "`break`",
+            Token::Continue => "`continue`",
+            Token::Return => "`return`",
             Token::Comment => "comment",
             Token::LineBreak { implicit } => if *implicit { "new line" } else { "`;;`" },
             Token::LineContinue => "`...`",
@@ -84,10 +144,27 @@ impl Token {
             "end" => Token::End,
             "else" => Token::Else,
             "local" => Token::Local,
+            "const" => Token::Const,
             "fn" => Token::Fn,
+            "extern" => Token::Extern,
             "mod" => Token::Mod,
             "pub" => Token::Pub,
             "where" => Token::Where,
+            "type" => Token::Type,
+            "enum" => Token::Enum,
+            "match" => Token::Match,
+            "case" => Token::Case,
+            "then" => Token::Then,
+            "mut" => Token::Mut,
+            "if" => Token::If,
+            "while" => Token::While,
+            "and" => Token::And,
+            "or" => Token::Or,
+            "not" => Token::Not,
+            "loop" => Token::Loop,
+            "break" => Token::Break,
+            "continue" => Token::Continue,
+            "return" => Token::Return,
             "true" => Token::Bool(true),
             "false" => Token::Bool(false),
             _ => Token::Id,
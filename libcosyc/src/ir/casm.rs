@@ -1,3 +1,12 @@
+//! Cosy ASM, a low-level control-flow graph representation of a Cosy package.
+//! Every value is stored in a typed local slot, and control flow is made
+//! explicit using basic blocks.
+//!
+//! Heap values, such as strings, are reference counted. Each local holding a
+//! heap value owns one reference to it, which is released when the local is
+//! overwritten or goes out of scope. The retains and releases are explicit
+//! instructions, inserted when lowering from HIR.
+
 mod gen_llvm;
 
 pub mod lower;
@@ -5,13 +14,293 @@ pub mod lower;
 use std::io;
 use bincode;
 
-use crate::src::SourceMap;
+use crate::src::{ SourceMap, Location };
 #[allow(unused_imports)] use crate::pretty::{ PrettyPrinter, Colour, Decoration };
 
 pub use gen_llvm::emit_llvm;
+pub use crate::ir::hir::Symbol;
+
+/// A unique identifier for a local slot within a function.
+pub type LocalId = usize;
+
+/// A unique identifier for a basic block within a function.
+pub type BlockId = usize;
 
 #[derive(Debug, Default, bincode::Encode, bincode::Decode)]
-pub struct Package;
+pub struct Package {
+    pub functions : Vec<Function>,
+    pub externs : Vec<ExternFn>,
+    /// The names of the unit tests in this package, if it was built as a
+    /// test harness. The harness runs the test whose index is passed as its
+    /// first argument, instead of running `main`.
+    pub tests : Option<Vec<Symbol>>,
+}
+
+/// A function and its control-flow graph. The first block is the entry block.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct Function {
+    pub name : Symbol,
+    /// Whether this function should be exported using its unmangled name.
+    pub external : bool,
+    /// Whether this function should be considered for inlining.
+    pub inline : bool,
+    pub ret : Type,
+    /// The number of parameters of this function. The arguments of the
+    /// function are stored in the first `params` locals.
+    pub params : usize,
+    pub locals : Vec<Type>,
+    pub blocks : Vec<Block>,
+}
+
+/// A function defined outside of the package, which is called using the C
+/// calling convention.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct ExternFn {
+    /// The unmangled symbol name of the function.
+    pub name : Symbol,
+    pub params : Vec<Type>,
+    pub ret : Type,
+}
+
+/// A straight-line sequence of instructions, ending in a terminator which
+/// transfers control to another block.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct Block {
+    pub instrs : Vec<Instr>,
+    pub terminator : Terminator,
+}
+
+/// Machine-level types.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum Type {
+    Unit,
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+    Bool,
+    /// A pointer to a reference-counted, NUL-terminated string.
+    Str,
+    Array {
+        elem : Box<Type>,
+        len : u64,
+    },
+    /// A tagged union, storing the index of the current variant followed by
+    /// the fields of that variant.
+    Tagged {
+        variants : Vec<Vec<Type>>,
+    },
+}
+
+impl Type {
+    /// Returns whether this is an unsigned integer type.
+    pub fn is_unsigned(&self) -> bool {
+        matches!(self, Type::U8 | Type::U16 | Type::U32 | Type::U64)
+    }
+
+    /// Returns whether values of this type contain any reference-counted
+    /// heap values.
+    pub fn is_managed(&self) -> bool {
+        match self {
+            Type::Str => true,
+            Type::Array { elem, .. } => elem.is_managed(),
+            Type::Tagged { variants } => variants.iter().flatten().any(Type::is_managed),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub enum Instr {
+    /// Evaluates `value` and stores the result in the local `dest`.
+    Assign {
+        dest : LocalId,
+        value : Value,
+    },
+    /// Stores `value` in an element of the array stored in the local `array`,
+    /// without any bounds checking. Each operand of `indices` indexes into
+    /// the array found using the previous indices.
+    ///
+    /// If the element contains heap values, the old element is released and
+    /// the new element is retained.
+    Store {
+        array : LocalId,
+        indices : Vec<Operand>,
+        value : Operand,
+    },
+    /// Increments the reference count of every heap value stored in `local`.
+    Retain(LocalId),
+    /// Decrements the reference count of every heap value stored in `local`,
+    /// freeing any which are no longer referenced. The local is then cleared,
+    /// so releasing it again has no effect.
+    Release(LocalId),
+}
+
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub enum Value {
+    Use(Operand),
+    Array(Vec<Operand>),
+    /// Reads an element of an array without any bounds checking.
+    Index {
+        array : LocalId,
+        index : Operand,
+    },
+    /// Constructs a value of a tagged union type.
+    Variant {
+        variant : usize,
+        fields : Vec<Operand>,
+    },
+    /// Reads the index of the current variant of a tagged union as an `I64`.
+    Tag(LocalId),
+    /// Reads a field of a tagged union, assuming `variant` is its current
+    /// variant.
+    Field {
+        local : LocalId,
+        variant : usize,
+        field : usize,
+    },
+    /// Calls the function named `func`.
+    Call {
+        func : Symbol,
+        args : Vec<Operand>,
+        /// Where the function was called from, which is shown in stack
+        /// backtraces.
+        loc : Location,
+    },
+    /// Applies a binary operator to two operands of the same type.
+    Binary {
+        op : BinaryOp,
+        lhs : Operand,
+        rhs : Operand,
+    },
+    Unary {
+        op : UnaryOp,
+        value : Operand,
+    },
+    /// Converts a number to the numeric type of the destination.
+    Convert(Operand),
+    /// Concatenates two strings into a newly allocated string.
+    Concat {
+        lhs : Operand,
+        rhs : Operand,
+        /// Where the string was allocated, which is reported if the string
+        /// is never freed. Only tracked by debug builds.
+        site : Option<Location>,
+    },
+}
+
+impl Value {
+    /// Returns whether this value produces a new reference, which the
+    /// destination takes ownership of. Otherwise, the value is a copy of an
+    /// existing reference, and must be retained.
+    pub fn is_owned(&self) -> bool {
+        matches!(self, Value::Call { .. } | Value::Concat { .. })
+    }
+}
+
+/// Binary operators on integers, floats and booleans. Note: the logical
+/// operators `and` and `or` are represented using control flow instead.
+#[derive(Debug, Clone, Copy, bincode::Encode, bincode::Decode)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, Copy, bincode::Encode, bincode::Decode)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
+pub enum Operand {
+    Local(LocalId),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
+pub enum Const {
+    Unit,
+    /// An integer of type `ty`, truncated to the size of that type.
+    Int {
+        value : u64,
+        ty : Type,
+    },
+    Float {
+        value : f64,
+        ty : Type,
+    },
+    Bool(bool),
+    /// A string literal, stored as a global which is never freed.
+    Str(String),
+}
+
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub enum Terminator {
+    Return(Operand),
+    Jump(BlockId),
+    /// Jumps to the block paired with the integer or boolean value of
+    /// `value`, or to `otherwise` if there is no such block.
+    Switch {
+        value : Operand,
+        cases : Vec<(u64, BlockId)>,
+        otherwise : BlockId,
+    },
+    /// Marks a block which can never be executed.
+    Unreachable,
+    /// Checks that a condition holds at runtime, continuing to `next` if it
+    /// does. Otherwise, the program panics with a message pointing to `loc`.
+    Assert {
+        check : Check,
+        loc : Location,
+        next : BlockId,
+    },
+}
+
+/// Runtime checks inserted by the compiler.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub enum Check {
+    /// Checks that `0 <= index < len`.
+    Bounds {
+        index : Operand,
+        len : u64,
+    },
+    /// Checks that the divisor of an integer division is not zero.
+    DivByZero {
+        divisor : Operand,
+    },
+    /// Checks that the condition of an `assert` is true. The source text of
+    /// the condition is found using the location of the check.
+    Assert {
+        cond : Operand,
+    },
+    /// Checks that applying an arithmetic operator to two integers doesn't
+    /// overflow.
+    Overflow {
+        op : BinaryOp,
+        lhs : Operand,
+        rhs : Operand,
+    },
+    /// Checks that negating a signed integer doesn't overflow.
+    NegOverflow {
+        value : Operand,
+    },
+}
 
 /// Pretty prints Cosy ASM for debugging purposes.
 pub fn debug_write_casm<W : io::Write>(
@@ -21,4 +310,4 @@ pub fn debug_write_casm<W : io::Write>(
 ) -> io::Result<()> {
     printer.write(&format!("{:?}", package))?;
     Ok(())
-}
\ No newline at end of file
+}
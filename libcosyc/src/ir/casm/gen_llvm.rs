@@ -1,77 +1,997 @@
+use std::collections::HashMap;
 use std::path::Path;
 
+use inkwell::AddressSpace;
+use inkwell::attributes::{ Attribute, AttributeLoc };
+use inkwell::basic_block::BasicBlock;
 use inkwell::builder::Builder;
 use inkwell::context::Context;
-use inkwell::module::Module;
-#[allow(unused_imports)] use inkwell::OptimizationLevel;
+use inkwell::intrinsics::Intrinsic;
+use inkwell::module::{ Module, Linkage };
+use inkwell::targets::{
+    Target, TargetMachine, TargetData, InitializationConfig, RelocMode, CodeModel,
+    FileType,
+};
+use inkwell::types::{
+    BasicType, BasicTypeEnum, BasicMetadataTypeEnum, FunctionType, PointerType, StructType
+};
+use inkwell::values::{
+    BasicValueEnum, BasicMetadataValueEnum, FunctionValue, IntValue, PointerValue
+};
+use inkwell::{ IntPredicate, FloatPredicate, OptimizationLevel };
 
+use crate::build::Config;
+use crate::src::{ SourceMap, Message, Location, GetFileResult, FileId };
 use crate::ir::casm;
-use crate::error::{ IssueManager, Diagnostic };
+use crate::error::{ IssueManager, Diagnostic, ErrorCode };
 
 /// Generates the LLVM code for this Cosy ASM module, and writes its bitcode to
-/// `path`.
+/// `bitcode_path` and its native object file to `object_path`.
+///
+/// Returns `true` if both files were written successfully.
 pub fn emit_llvm(
     issues : &mut IssueManager,
+    files : &SourceMap,
+    config : Config,
+    casm : &casm::Package,
     bitcode_path : &Path,
-    _casm : &casm::Package,
+    object_path : &Path,
 ) -> bool {
+    let opt_level = match config {
+        Config::Debug => OptimizationLevel::None,
+        Config::Release => OptimizationLevel::Default,
+    };
+    let machine = match create_target_machine(opt_level) {
+        Ok(machine) => machine,
+        Err(err) => {
+            Diagnostic::error()
+                .code(ErrorCode::E0009)
+                .message(msg!("llvm-create-target-failed"))
+                .note(Message::verbatim(err))
+                .report(issues);
+            return false;
+        },
+    };
     let context = Context::create();
     let module = context.create_module("main");
+    module.set_triple(&machine.get_triple());
+    module.set_data_layout(&machine.get_target_data().get_data_layout());
     let mut codegen = CodeGen {
         context : &context,
         module,
         builder : context.create_builder(),
+        target_data : machine.get_target_data(),
+        functions : HashMap::new(),
+        files,
+        config,
+        sites : Vec::new(),
+        site_ids : HashMap::new(),
     };
-    if codegen.emit_module().is_none() {
+    codegen.emit_package(casm);
+    if let Err(err) = codegen.module.verify() {
         Diagnostic::bug()
-            .message("unexpected error encountered when generating LLVM bitcode")
+            .message(msg!("llvm-invalid-ir"))
+            .note(Message::verbatim(err.to_string()))
             .report(issues);
+        return false;
     }
     if !codegen.module.write_bitcode_to_path(bitcode_path) {
         Diagnostic::error()
-            .message(("failed to write LLVM bitcode to path {}", [
+            .code(ErrorCode::E0002)
+            .message(msg!("llvm-write-bitcode-failed", [
                 bitcode_path.display().into()
             ]))
             .report(issues);
+        return false;
+    }
+    if let Err(err) = machine.write_to_file(&codegen.module, FileType::Object, object_path) {
+        Diagnostic::error()
+            .code(ErrorCode::E0002)
+            .message(msg!("llvm-write-object-failed", [
+                object_path.display().into()
+            ]))
+            .note(Message::verbatim(err.to_string()))
+            .report(issues);
+        return false;
+    }
+    true
+}
+
+fn create_target_machine(opt_level : OptimizationLevel) -> Result<TargetMachine, String> {
+    Target::initialize_native(&InitializationConfig::default())?;
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple).map_err(|err| err.to_string())?;
+    target.create_target_machine(
+        &triple,
+        &TargetMachine::get_host_cpu_name().to_string(),
+        &TargetMachine::get_host_cpu_features().to_string(),
+        opt_level,
+        RelocMode::PIC,
+        CodeModel::Default,
+    ).ok_or_else(|| "no target machine available".to_string())
+}
+
+/// The size of the header stored before every heap value, which must match
+/// `struct cosy_header` in the runtime library.
+const HEAP_HEADER_SIZE : usize = 32;
+
+/// The site passed to the runtime when a location isn't known, which must
+/// match `COSY_NO_SITE` in the runtime library.
+const NO_SITE : u64 = u32::MAX as u64;
+
+/// Returns the LLVM symbol name of a Cosy function.
+fn mangle(func : &casm::Function) -> String {
+    if func.external {
+        func.name.clone()
+    } else {
+        format!("cosy::{}", func.name)
     }
-    codegen.module.print_to_stderr();
-    false
 }
 
 struct CodeGen<'a> {
     context : &'a Context,
     module : Module<'a>,
     builder : Builder<'a>,
+    target_data : TargetData,
+    /// The LLVM function generated for each Cosy function, by name.
+    functions : HashMap<casm::Symbol, FunctionValue<'a>>,
+    files : &'a SourceMap,
+    config : Config,
+    /// The source locations referred to by the generated code, which are
+    /// emitted as a table so the runtime can report where errors happened.
+    sites : Vec<Location>,
+    site_ids : HashMap<Location, u32>,
+}
+
+/// The state of the function currently being generated.
+struct FnState<'a, 'f> {
+    func : &'f casm::Function,
+    value : FunctionValue<'a>,
+    locals : Vec<PointerValue<'a>>,
+    blocks : Vec<BasicBlock<'a>>,
+    /// The stack frame of this function, if stack backtraces are enabled.
+    frame : Option<PointerValue<'a>>,
 }
 
 impl<'a> CodeGen<'a> {
-    fn emit_module(&mut self) -> Option<()> {
+    fn emit_package(&mut self, package : &casm::Package) {
+        for ext in &package.externs {
+            let value = self.emit_extern(ext);
+            self.functions.insert(ext.name.clone(), value);
+        }
+        let mut values = Vec::new();
+        for func in &package.functions {
+            let params = func.locals[..func.params].iter()
+                .map(|ty| BasicMetadataTypeEnum::from(self.basic_type(ty)))
+                .collect::<Vec<_>>();
+            let fn_type = self.basic_type(&func.ret).fn_type(&params, false);
+            let value = self.module.add_function(&mangle(func), fn_type, None);
+            self.functions.insert(func.name.clone(), value);
+            if func.inline {
+                let kind = Attribute::get_named_enum_kind_id("inlinehint");
+                let attr = self.context.create_enum_attribute(kind, 0);
+                value.add_attribute(AttributeLoc::Function, attr);
+            }
+            values.push(value);
+        }
+        for (func, value) in package.functions.iter().zip(&values) {
+            self.emit_function(func, *value);
+        }
+        if let Some(tests) = &package.tests {
+            self.emit_test_entry_point(tests);
+        } else if let Some(i) = package.functions.iter().position(|func| func.name == "main") {
+            self.emit_entry_point(&package.functions[i], values[i]);
+        }
+        self.emit_location_table();
+    }
+
+    /// Generates the table of source locations used by the runtime library,
+    /// which is indexed by the site IDs passed to the runtime.
+    fn emit_location_table(&mut self) {
+        let i32_type = self.context.i32_type();
+        let entry_ty = self.context.struct_type(&[
+            self.ptr_type().into(),
+            i32_type.into(),
+            i32_type.into(),
+        ], false);
+        let mut paths = HashMap::<FileId, PointerValue>::new();
+        let entries = self.sites.clone().into_iter().map(|loc| {
+            let (path, line, column) = match self.files.get_existing_file(loc.file_id) {
+                GetFileResult::Ok((path, file)) => {
+                    let (line, column) = file.find_line_and_col(loc.span.start);
+                    (path.display().to_string(), line, column)
+                },
+                _ => ("<unknown>".to_string(), 0, 0),
+            };
+            let path = *paths.entry(loc.file_id).or_insert_with(|| {
+                let value = self.context.const_string(path.as_bytes(), true);
+                let global = self.module.add_global(value.get_type(), None, "path");
+                global.set_initializer(&value);
+                global.set_linkage(Linkage::Private);
+                global.set_constant(true);
+                global.as_pointer_value().const_cast(self.ptr_type())
+            });
+            entry_ty.const_named_struct(&[
+                path.into(),
+                i32_type.const_int(line as u64, false).into(),
+                i32_type.const_int(column as u64, false).into(),
+            ])
+        }).collect::<Vec<_>>();
+        let table = entry_ty.const_array(&entries);
+        let global = self.module.add_global(table.get_type(), None, "cosy_locations");
+        global.set_initializer(&table);
+        global.set_constant(true);
+    }
+
+    /// Returns the ID of a source location in the location table.
+    fn site_id(&mut self, loc : &Location) -> IntValue<'a> {
+        let id = *self.site_ids.entry(*loc).or_insert_with(|| {
+            self.sites.push(*loc);
+            self.sites.len() as u32 - 1
+        });
+        self.context.i32_type().const_int(id as u64, false)
+    }
+
+    /// Returns the type of a stack frame, which must match `struct cosy_frame`
+    /// in the runtime library. Each frame stores a pointer to the frame of
+    /// its caller, the name of its function, and the site of the last call it
+    /// made.
+    fn frame_type(&self) -> StructType<'a> {
+        self.context.struct_type(&[
+            self.ptr_type().into(),
+            self.ptr_type().into(),
+            self.context.i32_type().into(),
+        ], false)
+    }
+
+    /// Returns a pointer to the runtime's pointer to the innermost stack frame.
+    fn stack_ptr(&self) -> PointerValue<'a> {
+        self.module.get_global("cosy_stack").unwrap_or_else(|| {
+            self.module.add_global(self.ptr_type(), None, "cosy_stack")
+        }).as_pointer_value()
+    }
+
+    /// Pushes a new stack frame for the function `name`, so it appears in
+    /// stack backtraces.
+    fn push_frame(&mut self, name : &str) -> PointerValue<'a> {
+        let frame_ty = self.frame_type();
+        let frame = self.builder.build_alloca(frame_ty, "frame").unwrap();
+        let parent = self.load(self.ptr_type().into(), self.stack_ptr());
+        let name = self.builder.build_global_string_ptr(name, "fn_name").unwrap();
+        let site = self.context.i32_type().const_int(NO_SITE, false);
+        self.builder.build_store(self.field_ptr(frame_ty, frame, 0), parent).unwrap();
+        self.builder.build_store(self.field_ptr(frame_ty, frame, 1), name.as_pointer_value()).unwrap();
+        self.builder.build_store(self.field_ptr(frame_ty, frame, 2), site).unwrap();
+        self.builder.build_store(self.stack_ptr(), frame).unwrap();
+        frame
+    }
+
+    /// Declares an external function using the C calling convention.
+    ///
+    /// `()` return types become `void`, and `bool` values are passed as
+    /// zero-extended `i1` values, matching C's `bool`.
+    fn emit_extern(&mut self, ext : &casm::ExternFn) -> FunctionValue<'a> {
+        let params = ext.params.iter()
+            .map(|ty| BasicMetadataTypeEnum::from(self.basic_type(ty)))
+            .collect::<Vec<_>>();
+        let fn_type = match ext.ret {
+            casm::Type::Unit => self.context.void_type().fn_type(&params, false),
+            _ => self.basic_type(&ext.ret).fn_type(&params, false),
+        };
+        let value = self.module.get_function(&ext.name).unwrap_or_else(|| {
+            self.module.add_function(&ext.name, fn_type, Some(Linkage::External))
+        });
+        let kind = Attribute::get_named_enum_kind_id("zeroext");
+        let zeroext = self.context.create_enum_attribute(kind, 0);
+        for (i, ty) in ext.params.iter().enumerate() {
+            if let casm::Type::Bool = ty {
+                value.add_attribute(AttributeLoc::Param(i as u32), zeroext);
+            }
+        }
+        if let casm::Type::Bool = ext.ret {
+            value.add_attribute(AttributeLoc::Return, zeroext);
+        }
+        value
+    }
+
+    /// Generates the C `main` function of a test harness, which runs the test
+    /// whose index is passed as the first command-line argument.
+    ///
+    /// Exits with code 0 if the test passed, and code 2 if the index isn't a
+    /// valid test. Failed tests abort the program.
+    fn emit_test_entry_point(&mut self, tests : &[casm::Symbol]) {
+        let i32_type = self.context.i32_type();
+        let fn_type = i32_type.fn_type(&[i32_type.into(), self.ptr_type().into()], false);
+        let entry = self.module.add_function("main", fn_type, None);
+        let atoi = self.module.get_function("atoi").unwrap_or_else(|| {
+            let fn_type = i32_type.fn_type(&[self.ptr_type().into()], false);
+            self.module.add_function("atoi", fn_type, Some(Linkage::External))
+        });
+        let block = self.context.append_basic_block(entry, "entry");
+        let run = self.context.append_basic_block(entry, "run");
+        let invalid = self.context.append_basic_block(entry, "invalid");
+        self.builder.position_at_end(block);
+        let argc = entry.get_nth_param(0).unwrap().into_int_value();
+        let has_arg = self.builder.build_int_compare(
+            IntPredicate::SGT, argc, i32_type.const_int(1, false), "has_arg"
+        ).unwrap();
+        self.builder.build_conditional_branch(has_arg, run, invalid).unwrap();
+        self.builder.position_at_end(invalid);
+        self.builder.build_return(Some(&i32_type.const_int(2, false))).unwrap();
+        self.builder.position_at_end(run);
+        let argv = entry.get_nth_param(1).unwrap().into_pointer_value();
+        let one = self.context.i64_type().const_int(1, false);
+        let arg_ptr = unsafe {
+            self.builder.build_in_bounds_gep(self.ptr_type(), argv, &[one], "").unwrap()
+        };
+        let arg = self.load(self.ptr_type().into(), arg_ptr);
+        let index = self.builder.build_call(atoi, &[arg.into()], "index").unwrap()
+            .try_as_basic_value().left().unwrap().into_int_value();
+        let mut cases = Vec::new();
+        for (i, test) in tests.iter().enumerate() {
+            let case = self.context.append_basic_block(entry, "test");
+            cases.push((i32_type.const_int(i as u64, false), case));
+            self.builder.position_at_end(case);
+            self.builder.build_call(self.functions[test], &[], "").unwrap();
+            self.builder.build_return(Some(&i32_type.const_zero())).unwrap();
+        }
+        self.builder.position_at_end(run);
+        self.builder.build_switch(index, invalid, &cases).unwrap();
+    }
+
+    /// Generates the C `main` function, which calls the Cosy `main` function
+    /// and uses its result as the exit code.
+    fn emit_entry_point(&mut self, func : &casm::Function, value : FunctionValue<'a>) {
+        let i32_type = self.context.i32_type();
+        let entry = self.module.add_function("main", i32_type.fn_type(&[], false), None);
+        let block = self.context.append_basic_block(entry, "entry");
+        self.builder.position_at_end(block);
+        let result = self.builder.build_call(value, &[], "result").unwrap();
+        let code = match func.ret {
+            casm::Type::I64 => {
+                let result = result.try_as_basic_value().left().unwrap();
+                self.builder.build_int_truncate(result.into_int_value(), i32_type, "code").unwrap()
+            },
+            _ => i32_type.const_zero(),
+        };
+        self.builder.build_return(Some(&code)).unwrap();
+    }
+
+    fn emit_function(&mut self, func : &casm::Function, value : FunctionValue<'a>) {
+        let entry = self.context.append_basic_block(value, "entry");
+        self.builder.position_at_end(entry);
+        let locals = func.locals.iter()
+            .map(|ty| self.builder.build_alloca(self.basic_type(ty), "").unwrap())
+            .collect::<Vec<_>>();
+        for (local, param) in locals.iter().zip(value.get_param_iter()) {
+            self.builder.build_store(*local, param).unwrap();
+        }
+        // locals storing heap values start out empty, so they can be safely
+        // released before they are first assigned to
+        for (ty, local) in func.locals.iter().zip(&locals).skip(func.params) {
+            if ty.is_managed() {
+                self.builder.build_store(*local, self.basic_type(ty).const_zero()).unwrap();
+            }
+        }
+        // stack frames are only tracked by debug builds
+        let frame = match self.config {
+            Config::Debug => Some(self.push_frame(&func.name)),
+            Config::Release => None,
+        };
+        let blocks = (0..func.blocks.len())
+            .map(|i| self.context.append_basic_block(value, &format!("bb{}", i)))
+            .collect::<Vec<_>>();
+        self.builder.build_unconditional_branch(blocks[0]).unwrap();
+        let state = FnState { func, value, locals, blocks, frame };
+        for (block, llvm_block) in func.blocks.iter().zip(&state.blocks) {
+            self.builder.position_at_end(*llvm_block);
+            for instr in &block.instrs {
+                self.emit_instr(&state, instr);
+            }
+            self.emit_terminator(&state, &block.terminator);
+        }
+    }
+
+    fn emit_instr(&mut self, state : &FnState<'a, '_>, instr : &casm::Instr) {
+        match instr {
+            casm::Instr::Assign { dest, value } => {
+                let dest_ty = &state.func.locals[*dest];
+                let dest_ptr = state.locals[*dest];
+                match value {
+                    casm::Value::Use(operand) => {
+                        let value = self.operand(state, operand);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Array(elems) => {
+                        let array_ty = self.basic_type(dest_ty);
+                        for (i, elem) in elems.iter().enumerate() {
+                            let value = self.operand(state, elem);
+                            let i = self.context.i64_type().const_int(i as u64, false);
+                            let ptr = self.element_ptr(array_ty, dest_ptr, i);
+                            self.builder.build_store(ptr, value).unwrap();
+                        }
+                    },
+                    casm::Value::Index { array, index } => {
+                        let array_ty = self.basic_type(&state.func.locals[*array]);
+                        let index = self.operand(state, index).into_int_value();
+                        let ptr = self.element_ptr(array_ty, state.locals[*array], index);
+                        let value = self.load(self.basic_type(dest_ty), ptr);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Variant { variant, fields } => {
+                        let tag = self.context.i64_type().const_int(*variant as u64, false);
+                        let tag_ptr = self.field_ptr(self.tag_type(), dest_ptr, 0);
+                        self.builder.build_store(tag_ptr, tag).unwrap();
+                        let variant_ty = self.variant_type(dest_ty, *variant);
+                        for (i, field) in fields.iter().enumerate() {
+                            let value = self.operand(state, field);
+                            let ptr = self.field_ptr(variant_ty, dest_ptr, i as u32 + 1);
+                            self.builder.build_store(ptr, value).unwrap();
+                        }
+                    },
+                    casm::Value::Tag(local) => {
+                        let ptr = self.field_ptr(self.tag_type(), state.locals[*local], 0);
+                        let value = self.load(self.context.i64_type().into(), ptr);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Field { local, variant, field } => {
+                        let local_ty = &state.func.locals[*local];
+                        let variant_ty = self.variant_type(local_ty, *variant);
+                        let ptr = self.field_ptr(
+                            variant_ty, state.locals[*local], *field as u32 + 1
+                        );
+                        let value = self.load(self.basic_type(dest_ty), ptr);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Call { func, args, loc } => {
+                        if let Some(frame) = state.frame {
+                            let site = self.site_id(loc);
+                            let site_ptr = self.field_ptr(self.frame_type(), frame, 2);
+                            self.builder.build_store(site_ptr, site).unwrap();
+                        }
+                        let callee = self.functions[func];
+                        let args = args.iter()
+                            .map(|arg| BasicMetadataValueEnum::from(self.operand(state, arg)))
+                            .collect::<Vec<_>>();
+                        let result = self.builder.build_call(callee, &args, "").unwrap();
+                        // extern functions returning `void` produce `()`
+                        let value = result.try_as_basic_value().left()
+                            .unwrap_or_else(|| self.context.const_struct(&[], false).into());
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Binary { op, lhs, rhs } => {
+                        let unsigned = self.operand_type(state, lhs).is_unsigned();
+                        let lhs = self.operand(state, lhs);
+                        let rhs = self.operand(state, rhs);
+                        let value = self.binary(*op, lhs, rhs, unsigned);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Unary { op, value } => {
+                        let value : BasicValueEnum = match (op, self.operand(state, value)) {
+                            (casm::UnaryOp::Neg, BasicValueEnum::FloatValue(value)) => {
+                                self.builder.build_float_neg(value, "").unwrap().into()
+                            },
+                            (casm::UnaryOp::Neg, value) => {
+                                self.builder.build_int_neg(value.into_int_value(), "").unwrap().into()
+                            },
+                            (casm::UnaryOp::Not, value) => {
+                                self.builder.build_not(value.into_int_value(), "").unwrap().into()
+                            },
+                        };
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Convert(value) => {
+                        let unsigned = self.operand_type(state, value).is_unsigned();
+                        let value = self.convert(self.operand(state, value), dest_ty, unsigned);
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                    casm::Value::Concat { lhs, rhs, site } => {
+                        let concat = self.runtime_fn("cosy_str_concat", self.ptr_type().fn_type(&[
+                            self.ptr_type().into(),
+                            self.ptr_type().into(),
+                            self.ptr_type().into(),
+                        ], false));
+                        let site = self.site(site.as_ref());
+                        let args = [
+                            self.operand(state, lhs).into(),
+                            self.operand(state, rhs).into(),
+                            site.into(),
+                        ];
+                        let value = self.builder.build_call(concat, &args, "").unwrap()
+                            .try_as_basic_value().left().unwrap();
+                        self.builder.build_store(dest_ptr, value).unwrap();
+                    },
+                }
+            },
+            casm::Instr::Retain(local) => {
+                self.emit_rc(&state.func.locals[*local], state.locals[*local], true);
+            },
+            casm::Instr::Release(local) => {
+                let ty = &state.func.locals[*local];
+                self.emit_rc(ty, state.locals[*local], false);
+                self.builder.build_store(state.locals[*local], self.basic_type(ty).const_zero()).unwrap();
+            },
+            casm::Instr::Store { array, indices, value } => {
+                let mut ty = &state.func.locals[*array];
+                let mut ptr = state.locals[*array];
+                for index in indices {
+                    let casm::Type::Array { elem, .. } = ty else {
+                        unreachable!("expected an array type");
+                    };
+                    let index = self.operand(state, index).into_int_value();
+                    ptr = self.element_ptr(self.basic_type(ty), ptr, index);
+                    ty = elem;
+                }
+                let value = self.operand(state, value);
+                self.emit_rc(ty, ptr, false);
+                self.builder.build_store(ptr, value).unwrap();
+                self.emit_rc(ty, ptr, true);
+            },
+        }
+    }
+
+    /// Retains or releases every heap value stored in the value of type `ty`
+    /// at `ptr`. Does nothing if the type doesn't contain any heap values.
+    fn emit_rc(&mut self, ty : &casm::Type, ptr : PointerValue<'a>, retain : bool) {
         let i64_type = self.context.i64_type();
-        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into(), i64_type.into()], false);
-        let function = self.module.add_function("sum", fn_type, None);
-        let basic_block = self.context.append_basic_block(function, "entry");
+        match ty {
+            casm::Type::Str => {
+                let name = if retain { "cosy_retain" } else { "cosy_release" };
+                let fn_type = self.context.void_type().fn_type(&[self.ptr_type().into()], false);
+                let func = self.runtime_fn(name, fn_type);
+                let value = self.load(self.ptr_type().into(), ptr);
+                self.builder.build_call(func, &[value.into()], "").unwrap();
+            },
+            casm::Type::Array { elem, len } if *len > 0 && elem.is_managed() => {
+                let before = self.builder.get_insert_block().unwrap();
+                let func = before.get_parent().unwrap();
+                let header = self.context.append_basic_block(func, "rc_elem");
+                let exit = self.context.append_basic_block(func, "rc_done");
+                self.builder.build_unconditional_branch(header).unwrap();
+                self.builder.position_at_end(header);
+                let phi = self.builder.build_phi(i64_type, "i").unwrap();
+                let index = phi.as_basic_value().into_int_value();
+                let elem_ptr = self.element_ptr(self.basic_type(ty), ptr, index);
+                self.emit_rc(elem, elem_ptr, retain);
+                let next = self.builder.build_int_add(index, i64_type.const_int(1, false), "").unwrap();
+                let done = self.builder.build_int_compare(
+                    IntPredicate::EQ, next, i64_type.const_int(*len, false), "done"
+                ).unwrap();
+                // the element may have added blocks of its own
+                let latch = self.builder.get_insert_block().unwrap();
+                phi.add_incoming(&[(&i64_type.const_zero(), before), (&next, latch)]);
+                self.builder.build_conditional_branch(done, exit, header).unwrap();
+                self.builder.position_at_end(exit);
+            },
+            casm::Type::Tagged { variants } if ty.is_managed() => {
+                let before = self.builder.get_insert_block().unwrap();
+                let func = before.get_parent().unwrap();
+                let exit = self.context.append_basic_block(func, "rc_done");
+                let mut cases = Vec::new();
+                for (i, fields) in variants.iter().enumerate() {
+                    if !fields.iter().any(casm::Type::is_managed) {
+                        continue;
+                    }
+                    let case = self.context.append_basic_block(func, "rc_variant");
+                    cases.push((i64_type.const_int(i as u64, false), case));
+                    self.builder.position_at_end(case);
+                    let variant_ty = self.variant_type(ty, i);
+                    for (j, field) in fields.iter().enumerate() {
+                        let field_ptr = self.field_ptr(variant_ty, ptr, j as u32 + 1);
+                        self.emit_rc(field, field_ptr, retain);
+                    }
+                    self.builder.build_unconditional_branch(exit).unwrap();
+                }
+                self.builder.position_at_end(before);
+                let tag_ptr = self.field_ptr(self.tag_type(), ptr, 0);
+                let tag = self.load(i64_type.into(), tag_ptr).into_int_value();
+                self.builder.build_switch(tag, exit, &cases).unwrap();
+                self.builder.position_at_end(exit);
+            },
+            _ => (),
+        }
+    }
+
+    /// Returns a function from the runtime library, declaring it if it hasn't
+    /// been used yet.
+    fn runtime_fn(&self, name : &str, fn_type : FunctionType<'a>) -> FunctionValue<'a> {
+        self.module.get_function(name).unwrap_or_else(|| {
+            self.module.add_function(name, fn_type, Some(Linkage::External))
+        })
+    }
+
+    /// Returns a pointer to a string describing an allocation site, or a null
+    /// pointer if the site isn't tracked.
+    fn site(&self, site : Option<&Location>) -> PointerValue<'a> {
+        match site {
+            Some(loc) => {
+                let path = loc.show_path(self.files);
+                self.builder.build_global_string_ptr(&path, "site").unwrap().as_pointer_value()
+            },
+            None => self.ptr_type().const_null(),
+        }
+    }
+
+    /// Applies a binary operator to two integers, floats or booleans. If
+    /// `unsigned` is true, integers are treated as unsigned.
+    fn binary(
+        &self,
+        op : casm::BinaryOp,
+        lhs : BasicValueEnum<'a>,
+        rhs : BasicValueEnum<'a>,
+        unsigned : bool,
+    ) -> BasicValueEnum<'a> {
+        use casm::BinaryOp as Op;
+        if let (BasicValueEnum::FloatValue(lhs), BasicValueEnum::FloatValue(rhs)) = (lhs, rhs) {
+            let predicate = match op {
+                Op::Add => return self.builder.build_float_add(lhs, rhs, "").unwrap().into(),
+                Op::Sub => return self.builder.build_float_sub(lhs, rhs, "").unwrap().into(),
+                Op::Mul => return self.builder.build_float_mul(lhs, rhs, "").unwrap().into(),
+                Op::Div => return self.builder.build_float_div(lhs, rhs, "").unwrap().into(),
+                Op::Rem => return self.builder.build_float_rem(lhs, rhs, "").unwrap().into(),
+                Op::Eq => FloatPredicate::OEQ,
+                Op::Ne => FloatPredicate::UNE,
+                Op::Lt => FloatPredicate::OLT,
+                Op::Le => FloatPredicate::OLE,
+                Op::Gt => FloatPredicate::OGT,
+                Op::Ge => FloatPredicate::OGE,
+            };
+            return self.builder.build_float_compare(predicate, lhs, rhs, "").unwrap().into();
+        }
+        let (lhs, rhs) = (lhs.into_int_value(), rhs.into_int_value());
+        let predicate = match (op, unsigned) {
+            (Op::Add, _) => return self.builder.build_int_add(lhs, rhs, "").unwrap().into(),
+            (Op::Sub, _) => return self.builder.build_int_sub(lhs, rhs, "").unwrap().into(),
+            (Op::Mul, _) => return self.builder.build_int_mul(lhs, rhs, "").unwrap().into(),
+            (Op::Div, false) => return self.builder.build_int_signed_div(lhs, rhs, "").unwrap().into(),
+            (Op::Div, true) => return self.builder.build_int_unsigned_div(lhs, rhs, "").unwrap().into(),
+            (Op::Rem, false) => return self.builder.build_int_signed_rem(lhs, rhs, "").unwrap().into(),
+            (Op::Rem, true) => return self.builder.build_int_unsigned_rem(lhs, rhs, "").unwrap().into(),
+            (Op::Eq, _) => IntPredicate::EQ,
+            (Op::Ne, _) => IntPredicate::NE,
+            (Op::Lt, false) => IntPredicate::SLT,
+            (Op::Le, false) => IntPredicate::SLE,
+            (Op::Gt, false) => IntPredicate::SGT,
+            (Op::Ge, false) => IntPredicate::SGE,
+            (Op::Lt, true) => IntPredicate::ULT,
+            (Op::Le, true) => IntPredicate::ULE,
+            (Op::Gt, true) => IntPredicate::UGT,
+            (Op::Ge, true) => IntPredicate::UGE,
+        };
+        self.builder.build_int_compare(predicate, lhs, rhs, "").unwrap().into()
+    }
+
+    /// Converts an integer or float to the numeric type `ty`. If `unsigned`
+    /// is true, integers being converted are treated as unsigned.
+    fn convert(
+        &self,
+        value : BasicValueEnum<'a>,
+        ty : &casm::Type,
+        unsigned : bool,
+    ) -> BasicValueEnum<'a> {
+        let b = &self.builder;
+        match (value, self.basic_type(ty)) {
+            (BasicValueEnum::IntValue(value), BasicTypeEnum::IntType(ty)) => {
+                b.build_int_cast_sign_flag(value, ty, !unsigned, "").unwrap().into()
+            },
+            (BasicValueEnum::IntValue(value), BasicTypeEnum::FloatType(ty)) if unsigned => {
+                b.build_unsigned_int_to_float(value, ty, "").unwrap().into()
+            },
+            (BasicValueEnum::IntValue(value), BasicTypeEnum::FloatType(ty)) => {
+                b.build_signed_int_to_float(value, ty, "").unwrap().into()
+            },
+            (BasicValueEnum::FloatValue(value), BasicTypeEnum::IntType(int_ty)) if ty.is_unsigned() => {
+                b.build_float_to_unsigned_int(value, int_ty, "").unwrap().into()
+            },
+            (BasicValueEnum::FloatValue(value), BasicTypeEnum::IntType(ty)) => {
+                b.build_float_to_signed_int(value, ty, "").unwrap().into()
+            },
+            (BasicValueEnum::FloatValue(value), BasicTypeEnum::FloatType(ty)) => {
+                b.build_float_cast(value, ty, "").unwrap().into()
+            },
+            _ => unreachable!("expected a numeric conversion"),
+        }
+    }
 
-        self.builder.position_at_end(basic_block);
+    fn emit_terminator(
+        &mut self,
+        state : &FnState<'a, '_>,
+        terminator : &casm::Terminator,
+    ) {
+        match terminator {
+            casm::Terminator::Return(operand) => {
+                let value = self.operand(state, operand);
+                if let Some(frame) = state.frame {
+                    let parent_ptr = self.field_ptr(self.frame_type(), frame, 0);
+                    let parent = self.load(self.ptr_type().into(), parent_ptr);
+                    self.builder.build_store(self.stack_ptr(), parent).unwrap();
+                }
+                self.builder.build_return(Some(&value)).unwrap();
+            },
+            casm::Terminator::Jump(block) => {
+                self.builder.build_unconditional_branch(state.blocks[*block]).unwrap();
+            },
+            casm::Terminator::Switch { value, cases, otherwise } => {
+                let value = self.operand(state, value).into_int_value();
+                let cases = cases.iter()
+                    .map(|(n, block)| {
+                        (value.get_type().const_int(*n, false), state.blocks[*block])
+                    })
+                    .collect::<Vec<_>>();
+                self.builder.build_switch(value, state.blocks[*otherwise], &cases).unwrap();
+            },
+            casm::Terminator::Unreachable => {
+                self.builder.build_unreachable().unwrap();
+            },
+            casm::Terminator::Assert { check, loc, next } => {
+                let fail = self.context.append_basic_block(state.value, "assert_fail");
+                match check {
+                    casm::Check::Bounds { index, len } => {
+                        let index = self.operand(state, index).into_int_value();
+                        let len = self.context.i64_type().const_int(*len, false);
+                        let in_bounds = self.builder.build_int_compare(
+                            IntPredicate::ULT, index, len, "in_bounds"
+                        ).unwrap();
+                        self.builder.build_conditional_branch(
+                            in_bounds, state.blocks[*next], fail
+                        ).unwrap();
+                        self.builder.position_at_end(fail);
+                        self.emit_panic(
+                            loc,
+                            "index out of bounds: the length is %llu but the index is %lld",
+                            &[len.into(), index.into()],
+                        );
+                    },
+                    casm::Check::DivByZero { divisor } => {
+                        let divisor = self.operand(state, divisor).into_int_value();
+                        let nonzero = self.builder.build_int_compare(
+                            IntPredicate::NE, divisor, divisor.get_type().const_zero(), "nonzero"
+                        ).unwrap();
+                        self.builder.build_conditional_branch(
+                            nonzero, state.blocks[*next], fail
+                        ).unwrap();
+                        self.builder.position_at_end(fail);
+                        self.emit_panic(loc, "attempt to divide by zero", &[]);
+                    },
+                    casm::Check::Assert { cond } => {
+                        let cond = self.operand(state, cond).into_int_value();
+                        self.builder.build_conditional_branch(
+                            cond, state.blocks[*next], fail
+                        ).unwrap();
+                        self.builder.position_at_end(fail);
+                        let mut text = String::new();
+                        loc.write_to_string(self.files, &mut text);
+                        let text = self.builder.build_global_string_ptr(text.trim(), "").unwrap();
+                        self.emit_panic(
+                            loc,
+                            "assertion failed: `%s`",
+                            &[text.as_pointer_value().into()],
+                        );
+                    },
+                    casm::Check::Overflow { op, lhs, rhs } => {
+                        let unsigned = self.operand_type(state, lhs).is_unsigned();
+                        let lhs = self.operand(state, lhs).into_int_value();
+                        let rhs = self.operand(state, rhs).into_int_value();
+                        let int_type = lhs.get_type();
+                        let (overflow, action) = match op {
+                            casm::BinaryOp::Add | casm::BinaryOp::Sub | casm::BinaryOp::Mul => {
+                                let (name, action) = match op {
+                                    casm::BinaryOp::Add => ("add", "add"),
+                                    casm::BinaryOp::Sub => ("sub", "subtract"),
+                                    _ => ("mul", "multiply"),
+                                };
+                                let sign = if unsigned { "u" } else { "s" };
+                                let name = format!("llvm.{}{}.with.overflow", sign, name);
+                                let intrinsic = Intrinsic::find(&name).unwrap()
+                                    .get_declaration(&self.module, &[int_type.into()])
+                                    .unwrap();
+                                let result = self.builder.build_call(intrinsic, &[lhs.into(), rhs.into()], "")
+                                    .unwrap()
+                                    .try_as_basic_value().left().unwrap()
+                                    .into_struct_value();
+                                let overflow = self.builder.build_extract_value(result, 1, "overflow")
+                                    .unwrap()
+                                    .into_int_value();
+                                (overflow, action)
+                            },
+                            casm::BinaryOp::Div | casm::BinaryOp::Rem => {
+                                // only the minimum value divided by -1 overflows
+                                let is_min = self.is_min_int(lhs);
+                                let is_neg_one = self.builder.build_int_compare(
+                                    IntPredicate::EQ, rhs, int_type.const_all_ones(), ""
+                                ).unwrap();
+                                let overflow = self.builder.build_and(is_min, is_neg_one, "overflow").unwrap();
+                                let action = if let casm::BinaryOp::Div = op {
+                                    "divide"
+                                } else {
+                                    "calculate the remainder"
+                                };
+                                (overflow, action)
+                            },
+                            _ => unreachable!("expected an arithmetic operator"),
+                        };
+                        self.builder.build_conditional_branch(
+                            overflow, fail, state.blocks[*next]
+                        ).unwrap();
+                        self.builder.position_at_end(fail);
+                        self.emit_panic(loc, &format!("attempt to {} with overflow", action), &[]);
+                    },
+                    casm::Check::NegOverflow { value } => {
+                        // only the minimum value has no positive counterpart
+                        let value = self.operand(state, value).into_int_value();
+                        let overflow = self.is_min_int(value);
+                        self.builder.build_conditional_branch(
+                            overflow, fail, state.blocks[*next]
+                        ).unwrap();
+                        self.builder.position_at_end(fail);
+                        self.emit_panic(loc, "attempt to negate with overflow", &[]);
+                    },
+                }
+            },
+        }
+    }
 
-        let x = function.get_nth_param(0)?.into_int_value();
-        let y = function.get_nth_param(1)?.into_int_value();
-        let z = function.get_nth_param(2)?.into_int_value();
+    /// Returns whether a signed integer is the minimum value of its type.
+    fn is_min_int(&self, value : IntValue<'a>) -> IntValue<'a> {
+        let int_type = value.get_type();
+        let min = int_type.const_int(1 << (int_type.get_bit_width() - 1), false);
+        self.builder.build_int_compare(IntPredicate::EQ, value, min, "is_min").unwrap()
+    }
 
-        let sum = self.builder.build_int_add(x, y, "sum").unwrap();
-        let sum = self.builder.build_int_add(sum, z, "sum").unwrap();
+    /// Calls the runtime to panic with a formatted error message, which
+    /// reports the source location of the error and exits the program.
+    fn emit_panic(
+        &mut self,
+        loc : &Location,
+        format : &str,
+        args : &[BasicValueEnum<'a>],
+    ) {
+        let i32_type = self.context.i32_type();
+        let fn_type = self.context.void_type()
+            .fn_type(&[i32_type.into(), self.ptr_type().into()], true);
+        let panic = self.runtime_fn("cosy_panic", fn_type);
+        let site = self.site_id(loc);
+        let format = self.builder.build_global_string_ptr(format, "").unwrap();
+        let mut call_args : Vec<BasicMetadataValueEnum> = vec![
+            site.into(),
+            format.as_pointer_value().into(),
+        ];
+        call_args.extend(args.iter().map(|arg| BasicMetadataValueEnum::from(*arg)));
+        self.builder.build_call(panic, &call_args, "").unwrap();
+        self.builder.build_unreachable().unwrap();
+    }
 
-        self.builder.build_return(Some(&sum)).unwrap();
+    fn operand(
+        &self,
+        state : &FnState<'a, '_>,
+        operand : &casm::Operand,
+    ) -> BasicValueEnum<'a> {
+        match operand {
+            casm::Operand::Local(local) => {
+                let ty = self.basic_type(&state.func.locals[*local]);
+                self.load(ty, state.locals[*local])
+            },
+            casm::Operand::Const(value) => match value {
+                casm::Const::Unit => self.context.const_struct(&[], false).into(),
+                casm::Const::Int { value, ty } => {
+                    self.basic_type(ty).into_int_type().const_int(*value, false).into()
+                },
+                casm::Const::Float { value, ty } => {
+                    self.basic_type(ty).into_float_type().const_float(*value).into()
+                },
+                casm::Const::Bool(b) => {
+                    self.context.bool_type().const_int(*b as u64, false).into()
+                },
+                casm::Const::Str(s) => {
+                    // literals have the same header as heap strings, but with
+                    // a negative reference count so they're never freed
+                    let mut bytes = vec![0xff; 8];
+                    bytes.resize(HEAP_HEADER_SIZE, 0);
+                    bytes.extend(s.as_bytes());
+                    bytes.push(0);
+                    let value = self.context.const_string(&bytes, false);
+                    let global = self.module.add_global(value.get_type(), None, "str");
+                    global.set_initializer(&value);
+                    global.set_linkage(Linkage::Private);
+                    global.set_alignment(16);
+                    let offset = self.context.i64_type().const_int(HEAP_HEADER_SIZE as u64, false);
+                    let array_ty = value.get_type().into();
+                    self.element_ptr(array_ty, global.as_pointer_value(), offset).into()
+                },
+            },
+        }
+    }
 
-        let fn_type = i64_type.fn_type(&[], false);
-        let function = self.module.add_function("main", fn_type, None);
-        let basic_block = self.context.append_basic_block(function, "entry");
+    /// Returns the type of an operand.
+    fn operand_type(&self, state : &FnState<'a, '_>, operand : &casm::Operand) -> casm::Type {
+        match operand {
+            casm::Operand::Local(local) => state.func.locals[*local].clone(),
+            casm::Operand::Const(value) => match value {
+                casm::Const::Unit => casm::Type::Unit,
+                casm::Const::Int { ty, .. } | casm::Const::Float { ty, .. } => ty.clone(),
+                casm::Const::Bool(..) => casm::Type::Bool,
+                casm::Const::Str(..) => casm::Type::Str,
+            },
+        }
+    }
 
-        self.builder.position_at_end(basic_block);
+    fn basic_type(&self, ty : &casm::Type) -> BasicTypeEnum<'a> {
+        match ty {
+            casm::Type::Unit => self.context.struct_type(&[], false).into(),
+            casm::Type::I8 | casm::Type::U8 => self.context.i8_type().into(),
+            casm::Type::I16 | casm::Type::U16 => self.context.i16_type().into(),
+            casm::Type::I32 | casm::Type::U32 => self.context.i32_type().into(),
+            casm::Type::I64 | casm::Type::U64 => self.context.i64_type().into(),
+            casm::Type::F32 => self.context.f32_type().into(),
+            casm::Type::F64 => self.context.f64_type().into(),
+            casm::Type::Bool => self.context.bool_type().into(),
+            casm::Type::Str => self.ptr_type().into(),
+            casm::Type::Array { elem, len } => {
+                self.basic_type(elem).array_type(*len as u32).into()
+            },
+            casm::Type::Tagged { variants } => {
+                // the payload is large enough to store the fields of any
+                // variant, and is accessed using the type of a specific
+                // variant
+                let payload_size = (0..variants.len())
+                    .map(|i| self.target_data.get_abi_size(&self.variant_type(ty, i)))
+                    .max()
+                    .unwrap_or(0)
+                    .saturating_sub(8);
+                let payload_ty = self.context.i64_type().array_type(payload_size.div_ceil(8) as u32);
+                self.context.struct_type(&[
+                    self.context.i64_type().into(),
+                    payload_ty.into(),
+                ], false).into()
+            },
+        }
+    }
 
-        let n = i64_type.const_int(2, false);
+    /// Returns the type of a tagged union, but only including its tag.
+    fn tag_type(&self) -> StructType<'a> {
+        self.context.struct_type(&[self.context.i64_type().into()], false)
+    }
 
-        self.builder.build_return(Some(&n)).unwrap();
+    /// Returns the type of a tagged union, as if `variant` was its only
+    /// variant. The tag is stored in the first field, followed by each field
+    /// of the variant.
+    fn variant_type(&self, ty : &casm::Type, variant : usize) -> StructType<'a> {
+        let casm::Type::Tagged { variants } = ty else {
+            unreachable!("expected a tagged union type");
+        };
+        let mut fields = vec![self.context.i64_type().into()];
+        fields.extend(variants[variant].iter().map(|field| self.basic_type(field)));
+        self.context.struct_type(&fields, false)
+    }
 
-        Some(())
+    fn ptr_type(&self) -> PointerType<'a> {
+        self.context.ptr_type(AddressSpace::default())
     }
-}
\ No newline at end of file
+
+    fn load(&self, ty : BasicTypeEnum<'a>, ptr : PointerValue<'a>) -> BasicValueEnum<'a> {
+        self.builder.build_load(ty, ptr, "").unwrap()
+    }
+
+    /// Returns a pointer to the field at `index` of the struct at `ptr`.
+    fn field_ptr(
+        &self,
+        struct_ty : StructType<'a>,
+        ptr : PointerValue<'a>,
+        index : u32,
+    ) -> PointerValue<'a> {
+        self.builder.build_struct_gep(struct_ty, ptr, index, "").unwrap()
+    }
+
+    /// Returns a pointer to the element at `index` of the array at `ptr`.
+    fn element_ptr(
+        &self,
+        array_ty : BasicTypeEnum<'a>,
+        ptr : PointerValue<'a>,
+        index : IntValue<'a>,
+    ) -> PointerValue<'a> {
+        let zero = self.context.i64_type().const_zero();
+        unsafe {
+            self.builder.build_in_bounds_gep(array_ty, ptr, &[zero, index], "")
+        }.unwrap()
+    }
+}
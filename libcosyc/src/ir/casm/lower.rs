@@ -0,0 +1,873 @@
+use std::collections::{ HashMap, HashSet };
+
+use crate::build::Config;
+use crate::src::Location;
+use crate::error::{ lint, Diagnostic, IssueManager, ErrorCode };
+use crate::ir::{ hir, casm };
+use crate::ir::hir::consteval::ConstValue;
+
+/// Lowers a collection of HIR modules into Cosy ASM.
+///
+/// Runtime checks, such as array bounds checks, are only inserted when
+/// `config` is `Config::Debug`.
+///
+/// If `harness` is true, functions marked `#[test]` are included in the
+/// package, and it is built as a test harness.
+///
+/// Reports any errors to `issues`.
+pub fn from_hir(
+    issues : &mut IssueManager,
+    config : Config,
+    harness : bool,
+    modules : &[hir::Module],
+) -> casm::Package {
+    let mut ctx = Hir2Casm {
+        issues,
+        config,
+        harness,
+        enums : HashMap::new(),
+        consts : HashMap::new(),
+        package : casm::Package::default(),
+    };
+    for module in modules {
+        for item in &module.items {
+            match &item.decl {
+                hir::Decl::Enum { name, variants } => {
+                    ctx.enums.insert(name.value.clone(), variants.clone());
+                },
+                hir::Decl::Const { name, value : Some(value), .. } => {
+                    ctx.consts.insert(name.value.clone(), value.clone());
+                },
+                _ => (),
+            }
+        }
+    }
+    if harness {
+        ctx.package.tests = Some(Vec::new());
+    }
+    for module in modules {
+        ctx.lower_module(module);
+    }
+    ctx.package
+}
+
+struct Hir2Casm<'a> {
+    issues : &'a mut IssueManager,
+    config : Config,
+    harness : bool,
+    enums : HashMap<hir::Symbol, Vec<hir::Variant>>,
+    /// The values of constants, which are substituted wherever they are used.
+    consts : HashMap<hir::Symbol, ConstValue>,
+    package : casm::Package,
+}
+
+impl<'a> Hir2Casm<'a> {
+    fn lower_module(&mut self, module : &hir::Module) {
+        for item in &module.items {
+            self.lower_decl(&item.decl, None);
+        }
+    }
+
+    fn lower_decl(&mut self, decl : &hir::Decl, parent : Option<&str>) {
+        match decl {
+            hir::Decl::Fn { name, attrs, params, ret, body } => {
+                let is_test = hir::has_attr(attrs, hir::AttrKind::Test);
+                if is_test && !self.harness {
+                    // tests are excluded from regular builds
+                    return;
+                }
+                let name = if let Some(parent) = parent {
+                    format!("{}::{}", parent, name.value)
+                } else {
+                    name.value.clone()
+                };
+                if is_test && let Some(tests) = &mut self.package.tests {
+                    tests.push(name.clone());
+                }
+                let mut func = FnBuilder::new(name.clone(), self.lower_type(ret));
+                for param in params {
+                    let local = func.new_local(self.lower_type(&param.ty));
+                    func.local_map.insert(param.id, local);
+                }
+                func.params = params.len();
+                func.external = hir::has_attr(attrs, hir::AttrKind::Extern);
+                func.inline = hir::has_attr(attrs, hir::AttrKind::Inline);
+                self.issues.push_lint_scope(hir::lint_attrs(attrs));
+                let result = self.lower_expr(&mut func, &name, body);
+                self.issues.pop_lint_scope();
+                func.terminate(casm::Terminator::Return(result));
+                self.package.functions.push(func.finish());
+            },
+            hir::Decl::ExternFn { name, params, ret } => {
+                let params = params.iter().map(|ty| self.lower_type(ty)).collect();
+                self.package.externs.push(casm::ExternFn {
+                    name : name.value.clone(),
+                    params,
+                    ret : self.lower_type(ret),
+                });
+            },
+            // types and constants have no runtime representation of their own
+            hir::Decl::Enum { .. } | hir::Decl::Const { .. } => (),
+        }
+    }
+
+    fn lower_stmt(
+        &mut self,
+        func : &mut FnBuilder,
+        func_name : &str,
+        stmt : &hir::Stmt,
+    ) {
+        match stmt {
+            hir::Stmt::Decl(decl) => self.lower_decl(decl, Some(func_name)),
+            hir::Stmt::Expr(expr) => {
+                self.lower_expr(func, func_name, expr);
+            },
+            hir::Stmt::Local { id, mutable, ty, init, .. } => {
+                let local = func.new_local(self.lower_type(ty));
+                func.local_map.insert(*id, local);
+                if *mutable {
+                    func.mutable.insert(*id);
+                }
+                if let Some(init) = init {
+                    let value = self.lower_expr(func, func_name, init);
+                    func.assign(local, casm::Value::Use(value));
+                }
+            },
+        }
+    }
+
+    fn lower_expr(
+        &mut self,
+        func : &mut FnBuilder,
+        func_name : &str,
+        expr : &hir::Expr,
+    ) -> casm::Operand {
+        match &expr.kind {
+            hir::ExprKind::NumIntegral(n) => casm::Operand::Const(casm::Const::Int {
+                value : *n as u64,
+                ty : self.lower_type(&expr.ty),
+            }),
+            hir::ExprKind::NumRational(rational) => {
+                let value = match expr.ty {
+                    hir::Type::Num(hir::NumType::F32) => f64::from(rational.to_f32()),
+                    _ => rational.to_f64(),
+                };
+                casm::Operand::Const(casm::Const::Float { value, ty : self.lower_type(&expr.ty) })
+            },
+            hir::ExprKind::Bool(b) => casm::Operand::Const(casm::Const::Bool(*b)),
+            hir::ExprKind::Str(s) => casm::Operand::Const(casm::Const::Str(s.clone())),
+            hir::ExprKind::Const(name) => {
+                let Some(value) = self.consts.get(name).cloned() else {
+                    Diagnostic::unreachable()
+                        .label(expr.loc)
+                        .note(msg!("casm-unevaluated-const"))
+                        .report(self.issues);
+                    return casm::Operand::Const(casm::Const::Unit);
+                };
+                let ty = self.lower_type(&expr.ty);
+                casm::Operand::Const(match value {
+                    ConstValue::Unit => casm::Const::Unit,
+                    ConstValue::Int(n) => casm::Const::Int { value : n as u64, ty },
+                    ConstValue::Float(n) => casm::Const::Float { value : n, ty },
+                    ConstValue::Rational(n) => casm::Const::Float { value : n.to_f64(), ty },
+                    ConstValue::Bool(b) => casm::Const::Bool(b),
+                })
+            },
+            hir::ExprKind::Local(id) => {
+                if let Some(local) = func.local_map.get(id).copied() {
+                    if !func.mutable.contains(id) {
+                        return casm::Operand::Local(local);
+                    }
+                    // mutable locals are copied, so later assignments don't
+                    // change the value of this operand
+                    let copy = func.new_local(func.locals[local].clone());
+                    func.assign(copy, casm::Value::Use(casm::Operand::Local(local)));
+                    casm::Operand::Local(copy)
+                } else {
+                    Diagnostic::unreachable()
+                        .label(expr.loc)
+                        .note(msg!("casm-local-before-definition"))
+                        .report(self.issues);
+                    casm::Operand::Const(casm::Const::Unit)
+                }
+            },
+            hir::ExprKind::Block(stmts) => {
+                let mut result = casm::Operand::Const(casm::Const::Unit);
+                let mut warned = false;
+                for (i, stmt) in stmts.iter().enumerate() {
+                    if let hir::Stmt::Expr(expr) = stmt {
+                        result = self.lower_expr(func, func_name, expr);
+                    } else {
+                        self.lower_stmt(func, func_name, stmt);
+                        result = casm::Operand::Const(casm::Const::Unit);
+                    }
+                    if !warned && stmt.diverges() {
+                        warned = self.warn_unreachable(stmt, &stmts[i + 1..]);
+                    }
+                }
+                // release the variables declared in this block, except for
+                // the result, which is owned by whoever uses the block
+                for stmt in stmts {
+                    if let hir::Stmt::Local { id, .. } = stmt
+                            && let Some(local) = func.local_map.get(id).copied()
+                            && !matches!(result, casm::Operand::Local(result) if result == local) {
+                        func.release(local);
+                    }
+                }
+                result
+            },
+            hir::ExprKind::Array(elems) => {
+                let elems = elems.iter()
+                    .map(|elem| self.lower_expr(func, func_name, elem))
+                    .collect();
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Array(elems));
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Index { target, index } => {
+                let array = self.lower_expr(func, func_name, target);
+                let array = func.make_local(array, self.lower_type(&target.ty));
+                let index = self.lower_expr(func, func_name, index);
+                self.check_bounds(func, &target.ty, &index, expr.loc);
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Index { array, index });
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Variant { variant, args } => {
+                let fields = args.iter()
+                    .map(|arg| self.lower_expr(func, func_name, arg))
+                    .collect();
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Variant { variant : *variant, fields });
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Match { scrutinee, arms } => {
+                let value = self.lower_expr(func, func_name, scrutinee);
+                let value = func.make_local(value, self.lower_type(&scrutinee.ty));
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                let join = func.new_block();
+                // each arm is tested in order, falling through to the next
+                // arm if its pattern doesn't match
+                for arm in arms {
+                    let next_arm = func.new_block();
+                    self.lower_pattern(func, &arm.pattern, value, next_arm);
+                    let result = self.lower_expr(func, func_name, &arm.body);
+                    func.assign(dest, casm::Value::Use(result));
+                    func.terminate(casm::Terminator::Jump(join));
+                    func.current = next_arm;
+                }
+                // match expressions are exhaustive, so the final block can
+                // never be reached
+                func.terminate(casm::Terminator::Unreachable);
+                func.current = join;
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Call { func : callee, args } => {
+                let args = args.iter()
+                    .map(|arg| self.lower_expr(func, func_name, arg))
+                    .collect();
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Call { func : callee.clone(), args, loc : expr.loc });
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Assign { target, value } => {
+                // find the local being assigned to, and the indices of the
+                // element being assigned to, if there are any
+                let mut elems = Vec::new();
+                let mut root = target.as_ref();
+                while let hir::ExprKind::Index { target : array, index } = &root.kind {
+                    elems.push((array.as_ref(), index.as_ref(), root.loc));
+                    root = array;
+                }
+                let hir::ExprKind::Local(id) = root.kind else {
+                    Diagnostic::unreachable()
+                        .label(target.loc)
+                        .note(msg!("casm-invalid-assign-target"))
+                        .report(self.issues);
+                    return casm::Operand::Const(casm::Const::Unit);
+                };
+                let local = func.local_map[&id];
+                let mut indices = Vec::new();
+                for (array, index, loc) in elems.into_iter().rev() {
+                    let index = self.lower_expr(func, func_name, index);
+                    self.check_bounds(func, &array.ty, &index, loc);
+                    indices.push(index);
+                }
+                let value = self.lower_expr(func, func_name, value);
+                if indices.is_empty() {
+                    func.assign(local, casm::Value::Use(value));
+                } else {
+                    func.store(local, indices, value);
+                }
+                casm::Operand::Const(casm::Const::Unit)
+            },
+            hir::ExprKind::If { cond, then_branch, else_branch } => {
+                let cond = self.lower_expr(func, func_name, cond);
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                let then_block = func.new_block();
+                let else_block = func.new_block();
+                let join = func.new_block();
+                func.terminate(casm::Terminator::Switch {
+                    value : cond,
+                    cases : vec![(1, then_block)],
+                    otherwise : else_block,
+                });
+                func.current = then_block;
+                let result = self.lower_expr(func, func_name, then_branch);
+                if else_branch.is_some() {
+                    func.assign(dest, casm::Value::Use(result));
+                }
+                func.terminate(casm::Terminator::Jump(join));
+                func.current = else_block;
+                if let Some(else_branch) = else_branch {
+                    let result = self.lower_expr(func, func_name, else_branch);
+                    func.assign(dest, casm::Value::Use(result));
+                } else {
+                    func.assign(dest, casm::Value::Use(casm::Operand::Const(casm::Const::Unit)));
+                }
+                func.terminate(casm::Terminator::Jump(join));
+                func.current = join;
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::While { cond, body } => {
+                let header = func.new_block();
+                func.terminate(casm::Terminator::Jump(header));
+                func.current = header;
+                let cond = self.lower_expr(func, func_name, cond);
+                let body_block = func.new_block();
+                let exit = func.new_block();
+                func.terminate(casm::Terminator::Switch {
+                    value : cond,
+                    cases : vec![(1, body_block)],
+                    otherwise : exit,
+                });
+                func.current = body_block;
+                func.loops.push(LoopTarget { start : header, exit, dest : None });
+                self.lower_expr(func, func_name, body);
+                func.loops.pop();
+                func.terminate(casm::Terminator::Jump(header));
+                func.current = exit;
+                casm::Operand::Const(casm::Const::Unit)
+            },
+            hir::ExprKind::Loop(body) => {
+                let start = func.new_block();
+                let exit = func.new_block();
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.terminate(casm::Terminator::Jump(start));
+                func.current = start;
+                func.loops.push(LoopTarget { start, exit, dest : Some(dest) });
+                self.lower_expr(func, func_name, body);
+                func.loops.pop();
+                func.terminate(casm::Terminator::Jump(start));
+                func.current = exit;
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Break(value) => {
+                let value = value.as_ref()
+                    .map(|value| self.lower_expr(func, func_name, value));
+                let Some(target) = func.loops.last() else {
+                    Diagnostic::unreachable()
+                        .label(expr.loc)
+                        .note(msg!("casm-break-outside-loop"))
+                        .report(self.issues);
+                    return casm::Operand::Const(casm::Const::Unit);
+                };
+                let (exit, dest) = (target.exit, target.dest);
+                if let Some(dest) = dest {
+                    let value = value.unwrap_or(casm::Operand::Const(casm::Const::Unit));
+                    func.assign(dest, casm::Value::Use(value));
+                }
+                func.terminate(casm::Terminator::Jump(exit));
+                func.diverge(self.lower_type(&expr.ty))
+            },
+            hir::ExprKind::Continue => {
+                let Some(target) = func.loops.last() else {
+                    Diagnostic::unreachable()
+                        .label(expr.loc)
+                        .note(msg!("casm-continue-outside-loop"))
+                        .report(self.issues);
+                    return casm::Operand::Const(casm::Const::Unit);
+                };
+                func.terminate(casm::Terminator::Jump(target.start));
+                func.diverge(self.lower_type(&expr.ty))
+            },
+            hir::ExprKind::Binary { op : op @ (hir::BinaryOp::And | hir::BinaryOp::Or), lhs, rhs } => {
+                // the right-hand side is only evaluated if the left-hand side
+                // doesn't already determine the result
+                let lhs = self.lower_expr(func, func_name, lhs);
+                let dest = func.new_local(casm::Type::Bool);
+                func.assign(dest, casm::Value::Use(lhs.clone()));
+                let rhs_block = func.new_block();
+                let join = func.new_block();
+                let evaluate_rhs = if let hir::BinaryOp::And = op { 1 } else { 0 };
+                func.terminate(casm::Terminator::Switch {
+                    value : lhs,
+                    cases : vec![(evaluate_rhs, rhs_block)],
+                    otherwise : join,
+                });
+                func.current = rhs_block;
+                let rhs = self.lower_expr(func, func_name, rhs);
+                func.assign(dest, casm::Value::Use(rhs));
+                func.terminate(casm::Terminator::Jump(join));
+                func.current = join;
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Binary { op, lhs, rhs } => {
+                let lhs_ty = &lhs.ty;
+                let lhs = self.lower_expr(func, func_name, lhs);
+                let rhs = self.lower_expr(func, func_name, rhs);
+                if let hir::Type::Str = lhs_ty {
+                    // strings are concatenated instead
+                    let site = if let Config::Debug = self.config { Some(expr.loc) } else { None };
+                    let dest = func.new_local(casm::Type::Str);
+                    func.assign(dest, casm::Value::Concat { lhs, rhs, site });
+                    return casm::Operand::Local(dest);
+                }
+                let op = match op {
+                    hir::BinaryOp::Add => casm::BinaryOp::Add,
+                    hir::BinaryOp::Sub => casm::BinaryOp::Sub,
+                    hir::BinaryOp::Mul => casm::BinaryOp::Mul,
+                    hir::BinaryOp::Div => casm::BinaryOp::Div,
+                    hir::BinaryOp::Rem => casm::BinaryOp::Rem,
+                    hir::BinaryOp::Eq => casm::BinaryOp::Eq,
+                    hir::BinaryOp::Ne => casm::BinaryOp::Ne,
+                    hir::BinaryOp::Lt => casm::BinaryOp::Lt,
+                    hir::BinaryOp::Le => casm::BinaryOp::Le,
+                    hir::BinaryOp::Gt => casm::BinaryOp::Gt,
+                    hir::BinaryOp::Ge => casm::BinaryOp::Ge,
+                    hir::BinaryOp::And | hir::BinaryOp::Or => unreachable!(),
+                };
+                if let (Config::Debug, casm::BinaryOp::Div | casm::BinaryOp::Rem) = (self.config, op)
+                        && lhs_ty.is_integral() {
+                    let next = func.new_block();
+                    func.terminate(casm::Terminator::Assert {
+                        check : casm::Check::DivByZero { divisor : rhs.clone() },
+                        loc : expr.loc,
+                        next,
+                    });
+                    func.current = next;
+                }
+                // signed division overflows when dividing the minimum value
+                // by -1
+                let can_overflow = match op {
+                    casm::BinaryOp::Add | casm::BinaryOp::Sub | casm::BinaryOp::Mul => true,
+                    casm::BinaryOp::Div | casm::BinaryOp::Rem => !self.lower_type(lhs_ty).is_unsigned(),
+                    _ => false,
+                };
+                if let Config::Debug = self.config && lhs_ty.is_integral() && can_overflow {
+                    self.check_overflow(func, op, lhs.clone(), rhs.clone(), expr.loc);
+                }
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Binary { op, lhs, rhs });
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Unary { op, value } => {
+                let value_ty = &value.ty;
+                let value = self.lower_expr(func, func_name, value);
+                let op = match op {
+                    hir::UnaryOp::Neg => casm::UnaryOp::Neg,
+                    hir::UnaryOp::Not => casm::UnaryOp::Not,
+                };
+                if let (Config::Debug, casm::UnaryOp::Neg) = (self.config, op)
+                        && value_ty.is_integral() {
+                    let next = func.new_block();
+                    func.terminate(casm::Terminator::Assert {
+                        check : casm::Check::NegOverflow { value : value.clone() },
+                        loc : expr.loc,
+                        next,
+                    });
+                    func.current = next;
+                }
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Unary { op, value });
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Convert(value) => {
+                let value = self.lower_expr(func, func_name, value);
+                let dest = func.new_local(self.lower_type(&expr.ty));
+                func.assign(dest, casm::Value::Convert(value));
+                casm::Operand::Local(dest)
+            },
+            hir::ExprKind::Assert(cond) => {
+                // assertions are checked in every configuration
+                let cond = self.lower_expr(func, func_name, cond);
+                let next = func.new_block();
+                func.terminate(casm::Terminator::Assert {
+                    check : casm::Check::Assert { cond },
+                    loc : expr.loc,
+                    next,
+                });
+                func.current = next;
+                casm::Operand::Const(casm::Const::Unit)
+            },
+            hir::ExprKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.lower_expr(func, func_name, value),
+                    None => casm::Operand::Const(casm::Const::Unit),
+                };
+                func.terminate(casm::Terminator::Return(value));
+                func.diverge(self.lower_type(&expr.ty))
+            },
+        }
+    }
+
+    /// Reports a warning if any of the statements in `rest` will never be
+    /// executed because `stmt` never finishes executing. Returns whether a
+    /// warning was reported.
+    fn warn_unreachable(&mut self, stmt : &hir::Stmt, rest : &[hir::Stmt]) -> bool {
+        let Some(next_loc) = rest.iter().find_map(|stmt| match stmt {
+            hir::Stmt::Expr(expr) => Some(expr.loc),
+            hir::Stmt::Local { name, .. } => Some(name.loc),
+            // declarations aren't executed, so are never unreachable
+            hir::Stmt::Decl(..) => None,
+        }) else {
+            return false;
+        };
+        let stmt_loc = match stmt {
+            hir::Stmt::Expr(expr) => expr.loc,
+            hir::Stmt::Local { loc, .. } => *loc,
+            hir::Stmt::Decl(decl) => decl.name().loc,
+        };
+        Diagnostic::lint(&lint::UNREACHABLE_CODE)
+            .code(ErrorCode::E0082)
+            .message(msg!("casm-unreachable-code"))
+            .label(next_loc)
+            .label_other((stmt_loc, msg!("casm-unreachable-code-label")))
+            .report(self.issues);
+        true
+    }
+
+    /// Inserts a runtime check that `index` is a valid index into an array of
+    /// type `array_ty`, if runtime checks are enabled.
+    fn check_bounds(
+        &mut self,
+        func : &mut FnBuilder,
+        array_ty : &hir::Type,
+        index : &casm::Operand,
+        loc : Location,
+    ) {
+        let (Config::Debug, hir::Type::Array { len, .. }) = (self.config, array_ty) else {
+            return;
+        };
+        let next = func.new_block();
+        func.terminate(casm::Terminator::Assert {
+            check : casm::Check::Bounds {
+                index : index.clone(),
+                len : *len,
+            },
+            loc,
+            next,
+        });
+        func.current = next;
+    }
+
+    /// Inserts a runtime check that applying `op` to two integers doesn't
+    /// overflow.
+    fn check_overflow(
+        &mut self,
+        func : &mut FnBuilder,
+        op : casm::BinaryOp,
+        lhs : casm::Operand,
+        rhs : casm::Operand,
+        loc : Location,
+    ) {
+        let next = func.new_block();
+        func.terminate(casm::Terminator::Assert {
+            check : casm::Check::Overflow { op, lhs, rhs },
+            loc,
+            next,
+        });
+        func.current = next;
+    }
+
+    /// Generates code which jumps to `fail` if the value stored in `local`
+    /// doesn't match `pattern`. Otherwise, execution continues in the current
+    /// block with any variables bound by the pattern.
+    fn lower_pattern(
+        &mut self,
+        func : &mut FnBuilder,
+        pattern : &hir::Pattern,
+        local : casm::LocalId,
+        fail : casm::BlockId,
+    ) {
+        let (value, expected) = match &pattern.kind {
+            hir::PatternKind::Wildcard => return,
+            hir::PatternKind::Binding(id) => {
+                let binding = func.new_local(self.lower_type(&pattern.ty));
+                func.assign(binding, casm::Value::Use(casm::Operand::Local(local)));
+                func.local_map.insert(*id, binding);
+                return;
+            },
+            hir::PatternKind::NumIntegral(n) => (local, *n as u64),
+            hir::PatternKind::Bool(b) => (local, *b as u64),
+            hir::PatternKind::Variant { variant, .. } => {
+                let tag = func.new_local(casm::Type::I64);
+                func.assign(tag, casm::Value::Tag(local));
+                (tag, *variant as u64)
+            },
+        };
+        let matched = func.new_block();
+        func.terminate(casm::Terminator::Switch {
+            value : casm::Operand::Local(value),
+            cases : vec![(expected, matched)],
+            otherwise : fail,
+        });
+        func.current = matched;
+        if let hir::PatternKind::Variant { variant, fields } = &pattern.kind {
+            for (i, field) in fields.iter().enumerate() {
+                if let hir::PatternKind::Wildcard = field.kind {
+                    continue;
+                }
+                let field_local = func.new_local(self.lower_type(&field.ty));
+                func.assign(field_local, casm::Value::Field {
+                    local,
+                    variant : *variant,
+                    field : i,
+                });
+                self.lower_pattern(func, field, field_local, fail);
+            }
+        }
+    }
+
+    /// Converts a HIR type into its machine representation.
+    fn lower_type(&self, ty : &hir::Type) -> casm::Type {
+        match ty {
+            hir::Type::Unknown => unreachable!("unknown types should not reach CASM"),
+            // values of the never type are never constructed
+            hir::Type::Unit | hir::Type::Never => casm::Type::Unit,
+            hir::Type::Int => casm::Type::I64,
+            hir::Type::Rational => casm::Type::F64,
+            hir::Type::Num(num) => match num {
+                hir::NumType::I8 => casm::Type::I8,
+                hir::NumType::I16 => casm::Type::I16,
+                hir::NumType::I32 => casm::Type::I32,
+                hir::NumType::I64 => casm::Type::I64,
+                hir::NumType::U8 => casm::Type::U8,
+                hir::NumType::U16 => casm::Type::U16,
+                hir::NumType::U32 => casm::Type::U32,
+                hir::NumType::U64 => casm::Type::U64,
+                hir::NumType::F32 => casm::Type::F32,
+                hir::NumType::F64 => casm::Type::F64,
+            },
+            hir::Type::Bool => casm::Type::Bool,
+            hir::Type::Str => casm::Type::Str,
+            hir::Type::Array { elem, len } => casm::Type::Array {
+                elem : Box::new(self.lower_type(elem)),
+                len : *len,
+            },
+            hir::Type::Result { ok, err } => casm::Type::Tagged {
+                variants : vec![
+                    vec![self.lower_type(ok)],
+                    vec![self.lower_type(err)],
+                ],
+            },
+            hir::Type::Enum(name) => casm::Type::Tagged {
+                variants : self.enums[name].iter()
+                    .map(|variant| variant.fields.iter()
+                        .map(|field| self.lower_type(field))
+                        .collect())
+                    .collect(),
+            },
+        }
+    }
+}
+
+/// The blocks a `continue` or `break` expression jumps to.
+struct LoopTarget {
+    start : casm::BlockId,
+    exit : casm::BlockId,
+    /// The local the value of a `break` is stored in, if the loop produces a
+    /// value.
+    dest : Option<casm::LocalId>,
+}
+
+/// Incrementally builds the control-flow graph of a function.
+struct FnBuilder {
+    name : casm::Symbol,
+    external : bool,
+    inline : bool,
+    ret : casm::Type,
+    params : usize,
+    locals : Vec<casm::Type>,
+    local_map : HashMap<hir::LocalId, casm::LocalId>,
+    /// HIR locals which can be assigned to after being initialised.
+    mutable : HashSet<hir::LocalId>,
+    /// Stack of loops enclosing the expression currently being lowered.
+    loops : Vec<LoopTarget>,
+    blocks : Vec<(Vec<casm::Instr>, Option<casm::Terminator>)>,
+    current : casm::BlockId,
+}
+
+impl FnBuilder {
+    fn new(name : casm::Symbol, ret : casm::Type) -> FnBuilder {
+        FnBuilder {
+            name,
+            external : false,
+            inline : false,
+            ret,
+            params : 0,
+            locals : Vec::new(),
+            local_map : HashMap::new(),
+            mutable : HashSet::new(),
+            loops : Vec::new(),
+            blocks : vec![(Vec::new(), None)],
+            current : 0,
+        }
+    }
+
+    fn new_local(&mut self, ty : casm::Type) -> casm::LocalId {
+        self.locals.push(ty);
+        self.locals.len() - 1
+    }
+
+    /// Ensures an operand is stored in a local, creating a new temporary local
+    /// if it is a constant.
+    fn make_local(
+        &mut self,
+        operand : casm::Operand,
+        ty : casm::Type,
+    ) -> casm::LocalId {
+        match operand {
+            casm::Operand::Local(local) => local,
+            casm::Operand::Const(_) => {
+                let local = self.new_local(ty);
+                self.assign(local, casm::Value::Use(operand));
+                local
+            },
+        }
+    }
+
+    fn new_block(&mut self) -> casm::BlockId {
+        self.blocks.push((Vec::new(), None));
+        self.blocks.len() - 1
+    }
+
+    /// Assigns a value to a local. If the local stores heap values, its old
+    /// value is released, and the new value is retained unless the local
+    /// takes ownership of it.
+    fn assign(&mut self, dest : casm::LocalId, value : casm::Value) {
+        let managed = self.locals[dest].is_managed();
+        let retain = managed && !value.is_owned();
+        let instrs = &mut self.blocks[self.current].0;
+        if managed {
+            instrs.push(casm::Instr::Release(dest));
+        }
+        instrs.push(casm::Instr::Assign { dest, value });
+        if retain {
+            instrs.push(casm::Instr::Retain(dest));
+        }
+    }
+
+    /// Releases the value stored in a local, if it stores heap values.
+    fn release(&mut self, local : casm::LocalId) {
+        if self.locals[local].is_managed() {
+            self.blocks[self.current].0.push(casm::Instr::Release(local));
+        }
+    }
+
+    fn store(
+        &mut self,
+        array : casm::LocalId,
+        indices : Vec<casm::Operand>,
+        value : casm::Operand,
+    ) {
+        let instr = casm::Instr::Store { array, indices, value };
+        self.blocks[self.current].0.push(instr);
+    }
+
+    fn terminate(&mut self, terminator : casm::Terminator) {
+        let block = &mut self.blocks[self.current];
+        assert!(block.1.is_none(), "block already has a terminator");
+        block.1 = Some(terminator);
+    }
+
+    /// Continues in a new block after the current block has been terminated
+    /// by an expression which never produces a value, such as `return`.
+    ///
+    /// Returns a placeholder for the value of the expression, which is never
+    /// initialised.
+    fn diverge(&mut self, ty : casm::Type) -> casm::Operand {
+        // any code after the expression is unreachable, but it still needs a
+        // block to live in
+        self.current = self.new_block();
+        casm::Operand::Local(self.new_local(ty))
+    }
+
+    /// Returns whether each block can be reached from the entry block.
+    fn reachable_blocks(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut stack = vec![0];
+        while let Some(block) = stack.pop() {
+            if reachable[block] {
+                continue;
+            }
+            reachable[block] = true;
+            match &self.blocks[block].1 {
+                Some(casm::Terminator::Jump(next))
+                | Some(casm::Terminator::Assert { next, .. }) => stack.push(*next),
+                Some(casm::Terminator::Switch { cases, otherwise, .. }) => {
+                    stack.extend(cases.iter().map(|(_, next)| *next));
+                    stack.push(*otherwise);
+                },
+                _ => (),
+            }
+        }
+        reachable
+    }
+
+    /// Inserts the retains and releases needed when entering and returning
+    /// from the function. Parameters are borrowed from the caller, so are
+    /// retained on entry. Every local is released on return, except for the
+    /// return value, which is owned by the caller.
+    fn manage_locals(&mut self) {
+        let managed = (0..self.locals.len())
+            .filter(|local| self.locals[*local].is_managed())
+            .collect::<Vec<_>>();
+        let entry = managed.iter()
+            .filter(|local| **local < self.params)
+            .map(|local| casm::Instr::Retain(*local));
+        self.blocks[0].0.splice(0..0, entry);
+        for (instrs, terminator) in &mut self.blocks {
+            let Some(casm::Terminator::Return(value)) = terminator else {
+                continue;
+            };
+            let result = match value {
+                casm::Operand::Local(local) => Some(*local),
+                casm::Operand::Const(_) => None,
+            };
+            instrs.extend(managed.iter()
+                .filter(|local| Some(**local) != result)
+                .map(|local| casm::Instr::Release(*local)));
+        }
+    }
+
+    fn finish(mut self) -> casm::Function {
+        self.manage_locals();
+        // unreachable blocks may use the placeholder values of expressions
+        // which never produce a value, so their contents are discarded
+        let reachable = self.reachable_blocks();
+        let blocks = self.blocks.into_iter()
+            .zip(reachable)
+            .map(|((instrs, terminator), reachable)| if reachable {
+                casm::Block {
+                    instrs,
+                    terminator : terminator.expect("unterminated block"),
+                }
+            } else {
+                casm::Block {
+                    instrs : Vec::new(),
+                    terminator : casm::Terminator::Unreachable,
+                }
+            })
+            .collect();
+        casm::Function {
+            name : self.name,
+            external : self.external,
+            inline : self.inline,
+            ret : self.ret,
+            params : self.params,
+            locals : self.locals,
+            blocks,
+        }
+    }
+}
@@ -2,14 +2,22 @@
 //! some language constructs.
 
 pub mod lower;
+pub mod exhaustive;
+pub mod definite;
+pub mod rational;
+pub mod consteval;
 
-use std::io;
+use std::{ io, fmt };
 use bincode;
 
-use crate::src::{ SourceMap, Located };
-use crate::pretty::{ PrettyPrinter, Colour, Decoration };
+use crate::src::{ SourceMap, Location, Located, Message };
+use crate::error::lint::{ LintAttr, LintLevel };
+use crate::pretty::PrettyPrinter;
 
-pub use crate::ir::ast::{ Symbol, Visibility };
+pub use crate::ir::ast::{ Symbol, Visibility, BinaryOp, UnaryOp };
+
+/// A unique identifier for a local variable within a function body.
+pub type LocalId = usize;
 
 #[derive(Debug, Default, bincode::Encode, bincode::Decode)]
 pub struct Module {
@@ -19,19 +27,520 @@ pub struct Module {
 /// Top-level declarations.
 #[derive(Debug, bincode::Encode, bincode::Decode)]
 pub struct ModuleItem {
-    vis : Visibility,
-    decl : Decl,
+    pub vis : Visibility,
+    pub decl : Decl,
+}
+
+/// The kinds of syntax an attribute can be applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttrTarget {
+    Fn,
+    ExternFn,
+    Type,
+    Const,
+    Local,
+    Expr,
+}
+
+impl AttrTarget {
+    /// Returns a description of this target, for use in error messages.
+    pub fn description(&self) -> Message {
+        match self {
+            AttrTarget::Fn => msg!("hir-attr-target-fn"),
+            AttrTarget::ExternFn => msg!("hir-attr-target-extern-fn"),
+            AttrTarget::Type => msg!("hir-attr-target-type"),
+            AttrTarget::Const => msg!("hir-attr-target-const"),
+            AttrTarget::Local => msg!("hir-attr-target-local"),
+            AttrTarget::Expr => msg!("hir-attr-target-expr"),
+        }
+    }
+}
+
+/// The registry of attributes known to the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum AttrKind {
+    /// `#[try]`, propagates errors to the caller of the enclosing function.
+    Try,
+    /// `#[inline]`, hints that a function should be inlined.
+    Inline,
+    /// `#[test]`, marks a function as a unit test.
+    Test,
+    /// `#[extern]`, exports a function using its unmangled name.
+    Extern,
+    /// `#[allow(..)]`, ignores the listed lints inside a function.
+    Allow,
+    /// `#[warn(..)]`, reports the listed lints as warnings inside a function.
+    Warn,
+    /// `#[deny(..)]`, reports the listed lints as errors inside a function.
+    Deny,
+}
+
+impl AttrKind {
+    /// Every attribute known to the compiler.
+    pub const ALL : &'static [AttrKind] = &[
+        AttrKind::Try,
+        AttrKind::Inline,
+        AttrKind::Test,
+        AttrKind::Extern,
+        AttrKind::Allow,
+        AttrKind::Warn,
+        AttrKind::Deny,
+    ];
+
+    /// Looks up an attribute by its name.
+    pub fn from_name(name : &str) -> Option<AttrKind> {
+        AttrKind::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+
+    /// Returns the name of this attribute, as it appears in source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AttrKind::Try => "try",
+            AttrKind::Inline => "inline",
+            AttrKind::Test => "test",
+            AttrKind::Extern => "extern",
+            AttrKind::Allow => "allow",
+            AttrKind::Warn => "warn",
+            AttrKind::Deny => "deny",
+        }
+    }
+
+    /// Returns the lint level set by this attribute, if it takes a list of
+    /// lints.
+    pub fn lint_level(&self) -> Option<LintLevel> {
+        match self {
+            AttrKind::Allow => Some(LintLevel::Allow),
+            AttrKind::Warn => Some(LintLevel::Warn),
+            AttrKind::Deny => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind of syntax this attribute can be applied to.
+    pub fn target(&self) -> AttrTarget {
+        match self {
+            AttrKind::Try => AttrTarget::Expr,
+            AttrKind::Inline
+            | AttrKind::Test
+            | AttrKind::Extern
+            | AttrKind::Allow
+            | AttrKind::Warn
+            | AttrKind::Deny => AttrTarget::Fn,
+        }
+    }
+}
+
+/// An attribute which has been checked against the attribute registry.
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
+pub struct Attr {
+    pub kind : AttrKind,
+    /// The lints named by `#[allow(..)]`, `#[warn(..)]`, or `#[deny(..)]`.
+    pub lints : Vec<Symbol>,
+    pub loc : Location,
+}
+
+/// Returns whether an attribute of this kind appears in `attrs`.
+pub fn has_attr(attrs : &[Attr], kind : AttrKind) -> bool {
+    attrs.iter().any(|attr| attr.kind == kind)
+}
+
+/// Returns the lint levels set by `attrs`, in the order they appear.
+pub fn lint_attrs(attrs : &[Attr]) -> Vec<LintAttr> {
+    let mut result = Vec::new();
+    for attr in attrs {
+        let Some(level) = attr.kind.lint_level() else { continue };
+        for name in &attr.lints {
+            result.push(LintAttr { name : name.clone(), level, loc : attr.loc });
+        }
+    }
+    result
+}
+
+/// All types available to Cosy.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum Type {
+    /// The type of an expression whose type couldn't be inferred due to an
+    /// earlier error. Never reported to the user.
+    Unknown,
+    /// The type of expressions which never produce a value, such as
+    /// `return`. Values of this type can be used as any other type.
+    Never,
+    Unit,
+    /// The default integer type, a signed 64-bit integer.
+    Int,
+    /// The default floating-point type, a 64-bit float at runtime. Constants
+    /// of this type are computed exactly, and only rounded when they're used.
+    Rational,
+    /// A numeric type with an explicit size, e.g. `u8` or `f32`.
+    Num(NumType),
+    Bool,
+    /// An immutable string, such as a string literal.
+    Str,
+    /// A fixed-size array of `len` elements of the same type.
+    Array {
+        elem : Box<Type>,
+        len : u64,
+    },
+    /// A user-defined enum type, referred to by name.
+    Enum(Symbol),
+    /// The built-in `Result[T, E]` type, with the variants `Ok(T)` and
+    /// `Err(E)`.
+    Result {
+        ok : Box<Type>,
+        err : Box<Type>,
+    },
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Unknown => write!(out, "?"),
+            Type::Never => write!(out, "!"),
+            Type::Unit => write!(out, "()"),
+            Type::Int => write!(out, "int"),
+            Type::Rational => write!(out, "rational"),
+            Type::Num(num) => write!(out, "{}", num.name()),
+            Type::Bool => write!(out, "bool"),
+            Type::Str => write!(out, "str"),
+            Type::Array { elem, len } => write!(out, "[{}]{}", len, elem),
+            Type::Enum(name) => write!(out, "{}", name),
+            Type::Result { ok, err } => write!(out, "Result[{}, {}]", ok, err),
+        }
+    }
+}
+
+impl Type {
+    /// Returns whether this type is unknown, or contains an unknown type.
+    pub fn is_unknown(&self) -> bool {
+        match self {
+            Type::Unknown => true,
+            Type::Array { elem, .. } => elem.is_unknown(),
+            Type::Result { ok, err } => ok.is_unknown() || err.is_unknown(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether a value of this type can be used where a value of type
+    /// `expected` is expected.
+    ///
+    /// Unknown types are compatible with every type, to avoid reporting
+    /// errors caused by earlier errors.
+    pub fn is_compatible(&self, expected : &Type) -> bool {
+        self == expected
+            || matches!(self, Type::Never)
+            || self.is_unknown()
+            || expected.is_unknown()
+    }
+
+    /// Returns the type of an expression whose value comes from one of two
+    /// branches with compatible types, ignoring any branch which never
+    /// produces a value.
+    pub fn join(&self, other : &Type) -> Type {
+        match self {
+            Type::Never | Type::Unknown => other.clone(),
+            _ => self.clone(),
+        }
+    }
+
+    /// Looks up a numeric type by its name, e.g. `int` or `u8`.
+    pub fn from_num_name(name : &str) -> Option<Type> {
+        match name {
+            "int" => Some(Type::Int),
+            "rational" => Some(Type::Rational),
+            _ => NumType::from_name(name).map(Type::Num),
+        }
+    }
+
+    /// Returns whether this is an integer type.
+    pub fn is_integral(&self) -> bool {
+        match self {
+            Type::Int => true,
+            Type::Num(num) => !num.is_float(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether this is a floating-point type.
+    pub fn is_float(&self) -> bool {
+        match self {
+            Type::Rational => true,
+            Type::Num(num) => num.is_float(),
+            _ => false,
+        }
+    }
+
+    /// Returns whether this is an integer or floating-point type.
+    pub fn is_numeric(&self) -> bool {
+        self.is_integral() || self.is_float()
+    }
+
+    /// Returns whether this numeric type can represent negative values.
+    pub fn is_signed(&self) -> bool {
+        match self {
+            Type::Int | Type::Rational => true,
+            Type::Num(num) => num.is_signed(),
+            _ => false,
+        }
+    }
+
+    /// Returns the smallest and largest values of an integer type.
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        match self {
+            Type::Int => NumType::I64.int_range(),
+            Type::Num(num) => num.int_range(),
+            _ => None,
+        }
+    }
+
+    /// Returns the C type used to pass values of this type to and from extern
+    /// functions, or `None` if values of this type have no C representation.
+    ///
+    /// Integers map to the `<stdint.h>` type of the same size, `rational` and
+    /// `f64` map to `double`, `f32` maps to `float`, `bool` maps to C's
+    /// `bool`, and `str` maps to a NUL-terminated `const char *`. The unit
+    /// type maps to `void`, but is only valid as a return type.
+    pub fn c_type(&self) -> Option<&'static str> {
+        let name = match self {
+            Type::Unit => "void",
+            Type::Int => "int64_t",
+            Type::Rational => "double",
+            Type::Bool => "bool",
+            Type::Str => "const char *",
+            Type::Num(num) => match num {
+                NumType::I8 => "int8_t",
+                NumType::I16 => "int16_t",
+                NumType::I32 => "int32_t",
+                NumType::I64 => "int64_t",
+                NumType::U8 => "uint8_t",
+                NumType::U16 => "uint16_t",
+                NumType::U32 => "uint32_t",
+                NumType::U64 => "uint64_t",
+                NumType::F32 => "float",
+                NumType::F64 => "double",
+            },
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Returns the names and fields of each variant of the built-in `Result`
+    /// type, in the order of their tags.
+    pub fn result_variants(ok : &Type, err : &Type) -> [(&'static str, Type); 2] {
+        [("Ok", ok.clone()), ("Err", err.clone())]
+    }
+}
+
+/// Numeric types with an explicit size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub enum NumType {
+    I8,
+    I16,
+    I32,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
+    F32,
+    F64,
+}
+
+impl NumType {
+    /// Every sized numeric type.
+    pub const ALL : &'static [NumType] = &[
+        NumType::I8,
+        NumType::I16,
+        NumType::I32,
+        NumType::I64,
+        NumType::U8,
+        NumType::U16,
+        NumType::U32,
+        NumType::U64,
+        NumType::F32,
+        NumType::F64,
+    ];
+
+    /// Looks up a numeric type by its name.
+    pub fn from_name(name : &str) -> Option<NumType> {
+        NumType::ALL.iter().copied().find(|num| num.name() == name)
+    }
+
+    /// Returns the name of this type, as it appears in source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumType::I8 => "i8",
+            NumType::I16 => "i16",
+            NumType::I32 => "i32",
+            NumType::I64 => "i64",
+            NumType::U8 => "u8",
+            NumType::U16 => "u16",
+            NumType::U32 => "u32",
+            NumType::U64 => "u64",
+            NumType::F32 => "f32",
+            NumType::F64 => "f64",
+        }
+    }
+
+    /// Returns the size of this type in bits.
+    pub fn bits(&self) -> u32 {
+        match self {
+            NumType::I8 | NumType::U8 => 8,
+            NumType::I16 | NumType::U16 => 16,
+            NumType::I32 | NumType::U32 | NumType::F32 => 32,
+            NumType::I64 | NumType::U64 | NumType::F64 => 64,
+        }
+    }
+
+    /// Returns whether this is a floating-point type.
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumType::F32 | NumType::F64)
+    }
+
+    /// Returns whether this type can represent negative values.
+    pub fn is_signed(&self) -> bool {
+        !matches!(self, NumType::U8 | NumType::U16 | NumType::U32 | NumType::U64)
+    }
+
+    /// Returns the smallest and largest values of an integer type, or `None`
+    /// if this is a floating-point type.
+    pub fn int_range(&self) -> Option<(i128, i128)> {
+        if self.is_float() {
+            return None;
+        }
+        let bits = self.bits();
+        Some(if self.is_signed() {
+            (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+        } else {
+            (0, (1 << bits) - 1)
+        })
+    }
+}
+
+/// All expressions available to Cosy, paired with their inferred type.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct Expr {
+    pub kind : ExprKind,
+    pub ty : Type,
+    pub loc : Location,
+    pub attrs : Vec<Attr>,
+}
+
+impl Expr {
+    /// Creates a new expression without any attributes.
+    pub fn new(kind : ExprKind, ty : Type, loc : Location) -> Expr {
+        Expr { kind, ty, loc, attrs : Vec::new() }
+    }
+}
+
+/// The different kinds of expression. Note: this doesn't include constructs
+/// like `local`, since those are statements.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub enum ExprKind {
+    NumIntegral(u128),
+    /// A floating-point literal, stored as the exact value written in the
+    /// source code.
+    NumRational(rational::Rational),
+    Bool(bool),
+    Str(String),
+    /// A reference to a local variable.
+    Local(LocalId),
+    /// A reference to a module-level constant.
+    Const(Symbol),
+    /// A sequence of statements. The value of the block is the value of its
+    /// final expression statement, or `()` if there isn't one.
+    Block(Vec<Stmt>),
+    Array(Vec<Expr>),
+    Index {
+        target : Box<Expr>,
+        index : Box<Expr>,
+    },
+    /// Constructs the variant at index `variant` of the enum type of this
+    /// expression.
+    Variant {
+        variant : usize,
+        args : Vec<Expr>,
+    },
+    Match {
+        scrutinee : Box<Expr>,
+        arms : Vec<MatchArm>,
+    },
+    /// Calls the function with the fully-qualified name `func`.
+    Call {
+        func : Symbol,
+        args : Vec<Expr>,
+    },
+    /// Returns a value from the enclosing function, or `()` if there is no
+    /// value.
+    Return(Option<Box<Expr>>),
+    /// Repeats its body until a `break` is reached.
+    Loop(Box<Expr>),
+    /// Exits the innermost loop, producing a value if it's a `loop`.
+    Break(Option<Box<Expr>>),
+    /// Skips to the next iteration of the innermost loop.
+    Continue,
+    /// Stores a value in a local variable, or in an element of an array
+    /// stored in a local variable.
+    Assign {
+        target : Box<Expr>,
+        value : Box<Expr>,
+    },
+    /// If there is no `else` branch, the value of the `then` branch is
+    /// discarded and the type of the expression is `()`.
+    If {
+        cond : Box<Expr>,
+        then_branch : Box<Expr>,
+        else_branch : Option<Box<Expr>>,
+    },
+    While {
+        cond : Box<Expr>,
+        body : Box<Expr>,
+    },
+    Binary {
+        op : BinaryOp,
+        lhs : Box<Expr>,
+        rhs : Box<Expr>,
+    },
+    Unary {
+        op : UnaryOp,
+        value : Box<Expr>,
+    },
+    /// Converts a numeric value to the numeric type of this expression,
+    /// e.g. `u8(x)`. Integers are truncated or extended, and floats are
+    /// rounded towards zero when converted to integers.
+    Convert(Box<Expr>),
+    /// `assert(cond)`, aborts the program if `cond` is false. The location of
+    /// this expression is the source text of `cond`, which is included in
+    /// the error message.
+    Assert(Box<Expr>),
+}
+
+/// A single `case` of a `match` expression.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct MatchArm {
+    pub pattern : Pattern,
+    pub body : Expr,
+}
+
+/// Patterns used to destructure values in `match` expressions.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct Pattern {
+    pub kind : PatternKind,
+    pub ty : Type,
+    pub loc : Location,
 }
 
-/// All expressions available to Cosy. Note: this doesn't include constructs
-/// like `var`, since those are statements.
 #[derive(Debug, bincode::Encode, bincode::Decode)]
-pub enum Expr {
-    NumIntegral(Located<u128>),
-    NumRational(Located<Symbol>),
-    Bool(Located<bool>),
-    Id(Located<Symbol>),
-    Block(Located<Vec<Stmt>>),
+pub enum PatternKind {
+    /// Matches anything, written `_`.
+    Wildcard,
+    /// Matches anything, and binds it to a new local variable.
+    Binding(LocalId),
+    NumIntegral(u128),
+    Bool(bool),
+    Variant {
+        variant : usize,
+        fields : Vec<Pattern>,
+    },
 }
 
 /// All statements available to Cosy.
@@ -39,20 +548,90 @@ pub enum Expr {
 pub enum Stmt {
     Decl(Decl),
     Expr(Expr),
+    /// Declares a local variable. Locals without an initial value can be
+    /// assigned exactly once, unless they are `mutable`.
     Local {
         name : Located<Symbol>,
+        id : LocalId,
+        mutable : bool,
+        ty : Type,
         init : Option<Expr>,
+        /// The location of the whole statement.
+        loc : Location,
     },
 }
 
+impl Stmt {
+    /// Returns whether this statement never finishes executing, e.g. because
+    /// it returns from the enclosing function.
+    pub fn diverges(&self) -> bool {
+        match self {
+            Stmt::Expr(expr)
+            | Stmt::Local { init : Some(expr), .. } => matches!(expr.ty, Type::Never),
+            _ => false,
+        }
+    }
+}
+
+/// A variant of an enum type.
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
+pub struct Variant {
+    pub name : Located<Symbol>,
+    pub fields : Vec<Type>,
+}
+
+/// A parameter of a function.
+#[derive(Debug, bincode::Encode, bincode::Decode)]
+pub struct Param {
+    pub name : Located<Symbol>,
+    pub id : LocalId,
+    pub ty : Type,
+}
+
 /// All declarations available to Cosy. Note: these should all be valid
 /// top-level declarations.
 #[derive(Debug, bincode::Encode, bincode::Decode)]
 pub enum Decl {
     Fn {
         name : Located<Symbol>,
+        attrs : Vec<Attr>,
+        params : Vec<Param>,
+        ret : Type,
         body : Box<Expr>,
     },
+    Enum {
+        name : Located<Symbol>,
+        variants : Vec<Variant>,
+    },
+    /// A constant, whose `value` is computed from `init` at compile time.
+    /// The value is `None` until it has been evaluated, or if evaluation
+    /// failed.
+    Const {
+        name : Located<Symbol>,
+        ty : Type,
+        init : Box<Expr>,
+        value : Option<consteval::ConstValue>,
+    },
+    /// A function defined outside of Cosy, which is called using the C
+    /// calling convention. Its parameter and return types always have a C
+    /// representation.
+    ExternFn {
+        name : Located<Symbol>,
+        params : Vec<Type>,
+        ret : Type,
+    },
+}
+
+impl Decl {
+    /// Returns the name of this declaration.
+    pub fn name(&self) -> &Located<Symbol> {
+        match self {
+            Decl::Fn { name, .. } => name,
+            Decl::Enum { name, .. } => name,
+            Decl::Const { name, .. } => name,
+            Decl::ExternFn { name, .. } => name,
+        }
+    }
 }
 
 /// Pretty prints Cosy HIR for debugging purposes.
@@ -63,4 +642,4 @@ pub fn debug_write_hir<W : io::Write>(
 ) -> io::Result<()> {
     printer.write(&format!("{:?}", module))?;
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,597 @@
+//! Computes the values of constants at compile time, by interpreting the HIR
+//! of their initialisers.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::src::Location;
+use crate::error::{ Diagnostic, IssueManager, ErrorCode };
+use crate::ir::hir;
+use crate::ir::hir::rational::Rational;
+
+/// The maximum number of expressions which can be evaluated when computing
+/// the value of a single constant, so infinite loops don't hang the compiler.
+const MAX_STEPS : usize = 1_000_000;
+
+/// The maximum depth of nested function calls when computing the value of a
+/// constant.
+const MAX_DEPTH : usize = 128;
+
+/// The maximum number of bits used to store the numerator and denominator of
+/// a `rational` constant, so repeated operations can't use unbounded memory.
+const MAX_RATIONAL_BITS : u64 = 8192;
+
+/// The value of a constant.
+#[derive(Debug, Clone, PartialEq, bincode::Encode, bincode::Decode)]
+pub enum ConstValue {
+    Unit,
+    /// An integer, which is always within the range of its type.
+    Int(i128),
+    /// A value of type `f32` or `f64`. Values of type `f32` are always
+    /// rounded to the nearest `f32`.
+    Float(f64),
+    /// A value of type `rational`, which is kept exact until it's used at
+    /// runtime.
+    Rational(Rational),
+    Bool(bool),
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstValue::Unit => write!(out, "()"),
+            ConstValue::Int(n) => write!(out, "{}", n),
+            ConstValue::Float(n) => write!(out, "{:?}", n),
+            ConstValue::Rational(n) => write!(out, "{}", n),
+            ConstValue::Bool(b) => write!(out, "{}", b),
+        }
+    }
+}
+
+/// Evaluates every constant declared in `module`, storing their values in
+/// the module.
+///
+/// Reports an error for each constant which can't be evaluated, including a
+/// backtrace of the function calls which led to the error.
+pub fn eval_module(issues : &mut IssueManager, module : &mut hir::Module) {
+    let mut eval = ConstEval {
+        issues,
+        fns : HashMap::new(),
+        consts : HashMap::new(),
+        states : HashMap::new(),
+        frames : Vec::new(),
+        steps : 0,
+    };
+    for item in &module.items {
+        match &item.decl {
+            decl @ (hir::Decl::Fn { name, .. } | hir::Decl::ExternFn { name, .. }) => {
+                eval.fns.insert(name.value.clone(), decl);
+            },
+            hir::Decl::Const { name, init, .. } => {
+                eval.consts.insert(name.value.clone(), (name.loc, init));
+            },
+            hir::Decl::Enum { .. } => (),
+        }
+    }
+    for item in &module.items {
+        if let hir::Decl::Const { name, .. } = &item.decl {
+            eval.steps = 0;
+            let _ = eval.eval_const(&name.value, name.loc);
+        }
+    }
+    let values = eval.states.into_iter()
+        .filter_map(|(name, state)| match state {
+            ConstState::Done(value) => Some((name, value?)),
+            ConstState::Evaluating => None,
+        })
+        .collect::<HashMap<_, _>>();
+    for item in &mut module.items {
+        if let hir::Decl::Const { name, value, .. } = &mut item.decl {
+            *value = values.get(&name.value).cloned();
+        }
+    }
+}
+
+enum ConstState {
+    /// The constant is currently being evaluated. Seeing a constant in this
+    /// state again means its value depends on itself.
+    Evaluating,
+    /// The constant has been evaluated, or failed to evaluate if its value is
+    /// `None`.
+    Done(Option<ConstValue>),
+}
+
+/// A function call or constant currently being evaluated, used to report a
+/// backtrace if evaluation fails.
+struct Frame {
+    loc : Location,
+    kind : FrameKind,
+}
+
+enum FrameKind {
+    Const(hir::Symbol),
+    /// A call to the function with this fully-qualified name.
+    Call(hir::Symbol),
+}
+
+/// Reasons evaluation of an expression can stop early.
+enum Interrupt {
+    /// An error was reported.
+    Error,
+    Break(ConstValue),
+    Continue,
+    Return(ConstValue),
+}
+
+type EvalResult<T> = Result<T, Interrupt>;
+
+/// The values of the local variables of a function.
+type Env = HashMap<hir::LocalId, ConstValue>;
+
+struct ConstEval<'a, 'm> {
+    issues : &'a mut IssueManager,
+    /// Every function which can be called, by its fully-qualified name.
+    fns : HashMap<hir::Symbol, &'m hir::Decl>,
+    consts : HashMap<hir::Symbol, (Location, &'m hir::Expr)>,
+    states : HashMap<hir::Symbol, ConstState>,
+    frames : Vec<Frame>,
+    steps : usize,
+}
+
+impl<'m> ConstEval<'_, 'm> {
+    /// Reports an error, followed by a backtrace of the calls and constants
+    /// being evaluated.
+    ///
+    /// Consecutive frames from the same place, such as the calls made by a
+    /// recursive function, are only labelled once.
+    fn error(&mut self, mut diagnostic : Diagnostic) -> Interrupt {
+        let mut frames = self.frames.iter().rev().peekable();
+        while let Some(frame) = frames.next() {
+            let mut repeats = 1usize;
+            while frames.next_if(|next| next.loc == frame.loc).is_some() {
+                repeats += 1;
+            }
+            let (label, name) = match &frame.kind {
+                FrameKind::Const(name) => {
+                    (msg!("consteval-evaluating-const", [name.clone().into()]), name)
+                },
+                FrameKind::Call(path) => {
+                    (msg!("consteval-in-call", [path.clone().into()]), path)
+                },
+            };
+            diagnostic = diagnostic.label_other((frame.loc, label));
+            if repeats > 1 {
+                diagnostic = diagnostic.note(msg!("consteval-repeated-call", [
+                    name.clone().into(), repeats.into()
+                ]));
+            }
+        }
+        diagnostic.report(self.issues);
+        Interrupt::Error
+    }
+
+    fn unsupported(&mut self, loc : Location) -> Interrupt {
+        self.error(Diagnostic::error()
+            .code(ErrorCode::E0071)
+            .message(msg!("consteval-not-const"))
+            .label(loc)
+            .note(msg!("consteval-not-const-note")))
+    }
+
+    fn eval_const(&mut self, name : &hir::Symbol, loc : Location) -> EvalResult<ConstValue> {
+        match self.states.get(name) {
+            Some(ConstState::Done(Some(value))) => return Ok(value.clone()),
+            // an error has already been reported
+            Some(ConstState::Done(None)) => return Err(Interrupt::Error),
+            Some(ConstState::Evaluating) => {
+                return Err(self.error(Diagnostic::error()
+                    .code(ErrorCode::E0072)
+                    .message(msg!("consteval-cycle", [
+                        name.clone().into()
+                    ]))
+                    .label(loc)));
+            },
+            None => (),
+        }
+        let Some((def_loc, init)) = self.consts.get(name).copied() else {
+            return Err(self.unsupported(loc));
+        };
+        self.states.insert(name.clone(), ConstState::Evaluating);
+        self.frames.push(Frame { loc : def_loc, kind : FrameKind::Const(name.clone()) });
+        let result = self.eval_expr(&mut Env::new(), init);
+        self.frames.pop();
+        let value = result.ok();
+        self.states.insert(name.clone(), ConstState::Done(value.clone()));
+        value.ok_or(Interrupt::Error)
+    }
+
+    fn eval_expr(&mut self, env : &mut Env, expr : &'m hir::Expr) -> EvalResult<ConstValue> {
+        self.steps += 1;
+        if self.steps == MAX_STEPS {
+            return Err(self.error(Diagnostic::error()
+                .code(ErrorCode::E0073)
+                .message(msg!("consteval-too-many-steps"))
+                .label(expr.loc)
+                .note(msg!("consteval-too-many-steps-note", [
+                    MAX_STEPS.into()
+                ]))));
+        }
+        let value = match &expr.kind {
+            hir::ExprKind::NumIntegral(n) => ConstValue::Int(*n as i128),
+            hir::ExprKind::NumRational(rational) => match expr.ty {
+                hir::Type::Num(hir::NumType::F32) => ConstValue::Float(rational.to_f32().into()),
+                hir::Type::Num(hir::NumType::F64) => ConstValue::Float(rational.to_f64()),
+                _ => ConstValue::Rational(rational.clone()),
+            },
+            hir::ExprKind::Bool(b) => ConstValue::Bool(*b),
+            hir::ExprKind::Local(id) => match env.get(id) {
+                Some(value) => value.clone(),
+                None => return Err(self.unsupported(expr.loc)),
+            },
+            hir::ExprKind::Const(name) => self.eval_const(name, expr.loc)?,
+            hir::ExprKind::Block(stmts) => {
+                self.declare_fns(stmts);
+                let mut value = ConstValue::Unit;
+                for stmt in stmts {
+                    value = match stmt {
+                        hir::Stmt::Expr(expr) => self.eval_expr(env, expr)?,
+                        hir::Stmt::Local { id, init : Some(init), .. } => {
+                            let init = self.eval_expr(env, init)?;
+                            env.insert(*id, init);
+                            ConstValue::Unit
+                        },
+                        hir::Stmt::Local { .. } | hir::Stmt::Decl(..) => ConstValue::Unit,
+                    };
+                }
+                value
+            },
+            hir::ExprKind::Call { func, args } => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.eval_expr(env, arg)?);
+                }
+                let (params, body) = match self.fns.get(func).copied() {
+                    Some(hir::Decl::Fn { params, body, .. }) => (params, body),
+                    Some(hir::Decl::ExternFn { .. }) => {
+                        return Err(self.error(Diagnostic::error()
+                            .code(ErrorCode::E0074)
+                            .message(msg!("consteval-extern-call", [
+                                func.clone().into()
+                            ]))
+                            .label(expr.loc)
+                            .note(msg!("consteval-extern-call-note"))));
+                    },
+                    _ => return Err(self.unsupported(expr.loc)),
+                };
+                if self.frames.len() >= MAX_DEPTH {
+                    return Err(self.error(Diagnostic::error()
+                        .code(ErrorCode::E0075)
+                        .message(msg!("consteval-too-deep", [
+                            MAX_DEPTH.into()
+                        ]))
+                        .label(expr.loc)));
+                }
+                let mut callee_env = params.iter()
+                    .map(|param| param.id)
+                    .zip(values)
+                    .collect::<Env>();
+                self.frames.push(Frame { loc : expr.loc, kind : FrameKind::Call(func.clone()) });
+                let result = self.eval_expr(&mut callee_env, body);
+                self.frames.pop();
+                match result {
+                    Ok(value) | Err(Interrupt::Return(value)) => value,
+                    Err(interrupt) => return Err(interrupt),
+                }
+            },
+            hir::ExprKind::Return(value) => {
+                let value = self.eval_opt(env, value)?;
+                return Err(Interrupt::Return(value));
+            },
+            hir::ExprKind::Break(value) => {
+                let value = self.eval_opt(env, value)?;
+                return Err(Interrupt::Break(value));
+            },
+            hir::ExprKind::Continue => return Err(Interrupt::Continue),
+            hir::ExprKind::Loop(body) => loop {
+                match self.eval_expr(env, body) {
+                    Ok(..) | Err(Interrupt::Continue) => (),
+                    Err(Interrupt::Break(value)) => break value,
+                    Err(interrupt) => return Err(interrupt),
+                }
+            },
+            hir::ExprKind::While { cond, body } => {
+                while self.eval_bool(env, cond)? {
+                    match self.eval_expr(env, body) {
+                        Ok(..) | Err(Interrupt::Continue) => (),
+                        Err(Interrupt::Break(..)) => break,
+                        Err(interrupt) => return Err(interrupt),
+                    }
+                }
+                ConstValue::Unit
+            },
+            hir::ExprKind::If { cond, then_branch, else_branch } => {
+                if self.eval_bool(env, cond)? {
+                    let value = self.eval_expr(env, then_branch)?;
+                    if else_branch.is_some() { value } else { ConstValue::Unit }
+                } else if let Some(else_branch) = else_branch {
+                    self.eval_expr(env, else_branch)?
+                } else {
+                    ConstValue::Unit
+                }
+            },
+            hir::ExprKind::Assign { target, value } => {
+                let hir::ExprKind::Local(id) = target.kind else {
+                    return Err(self.unsupported(target.loc));
+                };
+                let value = self.eval_expr(env, value)?;
+                env.insert(id, value);
+                ConstValue::Unit
+            },
+            hir::ExprKind::Binary { op : op @ (hir::BinaryOp::And | hir::BinaryOp::Or), lhs, rhs } => {
+                let lhs = self.eval_bool(env, lhs)?;
+                // the right-hand side is only evaluated if it's needed
+                let value = match op {
+                    hir::BinaryOp::And => lhs && self.eval_bool(env, rhs)?,
+                    _ => lhs || self.eval_bool(env, rhs)?,
+                };
+                ConstValue::Bool(value)
+            },
+            hir::ExprKind::Binary { op, lhs : lhs_expr, rhs } => {
+                let lhs = self.eval_expr(env, lhs_expr)?;
+                let rhs = self.eval_expr(env, rhs)?;
+                self.eval_binary(*op, lhs, rhs, &lhs_expr.ty, expr.loc)?
+            },
+            hir::ExprKind::Unary { op, value } => {
+                match (op, self.eval_expr(env, value)?) {
+                    (hir::UnaryOp::Neg, ConstValue::Int(n)) => {
+                        self.check_overflow(n.checked_neg(), &expr.ty, expr.loc, || {
+                            format!("-{}", n)
+                        })?
+                    },
+                    (hir::UnaryOp::Neg, ConstValue::Float(n)) => ConstValue::Float(-n),
+                    (hir::UnaryOp::Neg, ConstValue::Rational(n)) => ConstValue::Rational(n.neg()),
+                    (hir::UnaryOp::Not, ConstValue::Bool(b)) => ConstValue::Bool(!b),
+                    _ => return Err(self.unsupported(expr.loc)),
+                }
+            },
+            hir::ExprKind::Convert(value) => {
+                match self.eval_expr(env, value)? {
+                    ConstValue::Int(n) if expr.ty == hir::Type::Rational => {
+                        ConstValue::Rational(Rational::from_i128(n))
+                    },
+                    ConstValue::Int(n) if expr.ty.is_float() => {
+                        ConstValue::Float(round_float(n as f64, &expr.ty))
+                    },
+                    ConstValue::Int(n) => ConstValue::Int(wrap_int(n, &expr.ty)),
+                    ConstValue::Float(n) if expr.ty == hir::Type::Rational => {
+                        // infinities and NaN have no exact value
+                        let Some(n) = Rational::from_f64(n) else {
+                            return Err(self.error(Diagnostic::error()
+                                .code(ErrorCode::E0078)
+                                .message(msg!("consteval-overflow", [
+                                    format!("rational({:?})", n).into(),
+                                    expr.ty.to_string().into()
+                                ]))
+                                .label(expr.loc)));
+                        };
+                        ConstValue::Rational(n)
+                    },
+                    ConstValue::Float(n) if expr.ty.is_float() => {
+                        ConstValue::Float(round_float(n, &expr.ty))
+                    },
+                    ConstValue::Float(n) => ConstValue::Int(wrap_int(n as i128, &expr.ty)),
+                    ConstValue::Rational(n) => match expr.ty {
+                        hir::Type::Rational => ConstValue::Rational(n),
+                        hir::Type::Num(hir::NumType::F32) => ConstValue::Float(n.to_f32().into()),
+                        hir::Type::Num(hir::NumType::F64) => ConstValue::Float(n.to_f64()),
+                        // values outside of the range of `i128` saturate, like
+                        // conversions from floats
+                        _ => {
+                            let int = n.to_i128().unwrap_or(n.to_f64() as i128);
+                            ConstValue::Int(wrap_int(int, &expr.ty))
+                        },
+                    },
+                    _ => return Err(self.unsupported(expr.loc)),
+                }
+            },
+            hir::ExprKind::Assert(cond) => {
+                if !self.eval_bool(env, cond)? {
+                    return Err(self.error(Diagnostic::error()
+                        .code(ErrorCode::E0076)
+                        .message(msg!("consteval-assert-failed"))
+                        .label(expr.loc)));
+                }
+                ConstValue::Unit
+            },
+            hir::ExprKind::Str(..)
+            | hir::ExprKind::Array(..)
+            | hir::ExprKind::Index { .. }
+            | hir::ExprKind::Variant { .. }
+            | hir::ExprKind::Match { .. } => return Err(self.unsupported(expr.loc)),
+        };
+        Ok(value)
+    }
+
+    fn eval_opt(
+        &mut self,
+        env : &mut Env,
+        expr : &'m Option<Box<hir::Expr>>,
+    ) -> EvalResult<ConstValue> {
+        match expr {
+            Some(expr) => self.eval_expr(env, expr),
+            None => Ok(ConstValue::Unit),
+        }
+    }
+
+    fn eval_bool(&mut self, env : &mut Env, expr : &'m hir::Expr) -> EvalResult<bool> {
+        match self.eval_expr(env, expr)? {
+            ConstValue::Bool(b) => Ok(b),
+            _ => Err(self.unsupported(expr.loc)),
+        }
+    }
+
+    /// Applies a binary operator to two values whose type is `ty`.
+    fn eval_binary(
+        &mut self,
+        op : hir::BinaryOp,
+        lhs : ConstValue,
+        rhs : ConstValue,
+        ty : &hir::Type,
+        loc : Location,
+    ) -> EvalResult<ConstValue> {
+        use hir::BinaryOp as Op;
+        let value = match (lhs, rhs) {
+            (ConstValue::Int(lhs), ConstValue::Int(rhs)) => {
+                let result = match op {
+                    Op::Div | Op::Rem if rhs == 0 => {
+                        let message = if let Op::Div = op {
+                            msg!("consteval-divide-by-zero", [lhs.into()])
+                        } else {
+                            msg!("consteval-remainder-by-zero", [lhs.into()])
+                        };
+                        return Err(self.error(Diagnostic::error()
+                            .code(ErrorCode::E0077)
+                            .message(message)
+                            .label(loc)));
+                    },
+                    Op::Add => lhs.checked_add(rhs),
+                    Op::Sub => lhs.checked_sub(rhs),
+                    Op::Mul => lhs.checked_mul(rhs),
+                    Op::Div => lhs.checked_div(rhs),
+                    Op::Rem => lhs.checked_rem(rhs),
+                    _ => return Ok(ConstValue::Bool(compare(op, lhs.cmp(&rhs)))),
+                };
+                self.check_overflow(result, ty, loc, || {
+                    format!("{} {} {}", lhs, op.as_str(), rhs)
+                })?
+            },
+            (ConstValue::Float(lhs), ConstValue::Float(rhs)) => {
+                let result = match op {
+                    Op::Add => lhs + rhs,
+                    Op::Sub => lhs - rhs,
+                    Op::Mul => lhs * rhs,
+                    Op::Div => lhs / rhs,
+                    Op::Rem => lhs % rhs,
+                    // comparisons with NaN are always false, except `!=`
+                    _ => return Ok(ConstValue::Bool(match lhs.partial_cmp(&rhs) {
+                        Some(ordering) => compare(op, ordering),
+                        None => matches!(op, Op::Ne),
+                    })),
+                };
+                ConstValue::Float(round_float(result, ty))
+            },
+            (ConstValue::Rational(lhs), ConstValue::Rational(rhs)) => {
+                let result = match op {
+                    Op::Add => Some(lhs.add(&rhs)),
+                    Op::Sub => Some(lhs.sub(&rhs)),
+                    Op::Mul => Some(lhs.mul(&rhs)),
+                    Op::Div => lhs.div(&rhs),
+                    Op::Rem => lhs.rem(&rhs),
+                    _ => return Ok(ConstValue::Bool(compare(op, lhs.cmp(&rhs)))),
+                };
+                let Some(result) = result else {
+                    let message = if let Op::Div = op {
+                        msg!("consteval-divide-by-zero", [lhs.to_string().into()])
+                    } else {
+                        msg!("consteval-remainder-by-zero", [lhs.to_string().into()])
+                    };
+                    return Err(self.error(Diagnostic::error()
+                        .code(ErrorCode::E0077)
+                        .message(message)
+                        .label(loc)));
+                };
+                if result.bits() > MAX_RATIONAL_BITS {
+                    return Err(self.error(Diagnostic::error()
+                        .code(ErrorCode::E0078)
+                        .message(msg!("consteval-rational-too-large", [op.as_str().into()]))
+                        .label(loc)
+                        .note(msg!("consteval-rational-limit", [MAX_RATIONAL_BITS.into()]))));
+                }
+                ConstValue::Rational(result)
+            },
+            (ConstValue::Bool(lhs), ConstValue::Bool(rhs)) => ConstValue::Bool(match op {
+                Op::Eq => lhs == rhs,
+                _ => lhs != rhs,
+            }),
+            _ => return Err(self.unsupported(loc)),
+        };
+        Ok(value)
+    }
+
+    /// Reports an error if the result of an integer operation doesn't fit
+    /// into its type `ty`. The `operation` closure describes the operation.
+    fn check_overflow(
+        &mut self,
+        result : Option<i128>,
+        ty : &hir::Type,
+        loc : Location,
+        operation : impl FnOnce() -> String,
+    ) -> EvalResult<ConstValue> {
+        let (min, max) = ty.int_range().unwrap_or((i64::MIN.into(), i64::MAX.into()));
+        match result {
+            Some(n) if (min..=max).contains(&n) => Ok(ConstValue::Int(n)),
+            _ => Err(self.error(Diagnostic::error()
+                .code(ErrorCode::E0078)
+                .message(msg!("consteval-overflow", [
+                    operation().into(), ty.to_string().into()
+                ]))
+                .label(loc)
+                .note(msg!("consteval-valid-range", [
+                    ty.to_string().into(), min.into(), max.into()
+                ])))),
+        }
+    }
+
+    /// Adds any functions declared in a block to the functions which can be
+    /// called, so they can be called before they are defined.
+    fn declare_fns(&mut self, stmts : &'m [hir::Stmt]) {
+        let parent = self.frames.iter().rev().find_map(|frame| match &frame.kind {
+            FrameKind::Call(path) => Some(path.clone()),
+            FrameKind::Const(..) => None,
+        });
+        for stmt in stmts {
+            if let hir::Stmt::Decl(decl @ hir::Decl::Fn { name, .. }) = stmt {
+                let path = match &parent {
+                    Some(parent) => format!("{}::{}", parent, name.value),
+                    None => name.value.clone(),
+                };
+                self.fns.insert(path, decl);
+            }
+        }
+    }
+}
+
+/// Returns the result of a comparison operator given the ordering of its
+/// operands.
+fn compare(op : hir::BinaryOp, ordering : std::cmp::Ordering) -> bool {
+    use std::cmp::Ordering;
+    match op {
+        hir::BinaryOp::Eq => ordering == Ordering::Equal,
+        hir::BinaryOp::Ne => ordering != Ordering::Equal,
+        hir::BinaryOp::Lt => ordering == Ordering::Less,
+        hir::BinaryOp::Le => ordering != Ordering::Greater,
+        hir::BinaryOp::Gt => ordering == Ordering::Greater,
+        hir::BinaryOp::Ge => ordering != Ordering::Less,
+        _ => unreachable!("expected a comparison operator"),
+    }
+}
+
+/// Truncates an integer to the size of the integer type `ty`, the same way
+/// conversions do at runtime.
+fn wrap_int(n : i128, ty : &hir::Type) -> i128 {
+    let (bits, signed) = match ty {
+        hir::Type::Num(num) => (num.bits(), num.is_signed()),
+        _ => (64, true),
+    };
+    let modulus = 1i128 << bits;
+    let n = n.rem_euclid(modulus);
+    if signed && n >= modulus / 2 { n - modulus } else { n }
+}
+
+/// Rounds a float to the precision of the float type `ty`.
+fn round_float(n : f64, ty : &hir::Type) -> f64 {
+    match ty {
+        hir::Type::Num(hir::NumType::F32) => f64::from(n as f32),
+        _ => n,
+    }
+}
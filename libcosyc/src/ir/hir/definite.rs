@@ -0,0 +1,290 @@
+//! Checks that local variables declared without an initial value are always
+//! assigned to before they are used, and that immutable locals are assigned
+//! to at most once.
+
+use std::collections::{ HashMap, HashSet };
+
+use crate::src::{ Location, Located };
+use crate::error::{ Diagnostic, IssueManager, ErrorCode, Applicability };
+use crate::ir::hir;
+
+/// Reports an error for each use of a local variable in `body` which might
+/// not have been initialised, and for each assignment to an immutable local
+/// which might already have been initialised.
+///
+/// Note: the bodies of nested functions aren't checked, since they are
+/// checked separately when they are lowered.
+pub fn check_fn(issues : &mut IssueManager, body : &hir::Expr) {
+    let mut ctx = DefiniteAssignment {
+        issues,
+        locals : HashMap::new(),
+        loops : Vec::new(),
+        quiet : false,
+    };
+    let mut state = State::default();
+    ctx.check_expr(&mut state, body);
+}
+
+/// A local variable declared without an initial value.
+struct Deferred {
+    name : Located<hir::Symbol>,
+    mutable : bool,
+}
+
+/// The set of locals which have been assigned to at some point in the
+/// program.
+#[derive(Default, Clone)]
+struct State {
+    /// Locals which are assigned to on every path to this point.
+    definitely : HashSet<hir::LocalId>,
+    /// Locals which are assigned to on at least one path to this point.
+    maybe : HashSet<hir::LocalId>,
+    /// Whether this point can never be reached, e.g. because it comes after
+    /// a `return`.
+    diverges : bool,
+}
+
+impl State {
+    /// Combines the states at the end of two alternative paths. A path which
+    /// diverges never reaches the end, so it doesn't contribute anything.
+    fn join(self, other : State) -> State {
+        match (self.diverges, other.diverges) {
+            (true, _) => other,
+            (_, true) => self,
+            _ => State {
+                definitely : self.definitely.intersection(&other.definitely).copied().collect(),
+                maybe : self.maybe.union(&other.maybe).copied().collect(),
+                diverges : false,
+            },
+        }
+    }
+
+    /// Combines an optional state with the state of another path.
+    fn join_into(state : &mut Option<State>, other : State) {
+        *state = Some(match state.take() {
+            Some(state) => state.join(other),
+            None => other,
+        });
+    }
+}
+
+/// The states at each `break` and `continue` of a loop.
+#[derive(Default)]
+struct LoopStates {
+    breaks : Option<State>,
+    continues : Option<State>,
+}
+
+struct DefiniteAssignment<'a> {
+    issues : &'a mut IssueManager,
+    /// Every local declared without an initial value seen so far.
+    locals : HashMap<hir::LocalId, Deferred>,
+    /// For each loop enclosing the current expression, the combined states
+    /// of every `break` and `continue` of that loop.
+    loops : Vec<LoopStates>,
+    /// Whether errors are ignored, e.g. while finding the state at the end
+    /// of the first iteration of a loop.
+    quiet : bool,
+}
+
+impl DefiniteAssignment<'_> {
+    fn check_stmt(&mut self, state : &mut State, stmt : &hir::Stmt) {
+        match stmt {
+            hir::Stmt::Decl(..) => (),
+            hir::Stmt::Expr(expr) => self.check_expr(state, expr),
+            hir::Stmt::Local { init : Some(init), .. } => self.check_expr(state, init),
+            hir::Stmt::Local { name, id, mutable, init : None, .. } => {
+                // locals declared inside of loops are uninitialised at the
+                // start of each iteration
+                state.definitely.remove(id);
+                state.maybe.remove(id);
+                self.locals.insert(*id, Deferred { name : name.clone(), mutable : *mutable });
+            },
+        }
+    }
+
+    fn check_expr(&mut self, state : &mut State, expr : &hir::Expr) {
+        match &expr.kind {
+            hir::ExprKind::NumIntegral(..)
+            | hir::ExprKind::NumRational(..)
+            | hir::ExprKind::Bool(..)
+            | hir::ExprKind::Str(..)
+            | hir::ExprKind::Const(..) => (),
+            hir::ExprKind::Local(id) => self.check_use(state, *id, expr.loc),
+            hir::ExprKind::Block(stmts) => {
+                for stmt in stmts {
+                    self.check_stmt(state, stmt);
+                }
+            },
+            hir::ExprKind::Array(elems) => {
+                for elem in elems {
+                    self.check_expr(state, elem);
+                }
+            },
+            hir::ExprKind::Index { target, index } => {
+                self.check_expr(state, target);
+                self.check_expr(state, index);
+            },
+            hir::ExprKind::Variant { args, .. }
+            | hir::ExprKind::Call { args, .. } => {
+                for arg in args {
+                    self.check_expr(state, arg);
+                }
+            },
+            hir::ExprKind::Match { scrutinee, arms } => {
+                self.check_expr(state, scrutinee);
+                let mut result = None;
+                for arm in arms {
+                    let mut arm_state = state.clone();
+                    self.check_expr(&mut arm_state, &arm.body);
+                    State::join_into(&mut result, arm_state);
+                }
+                if let Some(result) = result {
+                    *state = result;
+                }
+            },
+            hir::ExprKind::Return(value) => {
+                if let Some(value) = value {
+                    self.check_expr(state, value);
+                }
+                state.diverges = true;
+            },
+            hir::ExprKind::Break(value) => {
+                if let Some(value) = value {
+                    self.check_expr(state, value);
+                }
+                if let Some(states) = self.loops.last_mut() {
+                    State::join_into(&mut states.breaks, state.clone());
+                }
+                state.diverges = true;
+            },
+            hir::ExprKind::Continue => {
+                if let Some(states) = self.loops.last_mut() {
+                    State::join_into(&mut states.continues, state.clone());
+                }
+                state.diverges = true;
+            },
+            hir::ExprKind::Loop(body) => self.check_loop(state, None, body),
+            hir::ExprKind::Assign { target, value } => {
+                self.check_expr(state, value);
+                match &target.kind {
+                    hir::ExprKind::Local(id) => self.check_assign(state, *id, target.loc),
+                    // assigning to an element of an array reads the array
+                    _ => self.check_expr(state, target),
+                }
+            },
+            hir::ExprKind::If { cond, then_branch, else_branch } => {
+                self.check_expr(state, cond);
+                let mut then_state = state.clone();
+                self.check_expr(&mut then_state, then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.check_expr(state, else_branch);
+                }
+                *state = then_state.join(state.clone());
+            },
+            hir::ExprKind::While { cond, body } => {
+                self.check_loop(state, Some(cond.as_ref()), body)
+            },
+            hir::ExprKind::Binary { op, lhs, rhs } => {
+                self.check_expr(state, lhs);
+                if let hir::BinaryOp::And | hir::BinaryOp::Or = op {
+                    // the right-hand side is only evaluated sometimes
+                    let mut rhs_state = state.clone();
+                    self.check_expr(&mut rhs_state, rhs);
+                    *state = rhs_state.join(state.clone());
+                } else {
+                    self.check_expr(state, rhs);
+                }
+            },
+            hir::ExprKind::Unary { value, .. }
+            | hir::ExprKind::Convert(value)
+            | hir::ExprKind::Assert(value) => self.check_expr(state, value),
+        }
+    }
+
+    /// Checks a `loop` or `while` loop. The body may run more than once, so
+    /// it's checked starting from both the state before the loop and the
+    /// state at the end of the first iteration.
+    fn check_loop(&mut self, state : &mut State, cond : Option<&hir::Expr>, body : &hir::Expr) {
+        let quiet = std::mem::replace(&mut self.quiet, true);
+        let (back_edge, _) = self.check_iteration(state.clone(), cond, body);
+        self.quiet = quiet;
+        let head = state.clone().join(back_edge);
+        let (_, exit) = self.check_iteration(head, cond, body);
+        *state = exit;
+    }
+
+    /// Checks a single iteration of a loop, returning the state at the end of
+    /// the iteration and the state after the loop.
+    fn check_iteration(
+        &mut self,
+        mut state : State,
+        cond : Option<&hir::Expr>,
+        body : &hir::Expr,
+    ) -> (State, State) {
+        if let Some(cond) = cond {
+            self.check_expr(&mut state, cond);
+        }
+        let mut body_state = state.clone();
+        self.loops.push(LoopStates::default());
+        self.check_expr(&mut body_state, body);
+        let states = self.loops.pop().unwrap();
+        let back_edge = match states.continues {
+            Some(continues) => continues.join(body_state),
+            None => body_state,
+        };
+        // a `while` loop exits when its condition is false, but a `loop`
+        // can only be exited using `break`
+        let exit = match (cond, states.breaks) {
+            (Some(_), Some(breaks)) => state.join(breaks),
+            (Some(_), None) => state,
+            (None, Some(breaks)) => breaks,
+            (None, None) => State { diverges : true, ..state },
+        };
+        (back_edge, exit)
+    }
+
+    fn check_use(&mut self, state : &State, id : hir::LocalId, loc : Location) {
+        let Some(local) = self.locals.get(&id) else { return };
+        if self.quiet || state.diverges || state.definitely.contains(&id) {
+            return;
+        }
+        let name = local.name.value.clone();
+        let message = if state.maybe.contains(&id) {
+            msg!("hir-use-maybe-uninit", [name.into()])
+        } else {
+            msg!("hir-use-uninit", [name.into()])
+        };
+        Diagnostic::error()
+            .code(ErrorCode::E0079)
+            .message(message)
+            .label(loc)
+            .label_other((local.name.loc, msg!("hir-declared-uninit")))
+            .report(self.issues);
+    }
+
+    fn check_assign(&mut self, state : &mut State, id : hir::LocalId, loc : Location) {
+        if let Some(local) = self.locals.get(&id)
+                && !self.quiet
+                && !local.mutable
+                && !state.diverges
+                && state.maybe.contains(&id) {
+            Diagnostic::error()
+                .code(ErrorCode::E0040)
+                .message(msg!("hir-assign-twice", [
+                    local.name.value.clone().into()
+                ]))
+                .label(loc)
+                .label_other((local.name.loc, msg!("hir-declared-here")))
+                .suggest(
+                    local.name.loc.start(),
+                    "mut ",
+                    Applicability::MachineApplicable,
+                    msg!("hir-make-mutable-help"),
+                )
+                .report(self.issues);
+        }
+        state.definitely.insert(id);
+        state.maybe.insert(id);
+    }
+}
@@ -0,0 +1,359 @@
+//! Checks `match` expressions for missing and unreachable patterns, using the
+//! pattern usefulness algorithm described in "Warnings for pattern matching"
+//! by Luc Maranget.
+
+use std::collections::HashMap;
+
+use crate::src::Location;
+use crate::error::{ lint, Diagnostic, IssueManager, ErrorCode };
+use crate::ir::hir;
+
+/// The maximum number of missing patterns to list in an error message.
+const MAX_MISSING : usize = 3;
+
+/// Reports an error if the arms of a `match` expression don't cover every
+/// possible value of `scrutinee`, and a warning for each arm which can never
+/// be reached.
+pub fn check_match(
+    issues : &mut IssueManager,
+    enums : &HashMap<hir::Symbol, Vec<hir::Variant>>,
+    scrutinee : &hir::Expr,
+    arms : &[hir::MatchArm],
+    match_loc : Location,
+) {
+    if scrutinee.ty.is_unknown() {
+        return;
+    }
+    let ctx = Usefulness { enums };
+    let tys = [scrutinee.ty.clone()];
+    let mut matrix = Vec::new();
+    for arm in arms {
+        let row = vec![Pat::from_pattern(&arm.pattern)];
+        if ctx.witnesses(&matrix, &tys, &row).is_empty() {
+            Diagnostic::lint(&lint::UNREACHABLE_PATTERNS)
+                .code(ErrorCode::E0080)
+                .message(msg!("hir-unreachable-pattern"))
+                .label((arm.pattern.loc, msg!("hir-unreachable-pattern-label")))
+                .report(issues);
+        }
+        matrix.push(row);
+    }
+    let missing = ctx.witnesses(&matrix, &tys, &[Pat::Wild]);
+    if missing.is_empty() {
+        return;
+    }
+    let shown = missing.iter()
+        .take(MAX_MISSING)
+        .map(|witness| format!("`{}`", ctx.show(&witness[0], &scrutinee.ty)))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = if missing.len() > MAX_MISSING {
+        msg!("hir-non-exhaustive-more", [shown.into(), (missing.len() - MAX_MISSING).into()])
+    } else {
+        msg!("hir-non-exhaustive", [shown.into()])
+    };
+    Diagnostic::error()
+        .code(ErrorCode::E0081)
+        .message(message)
+        .label(match_loc)
+        .label_other((scrutinee.loc, msg!("hir-has-type", [
+            scrutinee.ty.to_string().into()
+        ])))
+        .note(msg!("hir-non-exhaustive-help"))
+        .report(issues);
+}
+
+/// The name and field types of a variant.
+type VariantFields = (hir::Symbol, Vec<hir::Type>);
+
+/// A simplified pattern, ignoring bindings.
+#[derive(Debug, Clone)]
+enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+/// The value constructors which can appear in patterns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Ctor {
+    Variant(usize),
+    Bool(bool),
+    Int(u128),
+}
+
+impl Pat {
+    fn from_pattern(pattern : &hir::Pattern) -> Pat {
+        match &pattern.kind {
+            hir::PatternKind::Wildcard
+            | hir::PatternKind::Binding(..) => Pat::Wild,
+            hir::PatternKind::NumIntegral(n) => Pat::Ctor(Ctor::Int(*n), Vec::new()),
+            hir::PatternKind::Bool(b) => Pat::Ctor(Ctor::Bool(*b), Vec::new()),
+            hir::PatternKind::Variant { variant, fields } => Pat::Ctor(
+                Ctor::Variant(*variant),
+                fields.iter().map(Pat::from_pattern).collect(),
+            ),
+        }
+    }
+}
+
+/// Specialises a row of patterns by a constructor, returning `None` if the
+/// row could never match a value built using that constructor.
+fn specialise(row : &[Pat], ctor : &Ctor, arity : usize) -> Option<Vec<Pat>> {
+    let mut result = match &row[0] {
+        Pat::Wild => vec![Pat::Wild; arity],
+        Pat::Ctor(head, fields) if head == ctor => fields.clone(),
+        Pat::Ctor(..) => return None,
+    };
+    result.extend_from_slice(&row[1..]);
+    Some(result)
+}
+
+struct Usefulness<'a> {
+    enums : &'a HashMap<hir::Symbol, Vec<hir::Variant>>,
+}
+
+impl Usefulness<'_> {
+    /// Returns the name of a type with variants, and the name and fields of
+    /// each of its variants.
+    fn variants(&self, ty : &hir::Type) -> Option<(hir::Symbol, Vec<VariantFields>)> {
+        match ty {
+            hir::Type::Enum(name) => Some((name.clone(), self.enums.get(name)?.iter()
+                .map(|variant| (variant.name.value.clone(), variant.fields.clone()))
+                .collect())),
+            hir::Type::Result { ok, err } => Some(("Result".to_string(),
+                hir::Type::result_variants(ok, err).into_iter()
+                    .map(|(name, field)| (name.to_string(), vec![field]))
+                    .collect())),
+            _ => None,
+        }
+    }
+
+    /// Returns every constructor of a type, or `None` if there are infinitely
+    /// many (or the type cannot be matched on using constructors).
+    fn all_ctors(&self, ty : &hir::Type) -> Option<Vec<Ctor>> {
+        if let hir::Type::Bool = ty {
+            return Some(vec![Ctor::Bool(true), Ctor::Bool(false)]);
+        }
+        let (_, variants) = self.variants(ty)?;
+        Some((0..variants.len()).map(Ctor::Variant).collect())
+    }
+
+    /// Returns the types of the fields of a constructor.
+    fn field_types(&self, ty : &hir::Type, ctor : &Ctor) -> Vec<hir::Type> {
+        let Ctor::Variant(i) = ctor else { return Vec::new() };
+        self.variants(ty)
+            .and_then(|(_, variants)| variants.into_iter().nth(*i))
+            .map(|(_, fields)| fields)
+            .unwrap_or_default()
+    }
+
+    /// Returns the values matched by `row` which aren't matched by any row of
+    /// `matrix`. If this is empty, then `row` is redundant.
+    fn witnesses(
+        &self,
+        matrix : &[Vec<Pat>],
+        tys : &[hir::Type],
+        row : &[Pat],
+    ) -> Vec<Vec<Pat>> {
+        if row.is_empty() {
+            return if matrix.is_empty() { vec![Vec::new()] } else { Vec::new() };
+        }
+        let ty = &tys[0];
+        match &row[0] {
+            Pat::Ctor(ctor, _) => self.witnesses_ctor(matrix, tys, row, ctor),
+            Pat::Wild => {
+                let mut used = Vec::new();
+                for matrix_row in matrix {
+                    if let Pat::Ctor(ctor, _) = &matrix_row[0]
+                            && !used.contains(ctor) {
+                        used.push(ctor.clone());
+                    }
+                }
+                let all = self.all_ctors(ty);
+                if let Some(all) = &all && all.iter().all(|ctor| used.contains(ctor)) {
+                    // every constructor is covered, so check each of them
+                    return all.iter()
+                        .flat_map(|ctor| self.witnesses_ctor(matrix, tys, row, ctor))
+                        .collect();
+                }
+                // otherwise, only the rows with wildcards can match a missing
+                // constructor, but the constructors which are used may still
+                // be partly covered
+                let default = matrix.iter()
+                    .filter(|matrix_row| matches!(matrix_row[0], Pat::Wild))
+                    .map(|matrix_row| matrix_row[1..].to_vec())
+                    .collect::<Vec<_>>();
+                let witnesses = self.witnesses(&default, &tys[1..], &row[1..]);
+                let with_head = |head : Pat| witnesses.iter().map(move |witness| {
+                    let mut new_witness = vec![head.clone()];
+                    new_witness.extend_from_slice(witness);
+                    new_witness
+                });
+                let mut result = Vec::new();
+                match all {
+                    Some(all) if !used.is_empty() => for ctor in all {
+                        if used.contains(&ctor) {
+                            result.extend(self.witnesses_ctor(matrix, tys, row, &ctor));
+                        } else {
+                            let arity = self.field_types(ty, &ctor).len();
+                            result.extend(with_head(Pat::Ctor(ctor, vec![Pat::Wild; arity])));
+                        }
+                    },
+                    _ => {
+                        for ctor in &used {
+                            result.extend(self.witnesses_ctor(matrix, tys, row, ctor));
+                        }
+                        result.extend(with_head(Pat::Wild));
+                    },
+                }
+                result
+            },
+        }
+    }
+
+    fn witnesses_ctor(
+        &self,
+        matrix : &[Vec<Pat>],
+        tys : &[hir::Type],
+        row : &[Pat],
+        ctor : &Ctor,
+    ) -> Vec<Vec<Pat>> {
+        let mut field_tys = self.field_types(&tys[0], ctor);
+        let arity = field_tys.len();
+        let matrix = matrix.iter()
+            .filter_map(|matrix_row| specialise(matrix_row, ctor, arity))
+            .collect::<Vec<_>>();
+        let Some(row) = specialise(row, ctor, arity) else {
+            return Vec::new();
+        };
+        field_tys.extend_from_slice(&tys[1..]);
+        self.witnesses(&matrix, &field_tys, &row)
+            .into_iter()
+            .map(|mut witness| {
+                let rest = witness.split_off(arity);
+                let mut result = vec![Pat::Ctor(ctor.clone(), witness)];
+                result.extend(rest);
+                result
+            })
+            .collect()
+    }
+
+    /// Converts a pattern into a human-readable string.
+    fn show(&self, pat : &Pat, ty : &hir::Type) -> String {
+        match pat {
+            Pat::Wild => "_".to_string(),
+            Pat::Ctor(Ctor::Bool(b), _) => b.to_string(),
+            Pat::Ctor(Ctor::Int(n), _) => n.to_string(),
+            Pat::Ctor(ctor @ Ctor::Variant(i), fields) => {
+                let Some((ty_name, variants)) = self.variants(ty) else {
+                    return "_".to_string();
+                };
+                let Some((name, _)) = variants.get(*i) else { return "_".to_string() };
+                let mut result = format!("{}::{}", ty_name, name);
+                if !fields.is_empty() {
+                    let field_tys = self.field_types(ty, ctor);
+                    let fields = fields.iter()
+                        .zip(&field_tys)
+                        .map(|(field, field_ty)| self.show(field, field_ty))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    result.push_str(&format!("({})", fields));
+                }
+                result
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::src::{ Located, Span };
+
+    fn shape_enums() -> HashMap<hir::Symbol, Vec<hir::Variant>> {
+        let loc = Location { span : Span { start : 0, end : 0 }, file_id : 0 };
+        let variant = |name : &str, fields : Vec<hir::Type>| hir::Variant {
+            name : Located { value : name.to_string(), loc },
+            fields,
+        };
+        HashMap::from([("Shape".to_string(), vec![
+            variant("Empty", vec![]),
+            variant("Circle", vec![hir::Type::Int]),
+            variant("Rect", vec![hir::Type::Int, hir::Type::Int]),
+        ])])
+    }
+
+    fn variant(i : usize, fields : Vec<Pat>) -> Pat {
+        Pat::Ctor(Ctor::Variant(i), fields)
+    }
+
+    /// Returns the values of type `ty` which aren't matched by any of `rows`.
+    fn missing(ty : hir::Type, rows : Vec<Pat>) -> Vec<String> {
+        let enums = shape_enums();
+        let ctx = Usefulness { enums : &enums };
+        let matrix = rows.into_iter().map(|pat| vec![pat]).collect::<Vec<_>>();
+        ctx.witnesses(&matrix, &[ty.clone()], &[Pat::Wild])
+            .iter()
+            .map(|witness| ctx.show(&witness[0], &ty))
+            .collect()
+    }
+
+    #[test]
+    fn every_variant_covered() {
+        let shape = hir::Type::Enum("Shape".to_string());
+        let rows = vec![
+            variant(0, vec![]),
+            variant(1, vec![Pat::Wild]),
+            variant(2, vec![Pat::Wild, Pat::Wild]),
+        ];
+        assert!(missing(shape, rows).is_empty());
+    }
+
+    #[test]
+    fn unused_variants_missing() {
+        let shape = hir::Type::Enum("Shape".to_string());
+        let rows = vec![variant(1, vec![Pat::Wild])];
+        assert_eq!(missing(shape, rows), ["Shape::Empty", "Shape::Rect(_, _)"]);
+    }
+
+    #[test]
+    fn partly_covered_variants_missing() {
+        let shape = hir::Type::Enum("Shape".to_string());
+        let rows = vec![
+            variant(1, vec![Pat::Ctor(Ctor::Int(1), vec![])]),
+            variant(2, vec![Pat::Wild, Pat::Wild]),
+        ];
+        assert_eq!(missing(shape, rows), ["Shape::Empty", "Shape::Circle(_)"]);
+    }
+
+    #[test]
+    fn nested_result_missing() {
+        let ty = hir::Type::Result {
+            ok : Box::new(hir::Type::Bool),
+            err : Box::new(hir::Type::Int),
+        };
+        let rows = vec![
+            variant(0, vec![Pat::Ctor(Ctor::Bool(true), vec![])]),
+            variant(1, vec![Pat::Wild]),
+        ];
+        assert_eq!(missing(ty, rows), ["Result::Ok(false)"]);
+    }
+
+    #[test]
+    fn integers_need_wildcard() {
+        let rows = vec![Pat::Ctor(Ctor::Int(1), vec![])];
+        assert_eq!(missing(hir::Type::Int, rows), ["_"]);
+        let rows = vec![Pat::Ctor(Ctor::Int(1), vec![]), Pat::Wild];
+        assert!(missing(hir::Type::Int, rows).is_empty());
+    }
+
+    #[test]
+    fn redundant_row_has_no_witnesses() {
+        let enums = shape_enums();
+        let ctx = Usefulness { enums : &enums };
+        let matrix = vec![vec![Pat::Ctor(Ctor::Bool(true), vec![])], vec![Pat::Wild]];
+        let row = [Pat::Ctor(Ctor::Bool(false), vec![])];
+        assert!(ctx.witnesses(&matrix, &[hir::Type::Bool], &row).is_empty());
+        assert!(!ctx.witnesses(&matrix[..1], &[hir::Type::Bool], &row).is_empty());
+    }
+}
@@ -1,23 +1,165 @@
-use crate::error::{ Diagnostic, IssueManager };
+use std::collections::HashMap;
+
+use crate::src::{ Location, Located, Message };
+use crate::error::{ lint, Diagnostic, IssueManager, ErrorCode, Applicability };
 use crate::ir::{ ast, hir };
+use crate::ir::hir::{ exhaustive, definite, consteval };
+use crate::ir::hir::rational::Rational;
+
+/// The names of the types built into the language.
+const BUILTIN_TYPES : &[&str] = &["int", "rational", "bool", "str", "Result"];
 
-/// Convert an AST into an untyped HIR.
+/// Convert an AST into a typed HIR.
+///
+/// Expressions whose types could not be inferred are given the type
+/// `hir::Type::Unknown`, but only after an error has been reported.
 pub fn from_ast(
     issues : &mut IssueManager,
     ast_node : &ast::Node,
 ) -> hir::Module {
-    let mut ctx = Ast2Hir { issues };
-    ctx.lower_module(ast_node)
+    from_ast_with_prelude(issues, None, ast_node)
+}
+
+/// Similar to `from_ast`, except the declarations of `prelude` are also
+/// included in the module, unless the module declares something with the
+/// same name.
+pub fn from_ast_with_prelude(
+    issues : &mut IssueManager,
+    prelude : Option<&ast::Node>,
+    ast_node : &ast::Node,
+) -> hir::Module {
+    let mut ctx = Ast2Hir {
+        issues,
+        locals : Vec::new(),
+        next_local : 0,
+        type_names : Vec::new(),
+        enums : HashMap::new(),
+        fns : Vec::new(),
+        current_fn : None,
+        loops : Vec::new(),
+        const_names : Vec::new(),
+        const_annotations : HashMap::new(),
+        consts : HashMap::new(),
+    };
+    let mut module = ctx.lower_module(prelude, ast_node);
+    consteval::eval_module(issues, &mut module);
+    module
+}
+
+struct LocalDef {
+    name : hir::Symbol,
+    id : hir::LocalId,
+    /// The type of the local. This is `None` if the local was declared
+    /// without a type or an initial value, and hasn't been assigned to yet.
+    ty : Option<hir::Type>,
+    kind : LocalKind,
+    loc : Location,
+}
+
+/// The different ways a local variable can be introduced, which determine
+/// whether it can be assigned to.
+#[derive(Clone, Copy)]
+enum LocalKind {
+    Param,
+    Binding,
+    /// A local declared using `local`. If the local has no initial value,
+    /// then it is `deferred`, and can be assigned to once even if it isn't
+    /// `mutable`.
+    Local {
+        mutable : bool,
+        deferred : bool,
+    },
+}
+
+impl LocalKind {
+    /// Returns a description of this kind of local, for use in error
+    /// messages.
+    fn description(&self) -> Message {
+        match self {
+            LocalKind::Param => msg!("hir-local-kind-param"),
+            LocalKind::Binding => msg!("hir-local-kind-binding"),
+            LocalKind::Local { .. } => msg!("hir-local-kind-local"),
+        }
+    }
+}
+
+/// The name and fields of a variant, and where it was defined if it is
+/// user-defined.
+type VariantInfo = (hir::Symbol, Vec<hir::Type>, Option<Location>);
+
+/// The signature of a function, which is known before its body is lowered.
+#[derive(Clone)]
+struct FnDef {
+    name : hir::Symbol,
+    /// The fully-qualified name of the function, including the names of any
+    /// functions it is nested inside of.
+    path : hir::Symbol,
+    params : Vec<hir::Type>,
+    /// The return type of the function. If the function doesn't specify a
+    /// return type, this is `None` until its body has been lowered.
+    ret : Option<hir::Type>,
+    loc : Location,
+}
+
+/// Information about the function whose body is currently being lowered.
+struct FnCtx {
+    path : hir::Symbol,
+    /// The return type of the function. If the function doesn't specify a
+    /// return type, this is inferred from the first `return` expression.
+    ret : Option<hir::Type>,
+    ret_loc : Option<Location>,
+    params_loc : Location,
+    /// The location of the first `return` expression in the function body.
+    first_return : Option<Location>,
+}
+
+/// Information about a loop whose body is currently being lowered.
+struct LoopCtx {
+    /// The location of the `loop` or `while` keyword.
+    loc : Location,
+    /// Whether this is a `loop`, rather than a `while` loop. Only `loop`s can
+    /// produce values using `break`.
+    produces_value : bool,
+    /// The type of the value produced by the loop, inferred from the first
+    /// `break` expression.
+    break_ty : Option<hir::Type>,
+    break_loc : Option<Location>,
+    /// The type the loop is expected to have, used to infer the types of
+    /// `break` values.
+    expected : Option<hir::Type>,
 }
 
 struct Ast2Hir<'a> {
     issues : &'a mut IssueManager,
+    /// Stack of local variables currently in scope. Later definitions shadow
+    /// earlier ones.
+    locals : Vec<LocalDef>,
+    next_local : hir::LocalId,
+    /// The names of every type declared in the module, including those which
+    /// haven't been lowered yet.
+    type_names : Vec<hir::Symbol>,
+    enums : HashMap<hir::Symbol, Vec<hir::Variant>>,
+    /// Stack of functions currently in scope, similar to `locals`.
+    fns : Vec<FnDef>,
+    current_fn : Option<FnCtx>,
+    /// Stack of loops enclosing the expression currently being lowered.
+    loops : Vec<LoopCtx>,
+    /// The names of every constant declared in the module, including those
+    /// which haven't been lowered yet.
+    const_names : Vec<hir::Symbol>,
+    /// The types of constants declared with a type annotation, by the
+    /// location of their name.
+    const_annotations : HashMap<Location, hir::Type>,
+    /// The types of constants which have been lowered, or whose type is
+    /// known from their annotation.
+    consts : HashMap<hir::Symbol, hir::Type>,
 }
 
 impl<'a> Ast2Hir<'a> {
-    fn assert(&mut self, got : &ast::Node, message : &str) -> Option<()> {
+    fn assert(&mut self, got : &ast::Node, message : Message) -> Option<()> {
         Diagnostic::error()
-            .message(("malformed AST! {}, got {}", [
+            .code(ErrorCode::E0018)
+            .message(msg!("hir-malformed-ast", [
                 message.into(), got.name().into()
             ]))
             .label(got.primary_location())
@@ -25,69 +167,2368 @@ impl<'a> Ast2Hir<'a> {
         None
     }
 
-    fn lower_module(&mut self, ast_node : &ast::Node) -> hir::Module {
-        hir::Module::default()
+    fn assert_type(
+        &mut self,
+        expr : &hir::Expr,
+        expected : &hir::Type,
+        message : Message,
+    ) -> Option<()> {
+        if expr.ty.is_compatible(expected) {
+            return Some(());
+        }
+        Diagnostic::error()
+            .code(ErrorCode::E0019)
+            .message(msg!("hir-mismatched-type-got", [
+                message.into(), expr.ty.to_string().into()
+            ]))
+            .label((expr.loc, msg!("hir-expected-type", [
+                expected.to_string().into()
+            ])))
+            .report(self.issues);
+        None
     }
-}
 
-/*
-pub mod hir;
-pub mod casm;
+    /// Returns the declarations in the body of a module, along with their
+    /// visibility.
+    fn module_items<'n>(
+        &mut self,
+        ast_node : &'n ast::Node,
+    ) -> Option<Vec<(hir::Visibility, &'n ast::Node)>> {
+        let ast::Node::Block(blk) = ast_node else {
+            self.assert(ast_node, msg!("hir-ast-expected-module"));
+            return None;
+        };
+        let items = blk.value.iter().map(|node| {
+            if let ast::Node::Scope { vis, node } = node {
+                let vis = match vis.value {
+                    ast::Visibility::Public => hir::Visibility::Public,
+                    ast::Visibility::Internal => hir::Visibility::Internal,
+                };
+                (vis, node.as_ref())
+            } else {
+                (hir::Visibility::Internal, node)
+            }
+        }).collect();
+        Some(items)
+    }
 
-use crate::parse::ast;
-use crate::error::{ IssueManager, Diagnostic };
+    fn lower_module(
+        &mut self,
+        prelude : Option<&ast::Node>,
+        ast_node : &ast::Node,
+    ) -> hir::Module {
+        let mut module = hir::Module::default();
+        let Some(mut items) = self.module_items(ast_node) else { return module };
+        if let Some(prelude) = prelude
+                && let Some(prelude_items) = self.module_items(prelude) {
+            // declarations in the module shadow those in the prelude
+            let names = items.iter()
+                .filter_map(|(_, node)| decl_name(node))
+                .collect::<Vec<_>>();
+            items = prelude_items.into_iter()
+                .filter(|(_, node)| decl_name(node).is_none_or(|name| !names.contains(&name)))
+                .chain(items)
+                .collect();
+        }
+        // declare every type up-front, so types can refer to each other
+        // regardless of the order they were defined in
+        for (_, node) in &items {
+            match without_attrs(node) {
+                ast::Node::Type { name, .. } => self.type_names.push(name.value.clone()),
+                ast::Node::Const { name, .. } => self.const_names.push(name.value.clone()),
+                _ => (),
+            }
+        }
+        self.declare_consts(items.iter().map(|(_, node)| *node));
+        self.declare_fns(items.iter().map(|(_, node)| *node));
+        // type declarations are lowered before constants, which are lowered
+        // before functions, so the types of both are known when type checking
+        // function bodies
+        let (types, others) : (Vec<_>, Vec<_>) = items.into_iter()
+            .partition(|(_, node)| matches!(without_attrs(node), ast::Node::Type { .. }));
+        let (consts, others) : (Vec<_>, Vec<_>) = others.into_iter()
+            .partition(|(_, node)| matches!(without_attrs(node), ast::Node::Const { .. }));
+        for (vis, node) in types.into_iter().chain(consts).chain(others) {
+            let Some(decl) = self.lower_decl(node) else { continue };
+            if let Some(prev) = module.items.iter()
+                .find(|item| item.decl.name().value == decl.name().value)
+            {
+                let prev_loc = prev.decl.name().loc;
+                Diagnostic::error()
+                    .code(ErrorCode::E0020)
+                    .message(msg!("hir-redefinition", [
+                        decl.name().value.clone().into()
+                    ]))
+                    .label(decl.name().loc)
+                    .label_other((prev_loc, msg!("hir-previously-defined")))
+                    .report(self.issues);
+                continue;
+            }
+            if let hir::Decl::Enum { name, variants } = &decl {
+                self.enums.insert(name.value.clone(), variants.clone());
+                self.check_recursive_type(name, variants);
+            }
+            module.items.push(hir::ModuleItem { vis, decl });
+        }
+        module
+    }
 
-/// Responsible for building the first-pass HIR of a cosy file from its AST.
-pub struct Ast2Hir<'a> {
-    issues : &'a mut IssueManager,
-}
+    /// Reports an error if an enum contains itself, since it would have an
+    /// infinite size.
+    fn check_recursive_type(
+        &mut self,
+        name : &Located<hir::Symbol>,
+        variants : &[hir::Variant],
+    ) {
+        let mut visited = Vec::new();
+        let recursive = variants.iter()
+            .flat_map(|variant| &variant.fields)
+            .any(|field| self.type_contains(field, &name.value, &mut visited));
+        if recursive {
+            Diagnostic::error()
+                .code(ErrorCode::E0021)
+                .message(msg!("hir-recursive-type", [
+                    name.value.clone().into()
+                ]))
+                .label(name.loc)
+                .note(msg!("hir-recursive-type-note"))
+                .report(self.issues);
+        }
+    }
 
-impl<'a> Ast2Hir<'a> {
-    /// Lowers the AST into HIR, adding simple type information and writing errors
-    /// to `issues`.
-    pub fn lower(
-        issues : &'a mut IssueManager,
-        ast_node : &ast::Node
-    ) -> hir::Module {
-        let mut ctx = Self { issues };
-        ctx.lower_module(ast_node)
+    fn type_contains(
+        &self,
+        ty : &hir::Type,
+        target : &str,
+        visited : &mut Vec<hir::Symbol>,
+    ) -> bool {
+        match ty {
+            hir::Type::Array { elem, .. } => self.type_contains(elem, target, visited),
+            hir::Type::Enum(name) => {
+                if name == target {
+                    return true;
+                }
+                if visited.contains(name) {
+                    return false;
+                }
+                visited.push(name.clone());
+                let Some(variants) = self.enums.get(name) else { return false };
+                variants.iter()
+                    .flat_map(|variant| &variant.fields)
+                    .any(|field| self.type_contains(field, target, visited))
+            },
+            _ => false,
+        }
+    }
+
+    fn lower_decl(&mut self, ast_node : &ast::Node) -> Option<hir::Decl> {
+        match ast_node {
+            ast::Node::Attrs { attrs, node } => {
+                let target = match node.as_ref() {
+                    ast::Node::Type { .. } => hir::AttrTarget::Type,
+                    ast::Node::Const { .. } => hir::AttrTarget::Const,
+                    ast::Node::ExternFn { .. } => hir::AttrTarget::ExternFn,
+                    _ => hir::AttrTarget::Fn,
+                };
+                let attrs = self.lower_attrs(attrs, target, node);
+                self.issues.push_lint_scope(hir::lint_attrs(&attrs));
+                let decl = self.lower_decl(node);
+                self.issues.pop_lint_scope();
+                let mut decl = decl?;
+                match &mut decl {
+                    hir::Decl::Fn { name, attrs : fn_attrs, params, ret, .. } => {
+                        fn_attrs.extend(attrs);
+                        if hir::has_attr(fn_attrs, hir::AttrKind::Test) {
+                            self.check_test_fn(name, params, ret)?;
+                        }
+                    },
+                    // no attributes can currently be applied to types,
+                    // constants, or extern functions
+                    hir::Decl::Enum { .. }
+                    | hir::Decl::Const { .. }
+                    | hir::Decl::ExternFn { .. } => (),
+                }
+                Some(decl)
+            },
+            ast::Node::Fn { name, params, ret, body } => {
+                let def = match self.fns.iter().rev().find(|def| def.loc == name.loc) {
+                    Some(def) => def.clone(),
+                    None => self.declare_fn(ast_node)?,
+                };
+                // functions cannot see the locals of their enclosing scope
+                let outer_locals = std::mem::take(&mut self.locals);
+                let outer_next_local = std::mem::replace(&mut self.next_local, 0);
+                let mut hir_params : Vec<hir::Param> = Vec::new();
+                for (param, ty) in params.value.iter().zip(&def.params) {
+                    if let Some(prev) = hir_params.iter()
+                        .find(|prev| prev.name.value == param.name.value)
+                    {
+                        Diagnostic::error()
+                            .code(ErrorCode::E0022)
+                            .message(msg!("hir-duplicate-param", [
+                                param.name.value.clone().into()
+                            ]))
+                            .label(param.name.loc)
+                            .label_other((prev.name.loc, msg!("hir-previously-defined")))
+                            .report(self.issues);
+                    }
+                    let id = self.next_local;
+                    self.next_local += 1;
+                    self.locals.push(LocalDef {
+                        name : param.name.value.clone(),
+                        id,
+                        ty : Some(ty.clone()),
+                        kind : LocalKind::Param,
+                        loc : param.name.loc,
+                    });
+                    hir_params.push(hir::Param { name : param.name.clone(), id, ty : ty.clone() });
+                }
+                let ret_loc = ret.as_ref().map(|ret| type_location(ret));
+                let outer_loops = std::mem::take(&mut self.loops);
+                let outer_fn = self.current_fn.replace(FnCtx {
+                    path : def.path.clone(),
+                    ret : def.ret.clone(),
+                    ret_loc,
+                    params_loc : params.loc,
+                    first_return : None,
+                });
+                let body = self.lower_expr_expecting(body, def.ret.as_ref());
+                let ctx = std::mem::replace(&mut self.current_fn, outer_fn)?;
+                self.loops = outer_loops;
+                self.locals = outer_locals;
+                self.next_local = outer_next_local;
+                let body = body?;
+                definite::check_fn(self.issues, &body);
+                let ret = if let Some(ret) = &ctx.ret {
+                    if !body.ty.is_compatible(ret) {
+                        let reason = match (ctx.ret_loc, ctx.first_return) {
+                            (Some(ret_loc), _) => Some((ret_loc, msg!("hir-expected-by-return-type"))),
+                            (None, Some(return_loc)) => Some((return_loc, msg!("hir-return-type-inferred"))),
+                            (None, None) => None,
+                        };
+                        let mut diagnostic = Diagnostic::error()
+                            .code(ErrorCode::E0023)
+                            .message(msg!("hir-mismatched-return", [
+                                body.ty.to_string().into()
+                            ]))
+                            .label((value_loc(&body), msg!("hir-expected-type", [
+                                ret.to_string().into()
+                            ])));
+                        if let Some((loc, reason)) = reason {
+                            diagnostic = diagnostic.label_other((loc, reason));
+                        }
+                        diagnostic.report(self.issues);
+                    }
+                    ret.clone()
+                } else {
+                    body.ty.clone()
+                };
+                if def.ret.is_none() {
+                    // the return type is inferred from the body
+                    if let Some(def) = self.fns.iter_mut().rev().find(|def| def.loc == name.loc) {
+                        def.ret = Some(ret.clone());
+                    }
+                }
+                Some(hir::Decl::Fn {
+                    name : name.clone(),
+                    attrs : Vec::new(),
+                    params : hir_params,
+                    ret,
+                    body : Box::new(body),
+                })
+            },
+            ast::Node::ExternFn { name, params, ret : ret_node } => {
+                let def = match self.fns.iter().rev().find(|def| def.loc == name.loc) {
+                    Some(def) => def.clone(),
+                    None => self.declare_fn(ast_node)?,
+                };
+                let mut valid = true;
+                for (param, ty) in params.value.iter().zip(&def.params) {
+                    if matches!(ty, hir::Type::Unit) || ty.c_type().is_none() {
+                        self.report_no_c_type(ty, param.ty.primary_location());
+                        valid = false;
+                    }
+                }
+                let ret = def.ret.unwrap_or(hir::Type::Unit);
+                let ret_loc = ret_node.as_ref().map_or(name.loc, |node| type_location(node));
+                if ret.c_type().is_none() {
+                    self.report_no_c_type(&ret, ret_loc);
+                    valid = false;
+                } else if let hir::Type::Str = ret {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0024)
+                        .message(msg!("hir-extern-returns-str"))
+                        .label(ret_loc)
+                        .note(msg!("hir-extern-returns-str-note"))
+                        .report(self.issues);
+                    valid = false;
+                }
+                if !valid {
+                    return None;
+                }
+                Some(hir::Decl::ExternFn { name : name.clone(), params : def.params, ret })
+            },
+            ast::Node::Type { name, def } => {
+                if BUILTIN_TYPES.contains(&name.value.as_str()) {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0025)
+                        .message(msg!("hir-redefine-builtin-type", [
+                            name.value.clone().into()
+                        ]))
+                        .label(name.loc)
+                        .report(self.issues);
+                    return None;
+                }
+                let ast::Node::Enum(variants) = def.as_ref() else {
+                    self.assert(def, msg!("hir-ast-expected-enum"))?;
+                    return None;
+                };
+                let variants = self.lower_variants(&variants.value);
+                Some(hir::Decl::Enum { name : name.clone(), variants })
+            },
+            ast::Node::Const { name, ty : ty_node, value } => {
+                let ty = match self.const_annotations.get(&name.loc) {
+                    Some(ty) => Some(ty.clone()),
+                    None => ty_node.as_ref()
+                        .map(|node| self.lower_type(node).unwrap_or(hir::Type::Unknown)),
+                };
+                let init = self.lower_expr_expecting(value, ty.as_ref());
+                // later uses of the constant don't report errors if it fails
+                // to lower
+                let const_ty = match (&ty, &init) {
+                    (Some(ty), _) => ty.clone(),
+                    (None, Some(init)) => init.ty.clone(),
+                    (None, None) => hir::Type::Unknown,
+                };
+                self.consts.insert(name.value.clone(), const_ty.clone());
+                let init = init?;
+                if let (Some(ty), Some(ty_node)) = (&ty, ty_node)
+                        && !init.ty.is_compatible(ty) {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0019)
+                        .message(msg!("hir-mismatched-types", [
+                            init.ty.to_string().into()
+                        ]))
+                        .label((value_loc(&init), msg!("hir-expected-type", [
+                            ty.to_string().into()
+                        ])))
+                        .label_other((ty_node.primary_location(), msg!("hir-expected-by-type")))
+                        .report(self.issues);
+                    return None;
+                }
+                if const_ty == hir::Type::Unknown {
+                    // an error has already been reported
+                    return None;
+                }
+                if !(const_ty.is_numeric() || matches!(const_ty, hir::Type::Bool | hir::Type::Unit)) {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0026)
+                        .message(msg!("hir-unsupported-const-type", [
+                            const_ty.to_string().into()
+                        ]))
+                        .label(name.loc)
+                        .note(msg!("hir-unsupported-const-type-note"))
+                        .report(self.issues);
+                    return None;
+                }
+                definite::check_fn(self.issues, &init);
+                Some(hir::Decl::Const {
+                    name : name.clone(),
+                    ty : const_ty,
+                    init : Box::new(init),
+                    value : None,
+                })
+            },
+            _ => {
+                self.assert(ast_node, msg!("hir-ast-expected-decl"))?;
+                None
+            },
+        }
+    }
+
+    /// Resolves the types of any constants in `nodes` which have a type
+    /// annotation, so they can be used before they are defined.
+    fn declare_consts<'n>(&mut self, nodes : impl Iterator<Item = &'n ast::Node>) {
+        for node in nodes {
+            let ast::Node::Const { name, ty : Some(ty_node), .. } = without_attrs(node) else {
+                continue;
+            };
+            let ty = self.lower_type(ty_node).unwrap_or(hir::Type::Unknown);
+            self.const_annotations.insert(name.loc, ty.clone());
+            self.consts.entry(name.value.clone()).or_insert(ty);
+        }
+    }
+
+    /// Adds the signatures of any functions in `nodes` to the current scope,
+    /// so they can be called before they are defined.
+    fn declare_fns<'n>(&mut self, nodes : impl Iterator<Item = &'n ast::Node>) {
+        for node in nodes {
+            let node = without_attrs(node);
+            if let ast::Node::Fn { .. } | ast::Node::ExternFn { .. } = node {
+                self.declare_fn(node);
+            }
+        }
+    }
+
+    fn declare_fn(&mut self, ast_node : &ast::Node) -> Option<FnDef> {
+        let (ast::Node::Fn { name, params, ret, .. }
+                | ast::Node::ExternFn { name, params, ret }) = ast_node else {
+            self.assert(ast_node, msg!("hir-ast-expected-fn"))?;
+            return None;
+        };
+        let params = params.value.iter()
+            .map(|param| self.lower_type(&param.ty).unwrap_or(hir::Type::Unknown))
+            .collect();
+        let mut ret = ret.as_ref()
+            .map(|ret| self.lower_type(ret).unwrap_or(hir::Type::Unknown));
+        if let ast::Node::ExternFn { .. } = ast_node {
+            // extern functions have no body to infer their return type from
+            ret.get_or_insert(hir::Type::Unit);
+        }
+        let path = if let Some(parent) = &self.current_fn {
+            format!("{}::{}", parent.path, name.value)
+        } else {
+            name.value.clone()
+        };
+        let def = FnDef { name : name.value.clone(), path, params, ret, loc : name.loc };
+        self.fns.push(def.clone());
+        Some(def)
+    }
+
+    fn lower_stmt(
+        &mut self,
+        ast_node : &ast::Node,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Stmt> {
+        match ast_node {
+            ast::Node::Attrs { attrs, node } => match node.as_ref() {
+                ast::Node::Fn { .. } => Some(hir::Stmt::Decl(self.lower_decl(ast_node)?)),
+                ast::Node::Type { .. }
+                | ast::Node::ExternFn { .. } => self.lower_stmt(node, None),
+                ast::Node::Local { .. } => {
+                    // no attributes can currently be applied to locals, but
+                    // they are still checked for errors
+                    self.lower_attrs(attrs, hir::AttrTarget::Local, node);
+                    self.lower_stmt(node, None)
+                },
+                _ => Some(hir::Stmt::Expr(self.lower_expr_expecting(ast_node, expected)?)),
+            },
+            ast::Node::Fn { .. } => Some(hir::Stmt::Decl(self.lower_decl(ast_node)?)),
+            ast::Node::Type { name, .. } => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0027)
+                    .message(msg!("hir-nested-type-decl"))
+                    .label(name.loc)
+                    .report(self.issues);
+                None
+            },
+            ast::Node::ExternFn { name, .. } => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0027)
+                    .message(msg!("hir-nested-extern-fn"))
+                    .label(name.loc)
+                    .report(self.issues);
+                None
+            },
+            ast::Node::Local { name, mutable, ty : ty_node, init, loc } => {
+                let ty = ty_node.as_ref()
+                    .map(|node| self.lower_type(node).unwrap_or(hir::Type::Unknown));
+                // lower the initialiser first, so it can't refer to this local
+                let init = init.as_ref()
+                    .map(|node| self.lower_expr_expecting(node, ty.as_ref()));
+                if let (Some(ty), Some(ty_node), Some(Some(init))) = (&ty, ty_node, &init)
+                        && !init.ty.is_compatible(ty) {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0019)
+                        .message(msg!("hir-mismatched-types", [
+                            init.ty.to_string().into()
+                        ]))
+                        .label((value_loc(init), msg!("hir-expected-type", [
+                            ty.to_string().into()
+                        ])))
+                        .label_other((ty_node.primary_location(), msg!("hir-expected-by-type")))
+                        .report(self.issues);
+                }
+                let id = self.next_local;
+                self.next_local += 1;
+                // if there is no type or initial value, the type is inferred
+                // from the first assignment to the local
+                let ty = match (ty, &init) {
+                    (Some(ty), _) => Some(ty),
+                    (None, Some(Some(expr))) => Some(expr.ty.clone()),
+                    (None, Some(None)) => Some(hir::Type::Unknown),
+                    (None, None) => None,
+                };
+                self.locals.push(LocalDef {
+                    name : name.value.clone(),
+                    id,
+                    ty : ty.clone(),
+                    kind : LocalKind::Local { mutable : *mutable, deferred : init.is_none() },
+                    loc : name.loc,
+                });
+                // the local is still declared if its initial value has an
+                // error, so later uses of it don't report more errors
+                let init = match init {
+                    Some(None) => return None,
+                    init => init.flatten(),
+                };
+                Some(hir::Stmt::Local {
+                    name : name.clone(),
+                    id,
+                    mutable : *mutable,
+                    ty : ty.unwrap_or(hir::Type::Unknown),
+                    init,
+                    loc : *loc,
+                })
+            },
+            _ => Some(hir::Stmt::Expr(self.lower_expr_expecting(ast_node, expected)?)),
+        }
+    }
+
+    fn lower_expr(&mut self, ast_node : &ast::Node) -> Option<hir::Expr> {
+        self.lower_expr_expecting(ast_node, None)
+    }
+
+    /// Lowers an expression, using the type `expected` to infer the types of
+    /// expressions which can't be inferred on their own, such as `Result`
+    /// variants.
+    ///
+    /// Note: this doesn't check that the expression has the expected type.
+    fn lower_expr_expecting(
+        &mut self,
+        ast_node : &ast::Node,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Expr> {
+        let expr = match ast_node {
+            ast::Node::NumIntegral { value, suffix } => {
+                self.lower_num_integral(value, suffix, expected, false)?
+            },
+            ast::Node::NumRational { value, suffix } => {
+                self.lower_num_rational(value, suffix, expected)?
+            },
+            ast::Node::Str(s) => hir::Expr::new(
+                hir::ExprKind::Str(s.value.clone()),
+                hir::Type::Str,
+                s.loc,
+            ),
+            ast::Node::Bool(b) => hir::Expr::new(
+                hir::ExprKind::Bool(b.value),
+                hir::Type::Bool,
+                b.loc,
+            ),
+            ast::Node::Id(sym) => {
+                let Some(local) = self.locals.iter()
+                    .rev()
+                    .find(|local| local.name == sym.value)
+                else {
+                    if let Some(ty) = self.consts.get(&sym.value) {
+                        return Some(hir::Expr::new(
+                            hir::ExprKind::Const(sym.value.clone()),
+                            ty.clone(),
+                            sym.loc,
+                        ));
+                    }
+                    if self.const_names.contains(&sym.value) {
+                        Diagnostic::error()
+                            .code(ErrorCode::E0028)
+                            .message(msg!("hir-const-before-definition", [
+                                sym.value.clone().into()
+                            ]))
+                            .label(sym.loc)
+                            .note(msg!("hir-const-before-definition-help", [
+                                sym.value.clone().into()
+                            ]))
+                            .report(self.issues);
+                        return None;
+                    }
+                    Diagnostic::error()
+                        .code(ErrorCode::E0029)
+                        .message(msg!("hir-unknown-variable", [
+                            sym.value.clone().into()
+                        ]))
+                        .label(sym.loc)
+                        .report(self.issues);
+                    return None;
+                };
+                hir::Expr::new(
+                    hir::ExprKind::Local(local.id),
+                    local.ty.clone().unwrap_or(hir::Type::Unknown),
+                    sym.loc,
+                )
+            },
+            ast::Node::Block(blk) => self.lower_block(&blk.value, blk.loc, expected),
+            ast::Node::Parens(node) => self.lower_expr_expecting(&node.value, expected)?,
+            ast::Node::Attrs { attrs, node } => {
+                let attrs = self.lower_attrs(attrs, hir::AttrTarget::Expr, node);
+                let mut expr = self.lower_expr(node)?;
+                if let Some(attr) = attrs.iter().find(|attr| attr.kind == hir::AttrKind::Try) {
+                    expr = self.lower_try(expr, attr.loc)?;
+                }
+                expr.attrs.extend(attrs);
+                expr
+            },
+            ast::Node::Array(elems) => self.lower_array(&elems.value, elems.loc, expected)?,
+            ast::Node::Index { target, index } => {
+                let target = self.lower_expr(target);
+                let index_expr = self.lower_expr(&index.value);
+                let (target, index_expr) = (target?, index_expr?);
+                self.assert_type(&index_expr, &hir::Type::Int,
+                        msg!("hir-array-index-not-int"))?;
+                let ty = match &target.ty {
+                    hir::Type::Array { elem, len } => {
+                        // indices are `int`, so negative literals are folded
+                        // into their two's complement
+                        if let hir::ExprKind::NumIntegral(n) = index_expr.kind
+                                && !(0..i128::from(*len)).contains(&(n as i128)) {
+                            Diagnostic::error()
+                                .code(ErrorCode::E0030)
+                                .message(msg!("hir-index-out-of-bounds", [
+                                    len.into(), (n as i128).into()
+                                ]))
+                                .label(index.loc)
+                                .label_other((target.loc, msg!("hir-array-has-type", [
+                                    target.ty.to_string().into()
+                                ])))
+                                .report(self.issues);
+                            return None;
+                        }
+                        elem.as_ref().clone()
+                    },
+                    hir::Type::Unknown => hir::Type::Unknown,
+                    ty => {
+                        Diagnostic::error()
+                            .code(ErrorCode::E0031)
+                            .message(msg!("hir-cannot-index", [
+                                ty.to_string().into()
+                            ]))
+                            .label(index.loc)
+                            .label_other((target.loc, msg!("hir-expected-array")))
+                            .report(self.issues);
+                        return None;
+                    },
+                };
+                hir::Expr::new(
+                    hir::ExprKind::Index {
+                        target : Box::new(target),
+                        index : Box::new(index_expr),
+                    },
+                    ty,
+                    index.loc,
+                )
+            },
+            ast::Node::Path { ty, name } => {
+                let loc = ast_node.primary_location();
+                self.lower_variant_expr(ty, name, &[], loc, expected)?
+            },
+            ast::Node::Call { callee, args } => match callee.as_ref() {
+                ast::Node::Path { ty, name } => {
+                    self.lower_variant_expr(ty, name, &args.value, args.loc, expected)?
+                },
+                ast::Node::Id(name) => {
+                    let is_fn = self.fns.iter().any(|def| def.name == name.value);
+                    match hir::Type::from_num_name(&name.value) {
+                        Some(ty) if !is_fn => self.lower_convert(ty, name, &args.value, args.loc)?,
+                        _ if !is_fn && name.value == "assert" => {
+                            self.lower_assert(name, &args.value, args.loc)?
+                        },
+                        _ => self.lower_call(name, &args.value, args.loc)?,
+                    }
+                },
+                _ => {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0032)
+                        .message(msg!("hir-not-callable"))
+                        .label(callee.primary_location())
+                        .report(self.issues);
+                    return None;
+                },
+            },
+            ast::Node::Match { scrutinee, arms } => {
+                self.lower_match(scrutinee, &arms.value, arms.loc, expected)?
+            },
+            ast::Node::If { cond, then_branch, else_branch } => {
+                let cond_expr = self.lower_condition(&cond.value);
+                // the value of the `then` branch is only used if there is an
+                // `else` branch
+                let then_expected = if else_branch.is_some() { expected } else { None };
+                let then_expr = self.lower_expr_expecting(then_branch, then_expected);
+                let else_expr = else_branch.as_ref().map(|node| {
+                    let else_expected = expected.cloned()
+                        .or_else(|| then_expr.as_ref().map(|expr| expr.ty.clone()));
+                    self.lower_expr_expecting(node, else_expected.as_ref())
+                });
+                let (cond_expr, then_expr) = (cond_expr?, then_expr?);
+                let (else_expr, ty) = match else_expr {
+                    Some(else_expr) => {
+                        let else_expr = else_expr?;
+                        let ty = &then_expr.ty;
+                        if !else_expr.ty.is_compatible(ty) && !ty.is_compatible(&else_expr.ty) {
+                            Diagnostic::error()
+                                .code(ErrorCode::E0033)
+                                .message(msg!("hir-incompatible-if"))
+                                .label((value_loc(&else_expr), msg!("hir-expected-got", [
+                                    ty.to_string().into(), else_expr.ty.to_string().into()
+                                ])))
+                                .label_other((value_loc(&then_expr), msg!("hir-then-has-type", [
+                                    ty.to_string().into()
+                                ])))
+                                .report(self.issues);
+                            return None;
+                        }
+                        let ty = ty.join(&else_expr.ty);
+                        (Some(Box::new(else_expr)), ty)
+                    },
+                    None => (None, hir::Type::Unit),
+                };
+                hir::Expr::new(
+                    hir::ExprKind::If {
+                        cond : Box::new(cond_expr),
+                        then_branch : Box::new(then_expr),
+                        else_branch : else_expr,
+                    },
+                    ty,
+                    cond.loc,
+                )
+            },
+            ast::Node::While { cond, body } => {
+                let cond_expr = self.lower_condition(&cond.value);
+                self.loops.push(LoopCtx {
+                    loc : cond.loc,
+                    produces_value : false,
+                    break_ty : None,
+                    break_loc : None,
+                    expected : None,
+                });
+                let body = self.lower_expr(body);
+                self.loops.pop();
+                hir::Expr::new(
+                    hir::ExprKind::While {
+                        cond : Box::new(cond_expr?),
+                        body : Box::new(body?),
+                    },
+                    hir::Type::Unit,
+                    cond.loc,
+                )
+            },
+            ast::Node::Binary { op, lhs, rhs } => {
+                // the operands of arithmetic operators have the same type as
+                // the result, and the operands of comparisons have the same
+                // type as each other
+                let lhs_expected = match op.value {
+                    ast::BinaryOp::Add
+                    | ast::BinaryOp::Sub
+                    | ast::BinaryOp::Mul
+                    | ast::BinaryOp::Div
+                    | ast::BinaryOp::Rem => expected,
+                    _ => None,
+                };
+                let (lhs, rhs) = if is_untyped_literal(lhs) && !is_untyped_literal(rhs) {
+                    // infer the type of literals from the other operand, e.g.
+                    // `1 + x`
+                    let rhs = self.lower_expr_expecting(rhs, lhs_expected);
+                    let lhs = self.lower_expr_expecting(lhs, rhs.as_ref().map(|rhs| &rhs.ty));
+                    (lhs, rhs)
+                } else {
+                    let lhs = self.lower_expr_expecting(lhs, lhs_expected);
+                    let rhs = self.lower_expr_expecting(rhs, lhs.as_ref().map(|lhs| &lhs.ty));
+                    (lhs, rhs)
+                };
+                let (lhs, rhs) = (lhs?, rhs?);
+                let ty = self.binary_type(op, &lhs, &rhs)?;
+                hir::Expr::new(
+                    hir::ExprKind::Binary {
+                        op : op.value,
+                        lhs : Box::new(lhs),
+                        rhs : Box::new(rhs),
+                    },
+                    ty,
+                    op.loc,
+                )
+            },
+            ast::Node::Unary { op, value } => {
+                let value = match (op.value, value.as_ref()) {
+                    // negative literals are range checked as a whole
+                    (ast::UnaryOp::Neg, ast::Node::NumIntegral { value, suffix }) => {
+                        let value = self.lower_num_integral(value, suffix, expected, true)?;
+                        if let hir::ExprKind::NumIntegral(n) = value.kind && value.ty.is_signed() {
+                            // folded into a single literal, since negating the
+                            // minimum value would overflow
+                            return Some(hir::Expr::new(
+                                hir::ExprKind::NumIntegral(n.wrapping_neg()),
+                                value.ty,
+                                op.loc.join(&value.loc),
+                            ));
+                        }
+                        value
+                    },
+                    _ => self.lower_expr_expecting(value, expected)?,
+                };
+                let valid = match op.value {
+                    ast::UnaryOp::Neg => value.ty.is_signed() || matches!(value.ty, hir::Type::Never),
+                    ast::UnaryOp::Not => matches!(value.ty, hir::Type::Bool | hir::Type::Never),
+                };
+                if !valid && !value.ty.is_unknown() {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0034)
+                        .message(msg!("hir-invalid-unary", [
+                            op.value.as_str().into(), value.ty.to_string().into()
+                        ]))
+                        .label(op.loc)
+                        .label_other((value.loc, msg!("hir-has-type", [
+                            value.ty.to_string().into()
+                        ])))
+                        .report(self.issues);
+                    return None;
+                }
+                let ty = value.ty.clone();
+                hir::Expr::new(
+                    hir::ExprKind::Unary { op : op.value, value : Box::new(value) },
+                    ty,
+                    op.loc,
+                )
+            },
+            ast::Node::Assign { target, value } => {
+                self.lower_assign(target, &value.value, value.loc)?
+            },
+            ast::Node::Return(value) => self.lower_return(value)?,
+            ast::Node::Loop(body) => {
+                self.loops.push(LoopCtx {
+                    loc : body.loc,
+                    produces_value : true,
+                    break_ty : None,
+                    break_loc : None,
+                    expected : expected.cloned(),
+                });
+                let body_expr = self.lower_expr(&body.value);
+                let ctx = self.loops.pop()?;
+                // loops without a `break` never finish
+                let ty = ctx.break_ty.unwrap_or(hir::Type::Never);
+                hir::Expr::new(hir::ExprKind::Loop(Box::new(body_expr?)), ty, body.loc)
+            },
+            ast::Node::Break(value) => self.lower_break(value)?,
+            ast::Node::Continue(loc) => {
+                if self.loops.is_empty() {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0035)
+                        .message(msg!("hir-continue-outside-loop"))
+                        .label(*loc)
+                        .note(msg!("hir-continue-outside-loop-note"))
+                        .report(self.issues);
+                    return None;
+                }
+                hir::Expr::new(hir::ExprKind::Continue, hir::Type::Never, *loc)
+            },
+            _ => {
+                self.assert(ast_node, msg!("hir-ast-expected-expr"))?;
+                return None;
+            },
+        };
+        Some(expr)
+    }
+
+    /// Lowers a `return` expression, inferring the return type of the
+    /// enclosing function if it doesn't specify one.
+    fn lower_return(
+        &mut self,
+        value : &Located<Option<Box<ast::Node>>>,
+    ) -> Option<hir::Expr> {
+        let (value, loc) = (&value.value, value.loc);
+        let Some(ctx) = &self.current_fn else {
+            Diagnostic::error()
+                .code(ErrorCode::E0036)
+                .message(msg!("hir-return-outside-fn"))
+                .label(loc)
+                .report(self.issues);
+            return None;
+        };
+        let expected = ctx.ret.clone();
+        let value = match value {
+            Some(node) => Some(self.lower_expr_expecting(node, expected.as_ref())?),
+            None => None,
+        };
+        let value_ty = value.as_ref().map_or(hir::Type::Unit, |value| value.ty.clone());
+        let ctx = self.current_fn.as_mut()?;
+        match &ctx.ret {
+            Some(ret) if !value_ty.is_compatible(ret) => {
+                let mut diagnostic = Diagnostic::error()
+                    .code(ErrorCode::E0023)
+                    .message(msg!("hir-mismatched-return", [
+                        value_ty.to_string().into()
+                    ]))
+                    .label((value.as_ref().map_or(loc, value_loc), msg!("hir-expected-type", [
+                        ret.to_string().into()
+                    ])));
+                if let Some(ret_loc) = ctx.ret_loc {
+                    diagnostic = diagnostic
+                        .label_other((ret_loc, msg!("hir-expected-by-return-type")));
+                } else if let Some(return_loc) = ctx.first_return {
+                    diagnostic = diagnostic
+                        .label_other((return_loc, msg!("hir-return-type-inferred")));
+                }
+                diagnostic.report(self.issues);
+                return None;
+            },
+            Some(_) => (),
+            None => if !matches!(value_ty, hir::Type::Never) {
+                ctx.ret = Some(value_ty);
+            },
+        }
+        ctx.first_return.get_or_insert(loc);
+        Some(hir::Expr::new(
+            hir::ExprKind::Return(value.map(Box::new)),
+            hir::Type::Never,
+            loc,
+        ))
+    }
+
+    /// Lowers a `break` expression, inferring the type of the enclosing loop
+    /// from its value.
+    fn lower_break(
+        &mut self,
+        value : &Located<Option<Box<ast::Node>>>,
+    ) -> Option<hir::Expr> {
+        let (value, loc) = (&value.value, value.loc);
+        let Some(ctx) = self.loops.last() else {
+            Diagnostic::error()
+                .code(ErrorCode::E0035)
+                .message(msg!("hir-break-outside-loop"))
+                .label(loc)
+                .note(msg!("hir-break-outside-loop-note"))
+                .report(self.issues);
+            return None;
+        };
+        if let (false, Some(node)) = (ctx.produces_value, value) {
+            Diagnostic::error()
+                .code(ErrorCode::E0037)
+                .message(msg!("hir-break-value-in-while"))
+                .label(node.primary_location())
+                .label_other((ctx.loc, msg!("hir-break-value-in-while-label")))
+                .note(msg!("hir-break-value-in-while-help"))
+                .report(self.issues);
+            return None;
+        }
+        let expected = ctx.break_ty.clone().or_else(|| ctx.expected.clone());
+        let value = match value {
+            Some(node) => Some(self.lower_expr_expecting(node, expected.as_ref())?),
+            None => None,
+        };
+        let value_ty = value.as_ref().map_or(hir::Type::Unit, |value| value.ty.clone());
+        let ctx = self.loops.last_mut()?;
+        match (&ctx.break_ty, ctx.break_loc) {
+            (Some(break_ty), Some(break_loc)) if ctx.produces_value
+                    && !value_ty.is_compatible(break_ty) => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0019)
+                    .message(msg!("hir-mismatched-types", [
+                        value_ty.to_string().into()
+                    ]))
+                    .label((value.as_ref().map_or(loc, value_loc), msg!("hir-expected-type", [
+                        break_ty.to_string().into()
+                    ])))
+                    .label_other((break_loc, msg!("hir-expected-by-break")))
+                    .report(self.issues);
+                return None;
+            },
+            (None, _) if !matches!(value_ty, hir::Type::Never) => {
+                ctx.break_ty = Some(value_ty);
+                ctx.break_loc = Some(loc);
+            },
+            _ => (),
+        }
+        Some(hir::Expr::new(
+            hir::ExprKind::Break(value.map(Box::new)),
+            hir::Type::Never,
+            loc,
+        ))
+    }
+
+    /// Lowers the condition of an `if` or `while` expression, which must be
+    /// a `bool`.
+    fn lower_condition(&mut self, ast_node : &ast::Node) -> Option<hir::Expr> {
+        let cond = self.lower_expr(ast_node)?;
+        self.assert_type(&cond, &hir::Type::Bool, msg!("hir-condition-not-bool"))?;
+        Some(cond)
+    }
+
+    /// Returns the type of the result of a binary operator, or reports an
+    /// error if the operator can't be applied to its operands.
+    fn binary_type(
+        &mut self,
+        op : &Located<ast::BinaryOp>,
+        lhs : &hir::Expr,
+        rhs : &hir::Expr,
+    ) -> Option<hir::Type> {
+        use ast::BinaryOp as Op;
+        let (valid, note) = match op.value {
+            Op::Add => (
+                lhs.ty.is_numeric() || matches!(lhs.ty, hir::Type::Str),
+                msg!("hir-add-operands-note"),
+            ),
+            Op::Sub | Op::Mul | Op::Div | Op::Rem
+            | Op::Lt | Op::Le | Op::Gt | Op::Ge => (
+                lhs.ty.is_numeric(),
+                msg!("hir-numeric-operands-note"),
+            ),
+            Op::Eq | Op::Ne => (
+                lhs.ty.is_numeric() || matches!(lhs.ty, hir::Type::Bool),
+                msg!("hir-eq-operands-note"),
+            ),
+            Op::And | Op::Or => (
+                matches!(lhs.ty, hir::Type::Bool),
+                msg!("hir-bool-operands-note"),
+            ),
+        };
+        let arithmetic = matches!(op.value, Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Rem);
+        if lhs.ty.is_unknown() || rhs.ty.is_unknown() {
+            return Some(if arithmetic { hir::Type::Unknown } else { hir::Type::Bool });
+        }
+        if let hir::Type::Never = lhs.ty {
+            return Some(if arithmetic { hir::Type::Never } else { hir::Type::Bool });
+        }
+        if let hir::Type::Never = rhs.ty {
+            return Some(if arithmetic { lhs.ty.clone() } else { hir::Type::Bool });
+        }
+        if !valid || lhs.ty != rhs.ty {
+            Diagnostic::error()
+                .code(ErrorCode::E0038)
+                .message(msg!("hir-invalid-binary", [
+                    op.value.as_str().into(), lhs.ty.to_string().into(), rhs.ty.to_string().into()
+                ]))
+                .label(op.loc)
+                .label_other((lhs.loc, msg!("hir-has-type", [
+                    lhs.ty.to_string().into()
+                ])))
+                .label_other((rhs.loc, msg!("hir-has-type", [
+                    rhs.ty.to_string().into()
+                ])))
+                .note(note)
+                .report(self.issues);
+            return None;
+        }
+        Some(if arithmetic { lhs.ty.clone() } else { hir::Type::Bool })
+    }
+
+    /// Lowers an assignment to a local variable, or to an element of an
+    /// array stored in a local variable.
+    fn lower_assign(
+        &mut self,
+        target : &ast::Node,
+        value : &ast::Node,
+        loc : Location,
+    ) -> Option<hir::Expr> {
+        let mut target = self.lower_expr(target)?;
+        let mut root = &target;
+        while let hir::ExprKind::Index { target, .. } = &root.kind {
+            root = target;
+        }
+        let hir::ExprKind::Local(id) = root.kind else {
+            Diagnostic::error()
+                .code(ErrorCode::E0039)
+                .message(msg!("hir-invalid-assign-target"))
+                .label(target.loc)
+                .label_other(loc)
+                .note(msg!("hir-invalid-assign-target-note"))
+                .report(self.issues);
+            return None;
+        };
+        let root_loc = root.loc;
+        let whole = matches!(target.kind, hir::ExprKind::Local(..));
+        let local = self.locals.iter().rev().find(|local| local.id == id)?;
+        let assignable = match local.kind {
+            LocalKind::Local { mutable, deferred } => mutable || (deferred && whole),
+            LocalKind::Param | LocalKind::Binding => false,
+        };
+        if !assignable {
+            let mut diagnostic = Diagnostic::error()
+                .code(ErrorCode::E0040)
+                .message(msg!("hir-assign-immutable", [
+                    local.kind.description().into(), local.name.clone().into()
+                ]))
+                .label(root_loc)
+                .label_other((local.loc, msg!("hir-declared-here")));
+            diagnostic = match local.kind {
+                LocalKind::Local { .. } => diagnostic
+                    .suggest(
+                        local.loc.start(),
+                        "mut ",
+                        Applicability::MachineApplicable,
+                        msg!("hir-make-mutable-help"),
+                    ),
+                _ => diagnostic
+                    .note(msg!("hir-copy-to-mutable-help", [
+                        local.name.clone().into(), local.name.clone().into()
+                    ])),
+            };
+            diagnostic.report(self.issues);
+            return None;
+        }
+        let inferred = local.ty.is_none();
+        let expected = if inferred { None } else { Some(target.ty.clone()) };
+        let value = self.lower_expr_expecting(value, expected.as_ref());
+        if inferred {
+            // the type of a local without a type or initial value is
+            // inferred from its first assignment
+            let ty = value.as_ref().map_or(hir::Type::Unknown, |value| value.ty.clone());
+            if let Some(local) = self.locals.iter_mut().rev().find(|local| local.id == id) {
+                local.ty = Some(ty.clone());
+            }
+            target.ty = ty;
+        }
+        let value = value?;
+        if !value.ty.is_compatible(&target.ty) {
+            Diagnostic::error()
+                .code(ErrorCode::E0019)
+                .message(msg!("hir-mismatched-types", [
+                    value.ty.to_string().into()
+                ]))
+                .label((value_loc(&value), msg!("hir-expected-type", [
+                    target.ty.to_string().into()
+                ])))
+                .label_other((target.loc, msg!("hir-has-type", [
+                    target.ty.to_string().into()
+                ])))
+                .report(self.issues);
+            return None;
+        }
+        Some(hir::Expr::new(
+            hir::ExprKind::Assign {
+                target : Box::new(target),
+                value : Box::new(value),
+            },
+            hir::Type::Unit,
+            loc,
+        ))
+    }
+
+    /// Resolves the name of a type.
+    fn lower_type(&mut self, ast_node : &ast::Node) -> Option<hir::Type> {
+        match ast_node {
+            ast::Node::Id(name) => match name.value.as_str() {
+                "bool" => Some(hir::Type::Bool),
+                "str" => Some(hir::Type::Str),
+                num if hir::Type::from_num_name(num).is_some() => {
+                    hir::Type::from_num_name(num)
+                },
+                "Result" => {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0041)
+                        .message(msg!("hir-result-missing-args"))
+                        .label(name.loc)
+                        .note(msg!("hir-result-args-note"))
+                        .report(self.issues);
+                    None
+                },
+                _ if self.type_names.contains(&name.value) => {
+                    Some(hir::Type::Enum(name.value.clone()))
+                },
+                _ => {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0042)
+                        .message(msg!("hir-unknown-type", [name.value.clone().into()]))
+                        .label(name.loc)
+                        .report(self.issues);
+                    None
+                },
+            },
+            ast::Node::ArrayType { len, elem } => {
+                let elem = self.lower_type(elem);
+                let ast::Node::NumIntegral { value : n, suffix : None } = len.value.as_ref() else {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0043)
+                        .message(msg!("hir-array-length-not-literal"))
+                        .label(len.loc)
+                        .report(self.issues);
+                    return None;
+                };
+                let Ok(n) = u64::try_from(n.value) else {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0044)
+                        .message(msg!("hir-array-length-too-large", [n.value.into()]))
+                        .label(len.loc)
+                        .report(self.issues);
+                    return None;
+                };
+                Some(hir::Type::Array { elem : Box::new(elem?), len : n })
+            },
+            ast::Node::GenericType { name, args } => {
+                let arg_tys = args.value.iter()
+                    .map(|arg| self.lower_type(arg))
+                    .collect::<Vec<_>>();
+                if name.value != "Result" {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0041)
+                        .message(msg!("hir-unexpected-type-args", [
+                            name.value.clone().into()
+                        ]))
+                        .label(args.loc)
+                        .report(self.issues);
+                    return None;
+                }
+                let [ok, err] = arg_tys.as_slice() else {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0041)
+                        .message(msg!("hir-result-arg-count", [
+                            arg_tys.len().into()
+                        ]))
+                        .label(args.loc)
+                        .report(self.issues);
+                    return None;
+                };
+                Some(hir::Type::Result {
+                    ok : Box::new(ok.clone()?),
+                    err : Box::new(err.clone()?),
+                })
+            },
+            _ => {
+                self.assert(ast_node, msg!("hir-ast-expected-type"))?;
+                None
+            },
+        }
     }
 
-    fn assert(&mut self, got : &ast::Node, message : &str) -> Option<()> {
+    /// Lowers the variants of an enum. Invalid fields are given the type
+    /// `hir::Type::Unknown`, so the enum can still be used after an error.
+    fn lower_variants(&mut self, variants : &[ast::Variant]) -> Vec<hir::Variant> {
+        let mut result : Vec<hir::Variant> = Vec::new();
+        for variant in variants {
+            if let Some(prev) = result.iter()
+                .find(|prev| prev.name.value == variant.name.value)
+            {
+                Diagnostic::error()
+                    .code(ErrorCode::E0045)
+                    .message(msg!("hir-duplicate-variant", [
+                        variant.name.value.clone().into()
+                    ]))
+                    .label(variant.name.loc)
+                    .label_other((prev.name.loc, msg!("hir-previously-defined")))
+                    .report(self.issues);
+                continue;
+            }
+            let fields = variant.fields.iter()
+                .map(|field| self.lower_type(field).unwrap_or(hir::Type::Unknown))
+                .collect();
+            result.push(hir::Variant { name : variant.name.clone(), fields });
+        }
+        result
+    }
+
+    /// Returns the name of a type which has variants, or `None` if this type
+    /// doesn't have any variants.
+    fn variant_type_name(ty : &hir::Type) -> Option<hir::Symbol> {
+        match ty {
+            hir::Type::Enum(name) => Some(name.clone()),
+            hir::Type::Result { .. } => Some("Result".to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns the names and fields of each variant of an enum or `Result`
+    /// type, and where the variant was defined, if it is user-defined.
+    fn variants_of(
+        &self,
+        ty : &hir::Type,
+    ) -> Option<Vec<VariantInfo>> {
+        match ty {
+            hir::Type::Enum(name) => Some(self.enums.get(name)?.iter()
+                .map(|variant| (
+                    variant.name.value.clone(),
+                    variant.fields.clone(),
+                    Some(variant.name.loc),
+                ))
+                .collect()),
+            hir::Type::Result { ok, err } => Some(hir::Type::result_variants(ok, err)
+                .into_iter()
+                .map(|(name, field)| (name.to_string(), vec![field], None))
+                .collect()),
+            _ => None,
+        }
+    }
+
+    /// Looks up a variant of an enum or `Result` type, returning its index,
+    /// its fields, and where it was defined.
+    fn find_variant(
+        &mut self,
+        ty : &hir::Type,
+        name : &Located<hir::Symbol>,
+    ) -> Option<(usize, Vec<hir::Type>, Option<Location>)> {
+        let variants = self.variants_of(ty)?;
+        let Some(i) = variants.iter().position(|(variant, ..)| *variant == name.value) else {
+            let ty_name = Self::variant_type_name(ty).unwrap_or_default();
+            let known = variants.iter()
+                .map(|(variant, ..)| format!("`{}`", variant))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Diagnostic::error()
+                .code(ErrorCode::E0046)
+                .message(msg!("hir-unknown-variant", [
+                    name.value.clone().into(), ty_name.clone().into()
+                ]))
+                .label(name.loc)
+                .note(msg!("hir-known-variants", [ty_name.into(), known.into()]))
+                .report(self.issues);
+            return None;
+        };
+        let (_, fields, loc) = variants.into_iter().nth(i)?;
+        Some((i, fields, loc))
+    }
+
+    /// Resolves the type named in a variant path, e.g. the `Shape` in
+    /// `Shape::Circle`. The type arguments of `Result` are taken from `hint`.
+    fn resolve_variant_type(
+        &mut self,
+        ty : &Located<hir::Symbol>,
+        hint : Option<&hir::Type>,
+    ) -> Option<hir::Type> {
+        if ty.value == "Result" {
+            return match hint {
+                Some(hint @ hir::Type::Result { .. }) => Some(hint.clone()),
+                // an error has already been reported
+                Some(hir::Type::Unknown) => None,
+                _ => {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0047)
+                        .message(msg!("hir-infer-result"))
+                        .label(ty.loc)
+                        .note(msg!("hir-infer-result-note"))
+                        .report(self.issues);
+                    None
+                },
+            };
+        }
+        if !self.enums.contains_key(&ty.value) {
+            Diagnostic::error()
+                .code(ErrorCode::E0048)
+                .message(msg!("hir-unknown-enum", [ty.value.clone().into()]))
+                .label(ty.loc)
+                .report(self.issues);
+            return None;
+        }
+        Some(hir::Type::Enum(ty.value.clone()))
+    }
+
+    /// Lowers the construction of an enum variant, e.g. `Shape::Circle(1)`.
+    fn lower_variant_expr(
+        &mut self,
+        ty : &Located<hir::Symbol>,
+        name : &Located<hir::Symbol>,
+        args : &[ast::Node],
+        loc : Location,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Expr> {
+        let ty = self.resolve_variant_type(ty, expected)?;
+        let (variant, fields, def_loc) = self.find_variant(&ty, name)?;
+        let ty_name = Self::variant_type_name(&ty).unwrap_or_default();
+        if args.len() != fields.len() {
+            let mut diag = Diagnostic::error()
+                .code(ErrorCode::E0049)
+                .message(msg!("hir-variant-arg-count", [
+                    ty_name.into(), name.value.clone().into(),
+                    fields.len().into(), args.len().into()
+                ]))
+                .label(loc);
+            if let Some(def_loc) = def_loc {
+                diag = diag.label_other((def_loc, msg!("hir-variant-defined")));
+            }
+            diag.report(self.issues);
+            return None;
+        }
+        let hir_args = self.lower_args(args, &fields)?;
+        Some(hir::Expr::new(
+            hir::ExprKind::Variant { variant, args : hir_args },
+            ty,
+            loc,
+        ))
+    }
+
+    /// Lowers the arguments of a call, checking them against the expected
+    /// parameter types.
+    fn lower_args(
+        &mut self,
+        args : &[ast::Node],
+        param_tys : &[hir::Type],
+    ) -> Option<Vec<hir::Expr>> {
+        let mut failed = false;
+        let mut hir_args = Vec::new();
+        for (arg, param_ty) in args.iter().zip(param_tys) {
+            let Some(arg) = self.lower_expr_expecting(arg, Some(param_ty)) else {
+                failed = true;
+                continue;
+            };
+            if self.assert_type(&arg, param_ty, msg!("hir-mismatched-arg")).is_none() {
+                failed = true;
+            }
+            hir_args.push(arg);
+        }
+        if failed { None } else { Some(hir_args) }
+    }
+
+    fn lower_call(
+        &mut self,
+        name : &Located<hir::Symbol>,
+        args : &[ast::Node],
+        loc : Location,
+    ) -> Option<hir::Expr> {
+        let Some(def) = self.fns.iter().rev().find(|def| def.name == name.value).cloned() else {
+            Diagnostic::error()
+                .code(ErrorCode::E0050)
+                .message(msg!("hir-unknown-fn", [name.value.clone().into()]))
+                .label(name.loc)
+                .report(self.issues);
+            return None;
+        };
+        if args.len() != def.params.len() {
+            Diagnostic::error()
+                .code(ErrorCode::E0049)
+                .message(msg!("hir-fn-arg-count", [
+                    name.value.clone().into(), def.params.len().into(), args.len().into()
+                ]))
+                .label(loc)
+                .label_other((def.loc, msg!("hir-fn-defined")))
+                .report(self.issues);
+            return None;
+        }
+        let hir_args = self.lower_args(args, &def.params)?;
+        let Some(ret) = def.ret else {
+            Diagnostic::error()
+                .code(ErrorCode::E0051)
+                .message(msg!("hir-infer-return-type", [
+                    name.value.clone().into()
+                ]))
+                .label(name.loc)
+                .label_other((def.loc, msg!("hir-fn-defined")))
+                .note(msg!("hir-infer-return-type-note"))
+                .report(self.issues);
+            return None;
+        };
+        Some(hir::Expr::new(
+            hir::ExprKind::Call { func : def.path, args : hir_args },
+            ret,
+            name.loc,
+        ))
+    }
+
+    /// Lowers a call to a conversion function, e.g. `u8(x)`, whose name is the
+    /// name of a numeric type.
+    fn lower_convert(
+        &mut self,
+        ty : hir::Type,
+        name : &Located<hir::Symbol>,
+        args : &[ast::Node],
+        loc : Location,
+    ) -> Option<hir::Expr> {
+        let [arg] = args else {
+            Diagnostic::error()
+                .code(ErrorCode::E0049)
+                .message(msg!("hir-conversion-arg-count", [
+                    name.value.clone().into(), args.len().into()
+                ]))
+                .label(loc)
+                .note(msg!("hir-conversion-arg-count-note", [
+                    name.value.clone().into()
+                ]))
+                .report(self.issues);
+            return None;
+        };
+        let value = self.lower_expr_expecting(arg, Some(&ty))?;
+        if !value.ty.is_numeric() && !value.ty.is_compatible(&ty) {
+            Diagnostic::error()
+                .code(ErrorCode::E0052)
+                .message(msg!("hir-invalid-conversion", [
+                    value.ty.to_string().into(), ty.to_string().into()
+                ]))
+                .label(value.loc)
+                .label_other(name.loc)
+                .note(msg!("hir-invalid-conversion-note"))
+                .report(self.issues);
+            return None;
+        }
+        Some(hir::Expr::new(hir::ExprKind::Convert(Box::new(value)), ty, name.loc))
+    }
+
+    /// Reports an error for a type used by an extern function which can't be
+    /// passed to or from C.
+    fn report_no_c_type(&mut self, ty : &hir::Type, loc : Location) {
+        if ty.is_unknown() {
+            // an error has already been reported
+            return;
+        }
+        let mut diagnostic = Diagnostic::error()
+            .code(ErrorCode::E0053)
+            .message(msg!("hir-extern-invalid-type", [ty.to_string().into()]))
+            .label((loc, msg!("hir-extern-invalid-type-label")));
+        diagnostic = if matches!(ty, hir::Type::Unit) {
+            diagnostic.note(msg!("hir-extern-unit-param-note"))
+        } else {
+            diagnostic.note(msg!("hir-extern-invalid-type-note"))
+        };
+        diagnostic.report(self.issues);
+    }
+
+    /// Checks that a function marked `#[test]` can be called by the test
+    /// harness, i.e. it has no parameters and returns `()`.
+    fn check_test_fn(
+        &mut self,
+        name : &Located<hir::Symbol>,
+        params : &[hir::Param],
+        ret : &hir::Type,
+    ) -> Option<()> {
+        if let Some(param) = params.first() {
+            Diagnostic::error()
+                .code(ErrorCode::E0054)
+                .message(msg!("hir-test-has-params"))
+                .label(param.name.loc)
+                .label_other(name.loc)
+                .report(self.issues);
+            return None;
+        }
+        if !matches!(ret, hir::Type::Unit | hir::Type::Never | hir::Type::Unknown) {
+            Diagnostic::error()
+                .code(ErrorCode::E0054)
+                .message(msg!("hir-test-bad-return", [
+                    ret.to_string().into()
+                ]))
+                .label(name.loc)
+                .note(msg!("hir-test-bad-return-help"))
+                .report(self.issues);
+            return None;
+        }
+        Some(())
+    }
+
+    /// Lowers a call to the `assert` builtin, e.g. `assert(x > 0)`.
+    fn lower_assert(
+        &mut self,
+        name : &Located<hir::Symbol>,
+        args : &[ast::Node],
+        loc : Location,
+    ) -> Option<hir::Expr> {
+        let [arg] = args else {
+            Diagnostic::error()
+                .code(ErrorCode::E0049)
+                .message(msg!("hir-assert-arg-count", [
+                    args.len().into()
+                ]))
+                .label(loc)
+                .label_other(name.loc)
+                .note(msg!("hir-assert-arg-count-note"))
+                .report(self.issues);
+            return None;
+        };
+        let cond = self.lower_condition(arg)?;
+        // the location of the arguments without their parentheses, so the
+        // source text of the condition can be shown if the assertion fails
+        let cond_loc = Location { span : loc.span.shrink(1, 1), ..loc };
+        Some(hir::Expr::new(hir::ExprKind::Assert(Box::new(cond)), hir::Type::Unit, cond_loc))
+    }
+
+    /// Lowers an integer literal. Its type comes from its suffix if it has
+    /// one, otherwise from the expected type if it's an integer type, and is
+    /// `int` by default. If `negative` is true, the literal is range checked
+    /// as if it were negated.
+    fn lower_num_integral(
+        &mut self,
+        value : &Located<u128>,
+        suffix : &Option<Located<hir::Symbol>>,
+        expected : Option<&hir::Type>,
+        negative : bool,
+    ) -> Option<hir::Expr> {
+        let ty = match suffix {
+            Some(suffix) => self.lower_num_suffix(suffix)?,
+            None => match expected {
+                Some(ty) if ty.is_integral() => ty.clone(),
+                _ => hir::Type::Int,
+            },
+        };
+        if ty.is_float() {
+            // integers with a float suffix, e.g. `1f32`
+            let rational = Some(Rational::from_u128(value.value));
+            return self.lower_float_literal(rational, &value.value.to_string(), ty, value.loc);
+        }
+        let (min, max) = ty.int_range()?;
+        let in_range = if negative {
+            value.value <= min.unsigned_abs()
+        } else {
+            value.value <= max as u128
+        };
+        if !in_range {
+            let sign = if negative { "-" } else { "" };
+            Diagnostic::error()
+                .code(ErrorCode::E0055)
+                .message(msg!("hir-literal-out-of-range", [ty.to_string().into()]))
+                .label((value.loc, msg!("hir-negative-literal-overflow", [
+                    sign.into(), value.value.into(), ty.to_string().into()
+                ])))
+                .note(msg!("hir-valid-range", [
+                    ty.to_string().into(), min.into(), max.into()
+                ]))
+                .report(self.issues);
+            return None;
+        }
+        Some(hir::Expr::new(hir::ExprKind::NumIntegral(value.value), ty, value.loc))
+    }
+
+    /// Lowers a floating-point literal. Its type comes from its suffix if it
+    /// has one, otherwise from the expected type if it's a floating-point
+    /// type, and is `rational` by default.
+    fn lower_num_rational(
+        &mut self,
+        value : &Located<hir::Symbol>,
+        suffix : &Option<Located<hir::Symbol>>,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Expr> {
+        let ty = match suffix {
+            Some(suffix) => {
+                let ty = self.lower_num_suffix(suffix)?;
+                if !ty.is_float() {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0056)
+                        .message(msg!("hir-invalid-rational-suffix", [
+                            suffix.value.clone().into()
+                        ]))
+                        .label(suffix.loc)
+                        .note(msg!("hir-invalid-rational-suffix-note"))
+                        .report(self.issues);
+                    return None;
+                }
+                ty
+            },
+            None => match expected {
+                Some(ty) if ty.is_float() => ty.clone(),
+                _ => hir::Type::Rational,
+            },
+        };
+        let rational = Rational::from_decimal(&value.value);
+        self.lower_float_literal(rational, &value.value, ty, value.loc)
+    }
+
+    /// Checks that the exact value of a floating-point literal fits into the
+    /// float type `ty`, and warns if it will be rounded. A value of `None`
+    /// means the literal is too large or too small to be computed exactly.
+    fn lower_float_literal(
+        &mut self,
+        value : Option<Rational>,
+        src : &str,
+        ty : hir::Type,
+        loc : Location,
+    ) -> Option<hir::Expr> {
+        let is_f32 = matches!(ty, hir::Type::Num(hir::NumType::F32));
+        let nearest = value.as_ref().map(|value| {
+            if is_f32 { f64::from(value.to_f32()) } else { value.to_f64() }
+        });
+        let Some((value, nearest)) = value.zip(nearest)
+            .filter(|(_, nearest)| nearest.is_finite())
+        else {
+            let max = if is_f32 { f64::from(f32::MAX) } else { f64::MAX };
+            Diagnostic::error()
+                .code(ErrorCode::E0055)
+                .message(msg!("hir-literal-out-of-range", [ty.to_string().into()]))
+                .label((loc, msg!("hir-literal-overflow", [
+                    src.into(), ty.to_string().into()
+                ])))
+                .note(msg!("hir-valid-range", [
+                    ty.to_string().into(),
+                    format!("{:e}", -max).into(),
+                    format!("{:e}", max).into(),
+                ]))
+                .report(self.issues);
+            return None;
+        };
+        let exact = if is_f32 { value.is_exact_f32() } else { value.is_exact_f64() };
+        if !exact {
+            // the shortest decimal which rounds to the same float
+            let nearest = if is_f32 {
+                format!("{:?}", nearest as f32)
+            } else {
+                format!("{:?}", nearest)
+            };
+            Diagnostic::lint(&lint::INEXACT_FLOAT_LITERALS)
+                .code(ErrorCode::E0057)
+                .message(msg!("hir-inexact-float", [
+                    ty.to_string().into()
+                ]))
+                .label((loc, msg!("hir-inexact-float-label", [
+                    nearest.into()
+                ])))
+                .note(msg!("hir-inexact-float-note"))
+                .report(self.issues);
+        }
+        Some(hir::Expr::new(hir::ExprKind::NumRational(value), ty, loc))
+    }
+
+    /// Resolves the type suffix of a number literal.
+    fn lower_num_suffix(&mut self, suffix : &Located<hir::Symbol>) -> Option<hir::Type> {
+        let Some(num) = hir::NumType::from_name(&suffix.value) else {
+            let names = hir::NumType::ALL.iter()
+                .map(|num| format!("`{}`", num.name()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Diagnostic::error()
+                .code(ErrorCode::E0056)
+                .message(msg!("hir-invalid-number-suffix", [
+                    suffix.value.clone().into()
+                ]))
+                .label(suffix.loc)
+                .note(msg!("hir-valid-suffixes", [names.into()]))
+                .report(self.issues);
+            return None;
+        };
+        Some(hir::Type::Num(num))
+    }
+
+    /// Desugars `#[try] expr` into a `match` which returns early from the
+    /// enclosing function if `expr` is an error:
+    ///
+    /// ```text
+    /// match expr
+    /// case Result::Ok(value) then value
+    /// case Result::Err(err) then return Result::Err(err)
+    /// end
+    /// ```
+    fn lower_try(&mut self, expr : hir::Expr, try_loc : Location) -> Option<hir::Expr> {
+        let (ok, err) = match &expr.ty {
+            hir::Type::Result { ok, err } => (ok.as_ref().clone(), err.as_ref().clone()),
+            hir::Type::Unknown => return None,
+            ty => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0058)
+                    .message(msg!("hir-try-not-result", [
+                        ty.to_string().into()
+                    ]))
+                    .label(expr.loc)
+                    .label_other(try_loc)
+                    .report(self.issues);
+                return None;
+            },
+        };
+        let ctx = self.current_fn.as_ref()?;
+        let fn_ret = match &ctx.ret {
+            Some(ret @ hir::Type::Result { err : ret_err, .. }) => {
+                if **ret_err != err && !ret_err.is_unknown() && !err.is_unknown() {
+                    let ret_loc = ctx.ret_loc.unwrap_or(ctx.params_loc);
+                    Diagnostic::error()
+                        .code(ErrorCode::E0059)
+                        .message(msg!("hir-try-mismatched-error", [
+                            err.to_string().into()
+                        ]))
+                        .label((expr.loc, msg!("hir-has-type", [
+                            expr.ty.to_string().into()
+                        ])))
+                        .label_other((ret_loc, msg!("hir-try-mismatched-error-label", [
+                            ret_err.to_string().into()
+                        ])))
+                        .report(self.issues);
+                    return None;
+                }
+                ret.clone()
+            },
+            // an error has already been reported
+            Some(hir::Type::Unknown) => return None,
+            Some(ret) => {
+                let ret_loc = ctx.ret_loc.unwrap_or(ctx.params_loc);
+                Diagnostic::error()
+                    .code(ErrorCode::E0060)
+                    .message(msg!("hir-try-outside-result-fn"))
+                    .label(try_loc)
+                    .label_other((ret_loc, msg!("hir-fn-returns", [
+                        ret.to_string().into()
+                    ])))
+                    .note(msg!("hir-try-change-return-help", [
+                        ret.to_string().into(), err.to_string().into()
+                    ]))
+                    .report(self.issues);
+                return None;
+            },
+            None => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0060)
+                    .message(msg!("hir-try-outside-result-fn"))
+                    .label(try_loc)
+                    .label_other((ctx.params_loc, msg!("hir-fn-no-return-type")))
+                    .note(msg!("hir-try-add-return-help", [
+                        err.to_string().into()
+                    ]))
+                    .report(self.issues);
+                return None;
+            },
+        };
+        let loc = expr.loc;
+        let ok_local = self.next_local;
+        let err_local = self.next_local + 1;
+        self.next_local += 2;
+        let binding = |id, ty : &hir::Type| hir::Pattern {
+            kind : hir::PatternKind::Binding(id),
+            ty : ty.clone(),
+            loc,
+        };
+        let ok_arm = hir::MatchArm {
+            pattern : hir::Pattern {
+                kind : hir::PatternKind::Variant {
+                    variant : 0,
+                    fields : vec![binding(ok_local, &ok)],
+                },
+                ty : expr.ty.clone(),
+                loc,
+            },
+            body : hir::Expr::new(hir::ExprKind::Local(ok_local), ok.clone(), loc),
+        };
+        let err_value = hir::Expr::new(
+            hir::ExprKind::Variant {
+                variant : 1,
+                args : vec![hir::Expr::new(hir::ExprKind::Local(err_local), err.clone(), loc)],
+            },
+            fn_ret,
+            loc,
+        );
+        let err_arm = hir::MatchArm {
+            pattern : hir::Pattern {
+                kind : hir::PatternKind::Variant {
+                    variant : 1,
+                    fields : vec![binding(err_local, &err)],
+                },
+                ty : expr.ty.clone(),
+                loc,
+            },
+            body : hir::Expr::new(
+                hir::ExprKind::Return(Some(Box::new(err_value))),
+                hir::Type::Never,
+                loc,
+            ),
+        };
+        Some(hir::Expr::new(
+            hir::ExprKind::Match {
+                scrutinee : Box::new(expr),
+                arms : vec![ok_arm, err_arm],
+            },
+            ok,
+            try_loc,
+        ))
+    }
+
+    fn lower_match(
+        &mut self,
+        scrutinee : &ast::Node,
+        arms : &[ast::MatchArm],
+        loc : Location,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Expr> {
+        let scrutinee = self.lower_expr(scrutinee)?;
+        let mut hir_arms : Vec<hir::MatchArm> = Vec::new();
+        let mut failed = false;
+        for arm in arms {
+            let scope = self.locals.len();
+            let pattern = self.lower_pattern(&arm.pattern, &scrutinee.ty);
+            // later arms are expected to have the same type as the first arm
+            // which produces a value
+            let first = hir_arms.iter()
+                .find(|arm| !matches!(arm.body.ty, hir::Type::Never));
+            let arm_expected = expected.cloned()
+                .or_else(|| first.map(|first| first.body.ty.clone()));
+            let body = self.lower_expr_expecting(&arm.body, arm_expected.as_ref());
+            self.locals.truncate(scope);
+            let (Some(pattern), Some(body)) = (pattern, body) else {
+                failed = true;
+                continue;
+            };
+            if let Some(first) = hir_arms.iter()
+                    .find(|arm| !matches!(arm.body.ty, hir::Type::Never)) {
+                let expected = &first.body.ty;
+                if !body.ty.is_compatible(expected) {
+                    Diagnostic::error()
+                        .code(ErrorCode::E0033)
+                        .message(msg!("hir-incompatible-arms"))
+                        .label((value_loc(&body), msg!("hir-expected-got", [
+                            expected.to_string().into(), body.ty.to_string().into()
+                        ])))
+                        .label_other((value_loc(&first.body), msg!("hir-first-arm-has-type", [
+                            expected.to_string().into()
+                        ])))
+                        .report(self.issues);
+                    failed = true;
+                    continue;
+                }
+            }
+            hir_arms.push(hir::MatchArm { pattern, body });
+        }
+        if failed {
+            return None;
+        }
+        exhaustive::check_match(self.issues, &self.enums, &scrutinee, &hir_arms, loc);
+        let ty = hir_arms.iter()
+            .map(|arm| arm.body.ty.clone())
+            .reduce(|ty, arm_ty| ty.join(&arm_ty))
+            .unwrap_or(hir::Type::Unit);
+        Some(hir::Expr::new(
+            hir::ExprKind::Match {
+                scrutinee : Box::new(scrutinee),
+                arms : hir_arms,
+            },
+            ty,
+            loc,
+        ))
+    }
+
+    /// Lowers a pattern, checking that it can match values of type `ty`. Any
+    /// variables bound by the pattern are added to the current scope.
+    fn lower_pattern(
+        &mut self,
+        ast_node : &ast::Node,
+        ty : &hir::Type,
+    ) -> Option<hir::Pattern> {
+        let loc = match ast_node {
+            ast::Node::Call { callee, .. } => callee.primary_location(),
+            // negative literals are a single pattern, including their sign
+            ast::Node::Unary { op, value } => op.loc.join(&value.primary_location()),
+            _ => ast_node.primary_location(),
+        };
+        let (kind, pattern_ty) = match ast_node {
+            ast::Node::Parens(node) => return self.lower_pattern(&node.value, ty),
+            ast::Node::Id(name) if name.value == "_" => {
+                (hir::PatternKind::Wildcard, ty.clone())
+            },
+            ast::Node::Id(name) => {
+                // unqualified variant names take priority over bindings
+                if let Some(ty_name) = Self::variant_type_name(ty)
+                        && self.variants_of(ty).is_some_and(|variants| {
+                            variants.iter().any(|(variant, ..)| *variant == name.value)
+                        }) {
+                    let ty_name = Located { value : ty_name, loc : name.loc };
+                    return self.lower_variant_pattern(&ty_name, name, &[], loc, ty);
+                }
+                let id = self.next_local;
+                self.next_local += 1;
+                self.locals.push(LocalDef {
+                    name : name.value.clone(),
+                    id,
+                    ty : Some(ty.clone()),
+                    kind : LocalKind::Binding,
+                    loc : name.loc,
+                });
+                (hir::PatternKind::Binding(id), ty.clone())
+            },
+            ast::Node::NumIntegral { value, suffix } => {
+                self.lower_num_pattern(value, suffix, false, loc, ty)?
+            },
+            ast::Node::Unary { op, value : operand } if op.value == ast::UnaryOp::Neg
+                    && matches!(operand.as_ref(), ast::Node::NumIntegral { .. }) => {
+                let ast::Node::NumIntegral { value, suffix } = operand.as_ref() else {
+                    unreachable!()
+                };
+                self.lower_num_pattern(value, suffix, true, loc, ty)?
+            },
+            ast::Node::Bool(b) => (hir::PatternKind::Bool(b.value), hir::Type::Bool),
+            ast::Node::Path { ty : ty_name, name } => {
+                return self.lower_variant_pattern(ty_name, name, &[], loc, ty);
+            },
+            ast::Node::Call { callee, args } => {
+                let (ty_name, name) = match (callee.as_ref(), Self::variant_type_name(ty)) {
+                    (ast::Node::Path { ty : ty_name, name }, _) => (ty_name.clone(), name),
+                    (ast::Node::Id(name), Some(ty_name)) => {
+                        (Located { value : ty_name, loc : name.loc }, name)
+                    },
+                    _ => {
+                        Diagnostic::error()
+                            .code(ErrorCode::E0062)
+                            .message(msg!("hir-expected-variant"))
+                            .label(callee.primary_location())
+                            .report(self.issues);
+                        return None;
+                    },
+                };
+                return self.lower_variant_pattern(&ty_name, name, &args.value, loc, ty);
+            },
+            _ => {
+                Diagnostic::error()
+                    .code(ErrorCode::E0062)
+                    .message(msg!("hir-expected-pattern"))
+                    .label(loc)
+                    .note(msg!("hir-expected-pattern-note"))
+                    .report(self.issues);
+                return None;
+            },
+        };
+        self.assert_pattern_type(&pattern_ty.to_string(), ty, loc)?;
+        Some(hir::Pattern { kind, ty : ty.clone(), loc })
+    }
+
+    /// Lowers an integer literal pattern. Negative literals are range checked
+    /// as a whole, and folded into a single value like they are in
+    /// expressions.
+    fn lower_num_pattern(
+        &mut self,
+        value : &Located<u128>,
+        suffix : &Option<Located<hir::Symbol>>,
+        negative : bool,
+        loc : Location,
+        ty : &hir::Type,
+    ) -> Option<(hir::PatternKind, hir::Type)> {
+        let literal = self.lower_num_integral(value, suffix, Some(ty), negative)?;
+        let hir::ExprKind::NumIntegral(n) = literal.kind else {
+            Diagnostic::error()
+                .code(ErrorCode::E0061)
+                .message(msg!("hir-float-pattern"))
+                .label(loc)
+                .report(self.issues);
+            return None;
+        };
+        let n = if negative { n.wrapping_neg() } else { n };
+        Some((hir::PatternKind::NumIntegral(n), literal.ty))
+    }
+
+    fn lower_variant_pattern(
+        &mut self,
+        ty_name : &Located<hir::Symbol>,
+        name : &Located<hir::Symbol>,
+        args : &[ast::Node],
+        loc : Location,
+        ty : &hir::Type,
+    ) -> Option<hir::Pattern> {
+        if ty_name.value == "Result" && !matches!(ty, hir::Type::Result { .. }) {
+            // the type arguments of `Result` patterns come from the
+            // scrutinee, so they can't be checked against it
+            self.assert_pattern_type("Result", ty, loc)?;
+        }
+        let pattern_ty = self.resolve_variant_type(ty_name, Some(ty))?;
+        self.assert_pattern_type(&pattern_ty.to_string(), ty, loc)?;
+        let (variant, fields, def_loc) = self.find_variant(&pattern_ty, name)?;
+        if args.len() != fields.len() {
+            let mut diag = Diagnostic::error()
+                .code(ErrorCode::E0063)
+                .message(msg!("hir-pattern-field-count", [
+                    ty_name.value.clone().into(), name.value.clone().into(),
+                    fields.len().into(), args.len().into()
+                ]))
+                .label(loc);
+            if let Some(def_loc) = def_loc {
+                diag = diag.label_other((def_loc, msg!("hir-variant-defined")));
+            }
+            diag.report(self.issues);
+            return None;
+        }
+        let mut hir_fields = Vec::new();
+        let mut failed = false;
+        for (arg, field_ty) in args.iter().zip(&fields) {
+            if let Some(field) = self.lower_pattern(arg, field_ty) {
+                hir_fields.push(field);
+            } else {
+                failed = true;
+            }
+        }
+        if failed {
+            return None;
+        }
+        Some(hir::Pattern {
+            kind : hir::PatternKind::Variant { variant, fields : hir_fields },
+            ty : pattern_ty,
+            loc,
+        })
+    }
+
+    fn assert_pattern_type(
+        &mut self,
+        got : &str,
+        expected : &hir::Type,
+        loc : Location,
+    ) -> Option<()> {
+        if got == expected.to_string() || expected.is_unknown() {
+            return Some(());
+        }
         Diagnostic::error()
-            .message(("malformed AST! {}, got {}", [message.into(), got.name().into()]))
-            .label(got.primary_location())
+            .code(ErrorCode::E0064)
+            .message(msg!("hir-mismatched-pattern", [got.to_string().into()]))
+            .label((loc, msg!("hir-expected-type", [expected.to_string().into()])))
             .report(self.issues);
         None
     }
 
-    fn lower_module(&mut self, ast_node : &ast::Node) -> hir::Module {
-        let items = Vec::new();
-        //self.assert(ast_node, "expected block");
-        hir::Module { items }
+    /// Checks attributes against the attribute registry, reporting any which
+    /// are unknown, malformed, or cannot be applied to `target`.
+    fn lower_attrs(
+        &mut self,
+        attrs : &[ast::Attribute],
+        target : hir::AttrTarget,
+        node : &ast::Node,
+    ) -> Vec<hir::Attr> {
+        let mut result : Vec<hir::Attr> = Vec::new();
+        let mut duplicates = Vec::new();
+        for attr in attrs {
+            let Some(kind) = hir::AttrKind::from_name(&attr.name.value) else {
+                let known = hir::AttrKind::ALL.iter()
+                    .map(|kind| format!("`#[{}]`", kind.name()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                Diagnostic::error()
+                    .code(ErrorCode::E0065)
+                    .message(msg!("hir-unknown-attr", [
+                        attr.name.value.clone().into()
+                    ]))
+                    .label(attr.name.loc)
+                    .note(msg!("hir-known-attrs", [known.into()]))
+                    .report(self.issues);
+                continue;
+            };
+            let mut lints = Vec::new();
+            if kind.lint_level().is_some() {
+                lints = self.lower_lint_names(kind, attr);
+            } else if let Some(arg) = attr.args.first() {
+                Diagnostic::error()
+                    .code(ErrorCode::E0066)
+                    .message(msg!("hir-attr-has-args", [
+                        kind.name().into()
+                    ]))
+                    .label(arg.primary_location())
+                    .report(self.issues);
+                continue;
+            }
+            if kind.target() != target {
+                Diagnostic::error()
+                    .code(ErrorCode::E0067)
+                    .message(msg!("hir-attr-wrong-target", [
+                        kind.name().into(), target.description().into()
+                    ]))
+                    .label(attr.loc)
+                    .label_other(node.primary_location())
+                    .note(msg!("hir-attr-wrong-target-note", [
+                        kind.name().into(), kind.target().description().into()
+                    ]))
+                    .report(self.issues);
+                continue;
+            }
+            if kind.lint_level().is_none()
+                    && let Some(prev) = result.iter().find(|prev| prev.kind == kind) {
+                duplicates.push((kind, attr.loc, prev.loc));
+                continue;
+            }
+            result.push(hir::Attr { kind, lints, loc : attr.loc });
+        }
+        // duplicates are reported last, so they can be allowed by the very
+        // attributes they're applied alongside
+        self.issues.push_lint_scope(hir::lint_attrs(&result));
+        for (kind, loc, prev_loc) in duplicates {
+            Diagnostic::lint(&lint::DUPLICATE_ATTRIBUTES)
+                .code(ErrorCode::E0068)
+                .message(msg!("hir-duplicate-attr", [
+                    kind.name().into()
+                ]))
+                .label(loc)
+                .label_other((prev_loc, msg!("hir-duplicate-attr-label")))
+                .report(self.issues);
+        }
+        self.issues.pop_lint_scope();
+        result
+    }
+
+    /// Checks the arguments of a lint attribute such as `#[allow(..)]`,
+    /// returning the names of the lints. Unknown lints are reported and
+    /// skipped.
+    fn lower_lint_names(
+        &mut self,
+        kind : hir::AttrKind,
+        attr : &ast::Attribute,
+    ) -> Vec<hir::Symbol> {
+        if attr.args.is_empty() {
+            Diagnostic::error()
+                .code(ErrorCode::E0085)
+                .message(msg!("hir-lint-attr-no-args", [
+                    kind.name().into()
+                ]))
+                .label(attr.loc)
+                .note(msg!("hir-lint-attr-example", [
+                    kind.name().into(), lint::UNREACHABLE_CODE.name.into()
+                ]))
+                .report(self.issues);
+            return Vec::new();
+        }
+        let mut lints = Vec::new();
+        for arg in &attr.args {
+            let ast::Node::Id(name) = arg else {
+                Diagnostic::error()
+                    .code(ErrorCode::E0085)
+                    .message(msg!("hir-lint-attr-not-name"))
+                    .label(arg.primary_location())
+                    .report(self.issues);
+                continue;
+            };
+            if !lint::is_known(&name.value) {
+                Diagnostic::lint(&lint::UNKNOWN_LINTS)
+                    .code(ErrorCode::E0084)
+                    .message(msg!("hir-unknown-lint", [name.value.clone().into()]))
+                    .label(name.loc)
+                    .note(msg!("hir-known-lints", [lint::known_names().into()]))
+                    .report(self.issues);
+                continue;
+            }
+            lints.push(name.value.clone());
+        }
+        lints
+    }
+
+    fn lower_block(
+        &mut self,
+        nodes : &[ast::Node],
+        loc : Location,
+        expected : Option<&hir::Type>,
+    ) -> hir::Expr {
+        let scope = self.locals.len();
+        let fn_scope = self.fns.len();
+        self.declare_fns(nodes.iter());
+        let mut stmts = Vec::new();
+        let mut ty = hir::Type::Unit;
+        let mut diverges = false;
+        for (i, node) in nodes.iter().enumerate() {
+            // only the final statement determines the value of the block
+            let expected = if i + 1 == nodes.len() { expected } else { None };
+            if let Some(stmt) = self.lower_stmt(node, expected) {
+                ty = match &stmt {
+                    hir::Stmt::Expr(expr) => expr.ty.clone(),
+                    _ => hir::Type::Unit,
+                };
+                diverges |= stmt.diverges();
+                stmts.push(stmt);
+            } else {
+                ty = hir::Type::Unknown;
+            }
+        }
+        // update the types of locals whose types were inferred from their
+        // first assignment
+        for local in &self.locals[scope..] {
+            let LocalKind::Local { deferred : true, .. } = local.kind else { continue };
+            let Some(hir::Stmt::Local { ty, .. }) = stmts.iter_mut().find(|stmt| {
+                matches!(stmt, hir::Stmt::Local { id, .. } if *id == local.id)
+            }) else {
+                continue;
+            };
+            if let Some(local_ty) = &local.ty {
+                *ty = local_ty.clone();
+                continue;
+            }
+            Diagnostic::error()
+                .code(ErrorCode::E0069)
+                .message(msg!("hir-infer-local", [
+                    local.name.clone().into()
+                ]))
+                .label(local.loc)
+                .note(msg!("hir-infer-local-help", [
+                    local.name.clone().into()
+                ]))
+                .report(self.issues);
+        }
+        self.locals.truncate(scope);
+        self.fns.truncate(fn_scope);
+        // blocks containing a statement which never finishes never produce a
+        // value either
+        if diverges && !ty.is_unknown() {
+            ty = hir::Type::Never;
+        }
+        hir::Expr::new(hir::ExprKind::Block(stmts), ty, loc)
+    }
+
+    fn lower_array(
+        &mut self,
+        nodes : &[ast::Node],
+        loc : Location,
+        expected : Option<&hir::Type>,
+    ) -> Option<hir::Expr> {
+        let mut elem_expected = match expected {
+            Some(hir::Type::Array { elem, .. }) => Some(elem.as_ref().clone()),
+            _ => None,
+        };
+        let mut elems = Vec::new();
+        let mut failed = false;
+        for node in nodes {
+            if let Some(elem) = self.lower_expr_expecting(node, elem_expected.as_ref()) {
+                if elem_expected.is_none() {
+                    elem_expected = Some(elem.ty.clone());
+                }
+                elems.push(elem);
+            } else {
+                failed = true;
+            }
+        }
+        let Some(first) = elems.first() else {
+            if !failed {
+                Diagnostic::error()
+                    .code(ErrorCode::E0069)
+                    .message(msg!("hir-infer-empty-array"))
+                    .label(loc)
+                    .report(self.issues);
+            }
+            return None;
+        };
+        let elem_ty = first.ty.clone();
+        for elem in &elems[1..] {
+            if elem.ty.is_compatible(&elem_ty) {
+                continue;
+            }
+            Diagnostic::error()
+                .code(ErrorCode::E0070)
+                .message(msg!("hir-incompatible-elements"))
+                .label((elem.loc, msg!("hir-expected-got", [
+                    elem_ty.to_string().into(), elem.ty.to_string().into()
+                ])))
+                .label_other((first.loc, msg!("hir-first-element-has-type", [
+                    elem_ty.to_string().into()
+                ])))
+                .report(self.issues);
+            failed = true;
+        }
+        if failed {
+            return None;
+        }
+        let ty = hir::Type::Array {
+            elem : Box::new(elem_ty),
+            len : elems.len() as u64,
+        };
+        Some(hir::Expr::new(hir::ExprKind::Array(elems), ty, loc))
     }
 }
 
-/// Responsible for converting HIR into Cosy ASM.
-pub struct Hir2Casm<'a> {
-    issues : &'a mut IssueManager,
+/// Returns the location of a type annotation, including any type arguments
+/// or array lengths.
+fn type_location(ast_node : &ast::Node) -> Location {
+    match ast_node {
+        ast::Node::GenericType { name, args } => name.loc.join(&args.loc),
+        ast::Node::ArrayType { len, elem } => len.loc.join(&type_location(elem)),
+        _ => ast_node.primary_location(),
+    }
 }
 
-impl<'a> Hir2Casm<'a> {
-    /// Lowers a collection of HIR into Cosy ASM, resolving inter-module symbol
-    /// linking.
-    ///
-    /// Writes any errors to `issues`.
-    pub fn lower(
-        issues : &'a mut IssueManager,
-        hir : &hir::Module
-    ) -> casm::Package {
-        let mut ctx = Self { issues };
-        ctx.lower_module(hir)
+/// Returns the declaration wrapped by any attributes.
+fn without_attrs(ast_node : &ast::Node) -> &ast::Node {
+    match ast_node {
+        ast::Node::Attrs { node, .. } => without_attrs(node),
+        _ => ast_node,
     }
+}
+
+/// Returns whether `ast_node` is a number literal without a type suffix, whose
+/// type is inferred from how it's used.
+fn is_untyped_literal(ast_node : &ast::Node) -> bool {
+    match ast_node {
+        ast::Node::NumIntegral { suffix, .. }
+        | ast::Node::NumRational { suffix, .. } => suffix.is_none(),
+        ast::Node::Parens(node) => is_untyped_literal(&node.value),
+        ast::Node::Unary { value, .. } => is_untyped_literal(value),
+        _ => false,
+    }
+}
+
+/// Returns the name of a declaration, if it has one.
+fn decl_name(ast_node : &ast::Node) -> Option<&hir::Symbol> {
+    match without_attrs(ast_node) {
+        ast::Node::Fn { name, .. }
+        | ast::Node::ExternFn { name, .. }
+        | ast::Node::Type { name, .. }
+        | ast::Node::Const { name, .. } => Some(&name.value),
+        _ => None,
+    }
+}
 
-    fn lower_module(&mut self, _hir : &hir::Module) -> casm::Package {
-        casm::Package
+/// Returns the location of the expression which determines the value of
+/// `expr`, e.g. the final expression of a block.
+fn value_loc(expr : &hir::Expr) -> Location {
+    if let hir::ExprKind::Block(stmts) = &expr.kind
+            && let Some(hir::Stmt::Expr(last)) = stmts.last() {
+        return value_loc(last);
     }
+    expr.loc
 }
-*/
\ No newline at end of file
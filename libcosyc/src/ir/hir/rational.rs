@@ -0,0 +1,694 @@
+//! Exact rational numbers, used to interpret number literals and evaluate
+//! constants at compile time without losing precision.
+
+use std::{ cmp, fmt };
+use std::cmp::Ordering;
+
+/// The largest decimal exponent a rational literal can have. Numbers outside
+/// of this range can't be represented by any float type, so there is no
+/// point in computing them exactly.
+pub const MAX_EXPONENT : i64 = 1000;
+
+/// An arbitrary-precision unsigned integer.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct BigUint {
+    /// The base 2^32 digits of the number, least significant first, without
+    /// any trailing zero digits.
+    digits : Vec<u32>,
+}
+
+impl BigUint {
+    pub fn from_u128(mut n : u128) -> BigUint {
+        let mut digits = Vec::new();
+        while n > 0 {
+            digits.push(n as u32);
+            n >>= 32;
+        }
+        BigUint { digits }
+    }
+
+    pub fn is_zero(&self) -> bool { self.digits.is_empty() }
+
+    /// Returns the value of this number, if it fits into a `u128`.
+    pub fn to_u128(&self) -> Option<u128> {
+        if self.digits.len() > 4 {
+            return None;
+        }
+        Some(self.digits.iter().rev().fold(0, |n, digit| n << 32 | u128::from(*digit)))
+    }
+
+    /// Returns the number of bits needed to represent this number.
+    pub fn bits(&self) -> u64 {
+        let Some(last) = self.digits.last() else { return 0 };
+        (self.digits.len() as u64 - 1) * 32 + u64::from(32 - last.leading_zeros())
+    }
+
+    /// Replaces this number with `self * mul + add`.
+    fn mul_add_small(&mut self, mul : u32, add : u32) {
+        let mut carry = u64::from(add);
+        for digit in &mut self.digits {
+            let n = u64::from(*digit) * u64::from(mul) + carry;
+            *digit = n as u32;
+            carry = n >> 32;
+        }
+        if carry > 0 {
+            self.digits.push(carry as u32);
+        }
+        self.normalise();
+    }
+
+    /// Replaces this number with `self / div`, returning the remainder.
+    fn div_small(&mut self, div : u32) -> u32 {
+        let mut rem = 0u64;
+        for digit in self.digits.iter_mut().rev() {
+            let n = rem << 32 | u64::from(*digit);
+            *digit = (n / u64::from(div)) as u32;
+            rem = n % u64::from(div);
+        }
+        self.normalise();
+        rem as u32
+    }
+
+    /// Divides this number by `div` as many times as possible, returning the
+    /// number of divisions.
+    fn remove_factor(&mut self, div : u32) -> u64 {
+        let mut count = 0;
+        while !self.is_zero() {
+            let mut quotient = self.clone();
+            if quotient.div_small(div) != 0 {
+                break;
+            }
+            *self = quotient;
+            count += 1;
+        }
+        count
+    }
+
+    /// Returns the number of zero bits below the lowest set bit.
+    fn trailing_zeros(&self) -> u64 {
+        let mut zeros = 0;
+        for digit in &self.digits {
+            if *digit != 0 {
+                return zeros + u64::from(digit.trailing_zeros());
+            }
+            zeros += 32;
+        }
+        zeros
+    }
+
+    fn add(&self, other : &BigUint) -> BigUint {
+        let mut digits = Vec::with_capacity(cmp::max(self.digits.len(), other.digits.len()) + 1);
+        let mut carry = 0u64;
+        for i in 0..cmp::max(self.digits.len(), other.digits.len()) {
+            let n = u64::from(self.digit(i)) + u64::from(other.digit(i)) + carry;
+            digits.push(n as u32);
+            carry = n >> 32;
+        }
+        digits.push(carry as u32);
+        let mut result = BigUint { digits };
+        result.normalise();
+        result
+    }
+
+    /// Returns `self - other`, where `other` is no larger than `self`.
+    fn sub(&self, other : &BigUint) -> BigUint {
+        let mut digits = Vec::with_capacity(self.digits.len());
+        let mut borrow = 0i64;
+        for i in 0..self.digits.len() {
+            let mut n = i64::from(self.digit(i)) - i64::from(other.digit(i)) - borrow;
+            borrow = if n < 0 { 1 } else { 0 };
+            n += borrow << 32;
+            digits.push(n as u32);
+        }
+        let mut result = BigUint { digits };
+        result.normalise();
+        result
+    }
+
+    fn mul(&self, other : &BigUint) -> BigUint {
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, a) in self.digits.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.digits.iter().enumerate() {
+                let n = u64::from(*a) * u64::from(*b) + u64::from(digits[i + j]) + carry;
+                digits[i + j] = n as u32;
+                carry = n >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        let mut result = BigUint { digits };
+        result.normalise();
+        result
+    }
+
+    /// Returns `self * 2^bits`.
+    fn shl(&self, bits : u64) -> BigUint {
+        if self.is_zero() {
+            return self.clone();
+        }
+        let (words, bits) = ((bits / 32) as usize, (bits % 32) as u32);
+        let mut digits = vec![0u32; words];
+        let mut carry = 0u32;
+        for digit in &self.digits {
+            let n = u64::from(*digit) << bits;
+            digits.push(n as u32 | carry);
+            carry = (n >> 32) as u32;
+        }
+        digits.push(carry);
+        let mut result = BigUint { digits };
+        result.normalise();
+        result
+    }
+
+    /// Returns `self / 2^bits`, rounded down.
+    fn shr(&self, bits : u64) -> BigUint {
+        let (words, bits) = ((bits / 32) as usize, (bits % 32) as u32);
+        let digits = self.digits.get(words..).unwrap_or_default();
+        let digits = (0..digits.len())
+            .map(|i| {
+                let high = digits.get(i + 1).map_or(0, |digit| u64::from(*digit) << 32);
+                ((high | u64::from(digits[i])) >> bits) as u32
+            })
+            .collect();
+        let mut result = BigUint { digits };
+        result.normalise();
+        result
+    }
+
+    /// Returns the quotient and remainder of `self / div`, where `div` is not
+    /// zero.
+    fn div_rem(&self, div : &BigUint) -> (BigUint, BigUint) {
+        if let [small] = div.digits[..] {
+            let mut quotient = self.clone();
+            let rem = quotient.div_small(small);
+            return (quotient, BigUint::from_u128(rem.into()));
+        }
+        // binary long division
+        let mut quotient = vec![0u32; self.digits.len()];
+        let mut rem = BigUint::from_u128(0);
+        for i in (0..self.bits()).rev() {
+            rem = rem.shl(1);
+            if self.bit(i) {
+                rem = rem.add(&BigUint::from_u128(1));
+            }
+            if rem >= *div {
+                rem = rem.sub(div);
+                quotient[(i / 32) as usize] |= 1 << (i % 32);
+            }
+        }
+        let mut quotient = BigUint { digits : quotient };
+        quotient.normalise();
+        (quotient, rem)
+    }
+
+    /// Returns the greatest common divisor of two numbers, using the binary
+    /// GCD algorithm.
+    fn gcd(&self, other : &BigUint) -> BigUint {
+        if self.is_zero() {
+            return other.clone();
+        }
+        if other.is_zero() {
+            return self.clone();
+        }
+        let shift = cmp::min(self.trailing_zeros(), other.trailing_zeros());
+        let mut a = self.shr(self.trailing_zeros());
+        let mut b = other.clone();
+        while !b.is_zero() {
+            b = b.shr(b.trailing_zeros());
+            if a > b {
+                (a, b) = (b, a);
+            }
+            b = b.sub(&a);
+        }
+        a.shl(shift)
+    }
+
+    fn digit(&self, i : usize) -> u32 { self.digits.get(i).copied().unwrap_or(0) }
+
+    fn bit(&self, i : u64) -> bool { self.digit((i / 32) as usize) >> (i % 32) & 1 == 1 }
+
+    fn normalise(&mut self) {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other : &BigUint) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other : &BigUint) -> Ordering {
+        self.digits.len().cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        // split the number into chunks of 9 decimal digits
+        let mut n = self.clone();
+        let mut chunks = Vec::new();
+        while !n.is_zero() {
+            chunks.push(n.div_small(1_000_000_000));
+        }
+        let Some(last) = chunks.pop() else { return write!(out, "0") };
+        write!(out, "{}", last)?;
+        for chunk in chunks.iter().rev() {
+            write!(out, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+/// A rational number `numer / denom`, stored in its lowest terms. Zero is
+/// never negative.
+///
+/// Rationals are constructed from number literals and then kept exact by
+/// constant evaluation, so they are only rounded once a concrete float type
+/// is needed.
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+pub struct Rational {
+    pub negative : bool,
+    pub numer : BigUint,
+    pub denom : BigUint,
+}
+
+impl Rational {
+    fn new(negative : bool, numer : BigUint, denom : BigUint) -> Rational {
+        let gcd = numer.gcd(&denom);
+        let (numer, denom) = if gcd.bits() > 1 {
+            (numer.div_rem(&gcd).0, denom.div_rem(&gcd).0)
+        } else {
+            (numer, denom)
+        };
+        let denom = if numer.is_zero() { BigUint::from_u128(1) } else { denom };
+        Rational { negative : negative && !numer.is_zero(), numer, denom }
+    }
+
+    pub fn from_u128(n : u128) -> Rational {
+        Rational::from_i128_parts(false, n)
+    }
+
+    pub fn from_i128(n : i128) -> Rational {
+        Rational::from_i128_parts(n < 0, n.unsigned_abs())
+    }
+
+    fn from_i128_parts(negative : bool, n : u128) -> Rational {
+        Rational::new(negative, BigUint::from_u128(n), BigUint::from_u128(1))
+    }
+
+    /// Returns the exact value of a float, or `None` if it's infinite or NaN.
+    pub fn from_f64(n : f64) -> Option<Rational> {
+        if !n.is_finite() {
+            return None;
+        }
+        let bits = n.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = bits & ((1 << 52) - 1);
+        // subnormal numbers have no implicit leading bit
+        let (mantissa, exponent) = if exponent == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | 1 << 52, exponent - 1075)
+        };
+        let one = BigUint::from_u128(1);
+        let mantissa = BigUint::from_u128(mantissa.into());
+        let (numer, denom) = if exponent < 0 {
+            (mantissa, one.shl(exponent.unsigned_abs()))
+        } else {
+            (mantissa.shl(exponent as u64), one)
+        };
+        Some(Rational::new(n < 0.0, numer, denom))
+    }
+
+    /// Parses a decimal number such as `12.5` or `1.5e-3`, ignoring any
+    /// underscores.
+    ///
+    /// Returns `None` if `src` isn't a decimal number, or if its exponent is
+    /// larger than `MAX_EXPONENT`.
+    pub fn from_decimal(src : &str) -> Option<Rational> {
+        let (mantissa, exponent) = match src.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (src, 0),
+        };
+        if exponent.abs() > MAX_EXPONENT {
+            return None;
+        }
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let mut numer = BigUint::from_u128(0);
+        for x in int.chars().chain(frac.chars()).filter(|x| *x != '_') {
+            numer.mul_add_small(10, x.to_digit(10)?);
+        }
+        let frac_len = frac.chars().filter(|x| *x != '_').count() as i64;
+        let exponent = exponent - frac_len;
+        let mut denom = BigUint::from_u128(1);
+        let scaled = if exponent < 0 { &mut denom } else { &mut numer };
+        for _ in 0..exponent.abs() {
+            scaled.mul_add_small(10, 0);
+        }
+        Some(Rational::new(false, numer, denom))
+    }
+
+    pub fn is_zero(&self) -> bool { self.numer.is_zero() }
+
+    /// Returns the number of bits needed to store this number.
+    pub fn bits(&self) -> u64 { self.numer.bits() + self.denom.bits() }
+
+    pub fn neg(&self) -> Rational {
+        Rational { negative : !self.negative && !self.is_zero(), ..self.clone() }
+    }
+
+    pub fn add(&self, other : &Rational) -> Rational {
+        let lhs = self.numer.mul(&other.denom);
+        let rhs = other.numer.mul(&self.denom);
+        let denom = self.denom.mul(&other.denom);
+        if self.negative == other.negative {
+            return Rational::new(self.negative, lhs.add(&rhs), denom);
+        }
+        // the signs differ, so subtract the smaller magnitude from the larger
+        if lhs >= rhs {
+            Rational::new(self.negative, lhs.sub(&rhs), denom)
+        } else {
+            Rational::new(other.negative, rhs.sub(&lhs), denom)
+        }
+    }
+
+    pub fn sub(&self, other : &Rational) -> Rational { self.add(&other.neg()) }
+
+    pub fn mul(&self, other : &Rational) -> Rational {
+        Rational::new(
+            self.negative != other.negative,
+            self.numer.mul(&other.numer),
+            self.denom.mul(&other.denom),
+        )
+    }
+
+    /// Returns `self / other`, or `None` if `other` is zero.
+    pub fn div(&self, other : &Rational) -> Option<Rational> {
+        if other.is_zero() {
+            return None;
+        }
+        Some(Rational::new(
+            self.negative != other.negative,
+            self.numer.mul(&other.denom),
+            self.denom.mul(&other.numer),
+        ))
+    }
+
+    /// Returns the remainder of `self / other`, which has the same sign as
+    /// `self` like the `%` operator on floats, or `None` if `other` is zero.
+    pub fn rem(&self, other : &Rational) -> Option<Rational> {
+        let quotient = self.div(other)?.trunc();
+        Some(self.sub(&other.mul(&quotient)))
+    }
+
+    /// Rounds this number towards zero.
+    pub fn trunc(&self) -> Rational {
+        let (int, _) = self.numer.div_rem(&self.denom);
+        Rational::new(self.negative, int, BigUint::from_u128(1))
+    }
+
+    /// Rounds this number towards zero, returning `None` if the result
+    /// doesn't fit into an `i128`.
+    pub fn to_i128(&self) -> Option<i128> {
+        let (int, _) = self.numer.div_rem(&self.denom);
+        let int = i128::try_from(int.to_u128()?).ok();
+        match (int, self.negative) {
+            (Some(int), false) => Some(int),
+            (Some(int), true) => Some(-int),
+            // `i128::MIN` has no positive counterpart
+            (None, true) if self.trunc() == Rational::from_i128(i128::MIN) => Some(i128::MIN),
+            (None, _) => None,
+        }
+    }
+
+    /// Returns the closest `f64` to this number.
+    pub fn to_f64(&self) -> f64 { self.to_float(53, -1074, 1023) }
+
+    /// Returns the closest `f32` to this number.
+    pub fn to_f32(&self) -> f32 { self.to_float(24, -149, 127) as f32 }
+
+    /// Returns whether this number can be represented exactly by an `f64`.
+    pub fn is_exact_f64(&self) -> bool { self.is_exact_float(53, -1074, 1023) }
+
+    /// Returns whether this number can be represented exactly by an `f32`.
+    pub fn is_exact_f32(&self) -> bool { self.is_exact_float(24, -149, 127) }
+
+    /// Rounds this number to the nearest value of a binary float with
+    /// `precision` significant bits, whose smallest positive value is
+    /// `2^min_exponent` and whose largest values are below
+    /// `2^(max_exponent + 1)`. Ties are rounded to even, and values which are
+    /// too large become infinite.
+    ///
+    /// The result is returned as an `f64`, which can represent every value
+    /// of the smaller float types exactly.
+    fn to_float(&self, precision : u64, min_exponent : i64, max_exponent : i64) -> f64 {
+        if self.is_zero() {
+            return 0.0;
+        }
+        let sign = if self.negative { -1.0 } else { 1.0 };
+        // scale the numerator so the quotient has at least two more bits than
+        // are needed, so it can be rounded correctly
+        let shift = precision as i64 + 2 - (self.numer.bits() as i64 - self.denom.bits() as i64);
+        let (numer, denom) = if shift >= 0 {
+            (self.numer.shl(shift as u64), self.denom.clone())
+        } else {
+            (self.numer.clone(), self.denom.shl(shift.unsigned_abs()))
+        };
+        let (quotient, rem) = numer.div_rem(&denom);
+        let sticky = !rem.is_zero();
+        // the value is `quotient * 2^-shift`, where the quotient has at most
+        // `precision + 3` bits
+        let quotient = quotient.to_u128().unwrap_or(u128::MAX);
+        let top = 127 - i64::from(quotient.leading_zeros()) - shift;
+        if top > max_exponent {
+            return sign * f64::INFINITY;
+        }
+        // the exponent of the lowest bit which is kept
+        let low = cmp::max(top - (precision as i64 - 1), min_exponent);
+        let dropped = low + shift;
+        if dropped >= 128 {
+            return sign * 0.0;
+        }
+        let mut mantissa = quotient >> dropped;
+        let rest = quotient & ((1 << dropped) - 1);
+        let half = 1 << (dropped - 1);
+        if rest > half || rest == half && (sticky || mantissa & 1 == 1) {
+            mantissa += 1;
+        }
+        if mantissa == 0 {
+            return sign * 0.0;
+        }
+        if 127 - i64::from(mantissa.leading_zeros()) + low > max_exponent {
+            return sign * f64::INFINITY;
+        }
+        sign * scale(mantissa as f64, low)
+    }
+
+    /// Returns whether this number can be represented exactly by a binary
+    /// float with `precision` significant bits, whose smallest positive
+    /// value is `2^min_exponent` and whose largest values are below
+    /// `2^(max_exponent + 1)`.
+    fn is_exact_float(&self, precision : u64, min_exponent : i64, max_exponent : i64) -> bool {
+        if self.is_zero() {
+            return true;
+        }
+        // only fractions whose denominator is a power of 2 can be exact
+        let mut denom = self.denom.clone();
+        let denom_exponent = denom.remove_factor(2) as i64;
+        if denom.to_u128() != Some(1) {
+            return false;
+        }
+        let shift = self.numer.trailing_zeros();
+        let significant_bits = self.numer.bits() - shift;
+        let lowest_exponent = shift as i64 - denom_exponent;
+        let highest_exponent = self.numer.bits() as i64 - 1 - denom_exponent;
+        significant_bits <= precision
+            && lowest_exponent >= min_exponent
+            && highest_exponent <= max_exponent
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other : &Rational) -> Option<Ordering> { Some(self.cmp(other)) }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other : &Rational) -> Ordering {
+        let magnitude = || {
+            self.numer.mul(&other.denom).cmp(&other.numer.mul(&self.denom))
+        };
+        match (self.negative, other.negative) {
+            (false, false) => magnitude(),
+            (true, true) => magnitude().reverse(),
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+        }
+    }
+}
+
+impl fmt::Display for Rational {
+    /// Writes the exact decimal expansion of this number if it has one,
+    /// otherwise writes it as a fraction, e.g. `1/3`.
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        if self.negative {
+            write!(out, "-")?;
+        }
+        let mut denom = self.denom.clone();
+        let twos = denom.remove_factor(2);
+        let fives = denom.remove_factor(5);
+        if denom.to_u128() != Some(1) {
+            return write!(out, "{}/{}", self.numer, self.denom);
+        }
+        // scale the numerator so the denominator is a power of 10
+        let places = twos.max(fives);
+        let mut numer = self.numer.clone();
+        for _ in twos..places {
+            numer.mul_add_small(2, 0);
+        }
+        for _ in fives..places {
+            numer.mul_add_small(5, 0);
+        }
+        let digits = format!("{:0>width$}", numer.to_string(), width = places as usize + 1);
+        let (int, frac) = digits.split_at(digits.len() - places as usize);
+        if frac.is_empty() {
+            write!(out, "{}", int)
+        } else {
+            write!(out, "{}.{}", int, frac)
+        }
+    }
+}
+
+/// Returns `n * 2^exponent`, without rounding any intermediate results if the
+/// result can be represented exactly.
+fn scale(mut n : f64, mut exponent : i64) -> f64 {
+    let step = 2f64.powi(900);
+    while exponent > 900 {
+        n *= step;
+        exponent -= 900;
+    }
+    while exponent < -900 {
+        n /= step;
+        exponent += 900;
+    }
+    n * 2f64.powi(exponent as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(src : &str) -> Rational {
+        Rational::from_decimal(src).unwrap()
+    }
+
+    #[test]
+    fn parses_decimals_in_lowest_terms() {
+        let half = decimal("0.5");
+        assert_eq!(half.numer, BigUint::from_u128(1));
+        assert_eq!(half.denom, BigUint::from_u128(2));
+        assert_eq!(decimal("1_250e-3").to_string(), "1.25");
+        assert_eq!(decimal("12.5E2").to_string(), "1250");
+        assert!(decimal("0.000").is_zero());
+        assert_eq!(Rational::from_decimal("1e1001"), None);
+    }
+
+    #[test]
+    fn arithmetic_is_exact() {
+        let sum = decimal("0.1").add(&decimal("0.2"));
+        assert_eq!(sum, decimal("0.3"));
+        assert_eq!(decimal("0.1").sub(&decimal("0.3")).to_string(), "-0.2");
+        assert_eq!(decimal("1.5").mul(&decimal("4").neg()), Rational::from_i128(-6));
+        let third = Rational::from_u128(1).div(&Rational::from_u128(3)).unwrap();
+        assert_eq!(third.to_string(), "1/3");
+        assert_eq!(third.mul(&Rational::from_u128(3)), Rational::from_u128(1));
+        assert_eq!(third.div(&Rational::from_u128(0)), None);
+    }
+
+    #[test]
+    fn remainder_has_the_sign_of_the_dividend() {
+        let rem = |a : &str, b : &str| decimal(a).rem(&decimal(b)).unwrap().to_string();
+        assert_eq!(rem("7.5", "2"), "1.5");
+        assert_eq!(decimal("7.5").neg().rem(&decimal("2")).unwrap().to_string(), "-1.5");
+        assert_eq!(decimal("7.5").rem(&decimal("0")), None);
+    }
+
+    #[test]
+    fn compares_by_value() {
+        assert!(decimal("0.1") < decimal("0.25"));
+        assert!(decimal("2").neg() < decimal("1").neg());
+        assert!(decimal("1").neg() < decimal("0"));
+        assert_eq!(decimal("0").neg(), decimal("0"));
+    }
+
+    #[test]
+    fn truncates_to_integers() {
+        assert_eq!(decimal("2.75").to_i128(), Some(2));
+        assert_eq!(decimal("2.75").neg().to_i128(), Some(-2));
+        assert_eq!(Rational::from_i128(i128::MIN).to_i128(), Some(i128::MIN));
+        assert_eq!(Rational::from_u128(u128::MAX).to_i128(), None);
+    }
+
+    #[test]
+    fn rounds_to_the_nearest_float() {
+        for src in ["0.1", "0.3", "1e300", "2.5e-320", "4.9e-324", "123456.789", "1e-5"] {
+            assert_eq!(decimal(src).to_f64(), src.parse::<f64>().unwrap(), "{}", src);
+            assert_eq!(decimal(src).to_f32(), src.parse::<f32>().unwrap(), "{}", src);
+        }
+        // halfway between 1 and the next float rounds to even
+        assert_eq!(decimal("1.00000000000000011102230246251565404236316680908203125").to_f64(), 1.0);
+        assert_eq!(Rational::from_u128(1).div(&Rational::from_u128(3)).unwrap().to_f64(), 1.0 / 3.0);
+        assert_eq!(decimal("2e-324").to_f64(), 0.0);
+        assert_eq!(decimal("1e309").to_f64(), f64::INFINITY);
+        assert_eq!(decimal("3.5e38").to_f32(), f32::INFINITY);
+        assert_eq!(decimal("1.5").neg().to_f64(), -1.5);
+    }
+
+    #[test]
+    fn converts_floats_exactly() {
+        for n in [0.1, -2.5, f64::MAX, f64::MIN_POSITIVE, 5e-324] {
+            let rational = Rational::from_f64(n).unwrap();
+            assert!(rational.is_exact_f64());
+            assert_eq!(rational.to_f64(), n);
+        }
+        assert_eq!(Rational::from_f64(f64::NAN), None);
+        assert_eq!(Rational::from_f64(f64::INFINITY), None);
+    }
+
+    #[test]
+    fn checks_whether_floats_are_exact() {
+        assert!(decimal("0.5").is_exact_f32());
+        assert!(!decimal("0.1").is_exact_f64());
+        assert!(decimal("16777216").is_exact_f32());
+        assert!(!decimal("16777217").is_exact_f32());
+        assert!(decimal("16777217").is_exact_f64());
+        // too small to be represented, even as a subnormal
+        let tiny = Rational::from_f64(f64::from_bits(1)).unwrap();
+        assert!(tiny.is_exact_f64());
+        assert!(!tiny.div(&Rational::from_u128(2)).unwrap().is_exact_f64());
+    }
+
+    #[test]
+    fn exact_floats_cannot_overflow() {
+        // 2^128 has a single significant bit, but is larger than `f32::MAX`
+        let big = Rational::from_u128(1 << 127).mul(&Rational::from_u128(2));
+        assert!(Rational::from_u128(1 << 127).is_exact_f32());
+        assert!(!big.is_exact_f32());
+        assert!(big.is_exact_f64());
+        let max = Rational::from_f64(f64::MAX).unwrap();
+        assert!(!max.mul(&Rational::from_u128(2)).is_exact_f64());
+    }
+
+    #[test]
+    fn big_integers_divide() {
+        let a = BigUint::from_u128(u128::MAX).mul(&BigUint::from_u128(u128::MAX));
+        let b = BigUint::from_u128(u128::MAX - 6);
+        let (quotient, rem) = a.div_rem(&b);
+        assert_eq!(quotient.mul(&b).add(&rem), a);
+        assert!(rem < b);
+        assert_eq!(a.gcd(&BigUint::from_u128(u128::MAX)), BigUint::from_u128(u128::MAX));
+        assert_eq!(a.shl(70).shr(70), a);
+    }
+}
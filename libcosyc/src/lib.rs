@@ -1,3 +1,5 @@
+#[macro_use]
+pub mod locale;
 pub mod src;
 pub mod pretty;
 pub mod error;
@@ -0,0 +1,206 @@
+//! The catalogues of diagnostic messages, one for each supported language.
+//!
+//! Messages are identified by a key, e.g. `build-no-main`, and are written
+//! using a subset of the Fluent syntax:
+//!
+//! ```text
+//! # comments start with a hash
+//! build-no-main = package has no `main` function
+//! build-main-bad-return = `main` must return either `int` or `()`, got `{ $0 }`
+//! diag-bug-report =
+//!     likely caused by a bug in the compiler, please report the issue:
+//!     https://github.com/katsaii/cosy-lang/issues
+//! ```
+//!
+//! Placeholders refer to the arguments of a message by position, so a
+//! translation can reorder them. Indented lines continue the previous
+//! message on a new line.
+//!
+//! Catalogues are embedded in the compiler. If a message is missing from
+//! the selected language, the English message is used instead.
+
+use std::{ env, fmt };
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+/// Creates a `Message` from its key in the catalogue, and the arguments to
+/// substitute into its placeholders.
+///
+/// ```ignore
+/// Diagnostic::error()
+///     .message(msg!("build-no-main"))
+///     .note(msg!("build-main-bad-return", [ty.to_string().into()]))
+/// ```
+#[macro_export]
+macro_rules! msg {
+    ($key:literal) => {
+        $crate::src::Message::new($key, ::std::iter::empty())
+    };
+    ($key:literal, $args:expr $(,)?) => {
+        $crate::src::Message::new($key, $args)
+    };
+}
+
+/// The language used when no other language is selected, and the fallback
+/// for messages which haven't been translated.
+pub const DEFAULT_LANGUAGE : &str = "en";
+
+/// The source of every catalogue embedded in the compiler, with the default
+/// language first.
+pub static SOURCES : &[(&str, &str)] = &[
+    (DEFAULT_LANGUAGE, include_str!("../locales/en.ftl")),
+];
+
+static CATALOGUES : LazyLock<Vec<Catalogue>> = LazyLock::new(|| {
+    // a malformed catalogue is treated as empty, so its messages fall back
+    // to English. `tests/catalogue.rs` checks that they all parse
+    SOURCES.iter()
+        .map(|(_, src)| Catalogue::parse(src).unwrap_or_default())
+        .collect()
+});
+
+/// The index of the selected language in `SOURCES`.
+static SELECTED : AtomicUsize = AtomicUsize::new(0);
+
+/// A set of messages written in a single language.
+#[derive(Debug, Default)]
+pub struct Catalogue {
+    messages : HashMap<String, String>,
+}
+
+/// Describes why a catalogue could not be parsed.
+#[derive(Debug)]
+pub struct CatalogueError {
+    /// The line the error occurred on, starting from 1.
+    pub line : usize,
+    pub message : &'static str,
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, out : &mut fmt::Formatter) -> fmt::Result {
+        write!(out, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Catalogue {
+    /// Parses a catalogue from its source.
+    pub fn parse(src : &str) -> Result<Catalogue, CatalogueError> {
+        let mut messages = HashMap::new();
+        let mut current : Option<(String, String)> = None;
+        for (i, line) in src.lines().enumerate() {
+            let error = |message| CatalogueError { line : i + 1, message };
+            if line.starts_with([' ', '\t']) && !line.trim().is_empty() {
+                let Some((_, value)) = &mut current else {
+                    return Err(error("indented line outside of a message"));
+                };
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(line.trim());
+                continue;
+            }
+            if let Some((key, value)) = current.take() {
+                messages.insert(key, value);
+            }
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(error("expected `key = value`"));
+            };
+            let key = key.trim();
+            let is_key = key.starts_with(|chr : char| chr.is_ascii_lowercase())
+                && key.chars().all(|chr| chr.is_ascii_alphanumeric() || chr == '-');
+            if !is_key {
+                return Err(error("keys must be lowercase letters, digits, and `-`"));
+            }
+            if messages.contains_key(key) {
+                return Err(error("duplicate key"));
+            }
+            current = Some((key.to_string(), value.trim().to_string()));
+        }
+        if let Some((key, value)) = current {
+            messages.insert(key, value);
+        }
+        Ok(Catalogue { messages })
+    }
+
+    /// Looks up the template of a message by its key.
+    pub fn get(&self, key : &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// Returns the key of every message in this catalogue, in no particular
+    /// order.
+    pub fn keys(&self) -> impl Iterator<Item=&str> {
+        self.messages.keys().map(String::as_str)
+    }
+}
+
+/// Returns the catalogue of a language, if it is supported.
+pub fn catalogue(lang : &str) -> Option<&'static Catalogue> {
+    let i = SOURCES.iter().position(|(name, _)| *name == lang)?;
+    CATALOGUES.get(i)
+}
+
+/// Returns the catalogue of the default language.
+pub fn default_catalogue() -> &'static Catalogue {
+    &CATALOGUES[0]
+}
+
+/// Returns the name of every supported language.
+pub fn languages() -> impl Iterator<Item=&'static str> {
+    SOURCES.iter().map(|(name, _)| *name)
+}
+
+/// Returns the name of the selected language.
+pub fn language() -> &'static str {
+    SOURCES[SELECTED.load(Ordering::Relaxed)].0
+}
+
+/// Selects the language that messages are displayed in, given a language
+/// tag such as `fr`, `pt-BR`, or a POSIX locale such as `pt_BR.UTF-8`. If
+/// there is no catalogue for a regional variant, the catalogue for the
+/// language as a whole is used.
+///
+/// Returns `false` if the language isn't supported, in which case the
+/// selection is unchanged.
+pub fn set_language(tag : &str) -> bool {
+    let Some(i) = find_language(tag) else { return false };
+    SELECTED.store(i, Ordering::Relaxed);
+    true
+}
+
+/// Selects the language that messages are displayed in using the
+/// `LC_ALL`, `LC_MESSAGES`, and `LANG` environment variables, in that order.
+/// Uses the default language if none of them are set to a supported
+/// language.
+pub fn set_language_from_env() {
+    let tag = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty());
+    let i = tag.and_then(|tag| find_language(&tag)).unwrap_or(0);
+    SELECTED.store(i, Ordering::Relaxed);
+}
+
+fn find_language(tag : &str) -> Option<usize> {
+    // strip the encoding and modifier from POSIX locales, e.g. `.UTF-8`
+    let tag = tag.split(['.', '@']).next().unwrap_or(tag).replace('_', "-");
+    if tag == "C" || tag == "POSIX" {
+        return Some(0);
+    }
+    let find = |tag : &str| SOURCES.iter().position(|(name, _)| name.eq_ignore_ascii_case(tag));
+    find(&tag).or_else(|| find(tag.split('-').next()?))
+}
+
+/// Looks up the template of a message in the selected language, falling
+/// back to the default language if it hasn't been translated. If the key
+/// doesn't exist at all, the key itself is returned.
+pub fn lookup(key : &'static str) -> &'static str {
+    let selected = &CATALOGUES[SELECTED.load(Ordering::Relaxed)];
+    selected.get(key)
+        .or_else(|| default_catalogue().get(key))
+        .unwrap_or(key)
+}
@@ -244,7 +244,7 @@ impl Decoration {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct Style {
     decoration : Option<Decoration>,
     bg : Option<Colour>,
@@ -8,6 +8,8 @@ use std::hash::{ DefaultHasher, Hash, Hasher };
 use std::collections::HashMap;
 use std::{ fmt, fs, ops, cmp, io };
 
+use crate::locale;
+
 /// A simple handle to a file managed by the compiler.
 pub type FileId = u64;
 
@@ -128,6 +130,19 @@ impl SourceMap {
         SaveManifestResult::Ok
     }
 
+    /// Returns every source file which has been loaded, along with its path.
+    pub fn loaded_files(&self) -> Vec<(PathBuf, Arc<SourceFile>)> {
+        let files = self.files.borrow();
+        let mut loaded = files.iter()
+            .filter_map(|(file_id, file)| {
+                let path = &self.manifest.id_2_file.get(file_id)?.path;
+                Some((path.clone(), file.clone()))
+            })
+            .collect::<Vec<_>>();
+        loaded.sort_by_key(|(_, file)| file.id);
+        loaded
+    }
+
     fn add_file(&self, file_id : FileId, src : String) -> Arc<SourceFile> {
         let lines = str_get_lines(&src);
         let file = Arc::new(SourceFile { id : file_id, src, lines });
@@ -303,7 +318,7 @@ impl SourceFile {
 }
 
 /// Points to a file location within the current package/translation unit.
-#[derive(Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
 pub struct Location {
     pub span : Span,
     pub file_id : FileId,
@@ -316,6 +331,20 @@ impl fmt::Debug for Location {
 }
 
 impl Location {
+    /// Returns an empty location at the start of this one, e.g. to insert
+    /// code before it.
+    pub fn start(&self) -> Location {
+        Location {
+            span : Span::new(self.span.start..self.span.start),
+            file_id : self.file_id,
+        }
+    }
+
+    /// Joins two locations in the same file together, covering both.
+    pub fn join(&self, other : &Location) -> Location {
+        Location { span : self.span.join(&other.span), file_id : self.file_id }
+    }
+
     /// Returns the filename a source location points to in the format
     /// `filename.ext:line:column`.
     pub fn show_path(&self, source_map : &SourceMap) -> String {
@@ -349,14 +378,14 @@ impl Location {
 }
 
 /// Pairs a value with its location in the source code.
-#[derive(Debug, bincode::Encode, bincode::Decode)]
+#[derive(Debug, Clone, bincode::Encode, bincode::Decode)]
 pub struct Located<T> {
     pub value : T,
     pub loc : Location,
 }
 
 /// Represents a span of bytes within a file.
-#[derive(Clone, Copy, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, bincode::Encode, bincode::Decode)]
 pub struct Span {
     /// The starting byte of the span (inclusive).
     pub start : usize,
@@ -440,24 +469,37 @@ pub(crate) fn find_line_and_col(
 }
 
 /// Represents a complete message that may contain source information.
+///
+/// The text of a message is looked up in the catalogue of the selected
+/// language, see `locale`. Use `msg!` to create one.
+#[derive(PartialEq, Eq)]
 pub struct Message {
-    template : &'static str,
+    /// The key of this message in the catalogue, or `None` if the arguments
+    /// are displayed as-is.
+    key : Option<&'static str>,
     args : Vec<TextFragment>,
 }
 
-impl From<&'static str> for Message {
-    fn from(template : &'static str) -> Message {
-        Message { template, args : Vec::new() }
+impl Message {
+    /// Creates a new message from its key in the catalogue, and the
+    /// arguments to substitute into its placeholders.
+    pub fn new<I : IntoIterator<Item=TextFragment>>(
+        key : &'static str,
+        args : I,
+    ) -> Message {
+        Message { key : Some(key), args : args.into_iter().collect() }
     }
-}
 
-impl<I : IntoIterator<Item=TextFragment>> From<(&'static str, I)> for Message {
-    fn from((template, args) : (&'static str, I)) -> Message {
-        Message { template, args : args.into_iter().collect() }
+    /// Creates a message which displays `text` as-is, without translating
+    /// it. Used for text which comes from outside of the compiler, such as
+    /// the output of `clang`.
+    pub fn verbatim<T : Into<TextFragment>>(text : T) -> Message {
+        Message { key : None, args : vec![text.into()] }
     }
-}
 
-impl Message {
+    /// Returns the key of this message in the catalogue, if it has one.
+    pub fn key(&self) -> Option<&'static str> { self.key }
+
     /// Copies the contents of this message to a desination string.
     ///
     /// Returns the number of bytes written to `dest`.
@@ -466,22 +508,31 @@ impl Message {
         source_map : &SourceMap,
         dest : &mut String
     ) -> usize {
-        let mut arg_n = 0;
-        let mut template_slice = self.template;
+        let Some(key) = self.key else {
+            return self.args.iter()
+                .map(|arg| arg.write_to_string(source_map, dest))
+                .sum();
+        };
+        let mut template_slice = locale::lookup(key);
         let mut bytes = 0;
-        while let Some(end) = template_slice.find("{}") {
-            let prefix = &template_slice[..end];
+        while let Some(start) = template_slice.find('{') {
+            let prefix = &template_slice[..start];
             bytes += prefix.len();
             dest.push_str(prefix);
-            template_slice = &template_slice[end + 2..];
-            // write arg
-            if let Some(arg) = self.args.get(arg_n) {
+            template_slice = &template_slice[start..];
+            // placeholders look like `{ $0 }`
+            let arg = template_slice.find('}').and_then(|end| {
+                let n = template_slice[1..end].trim().strip_prefix('$')?;
+                Some((end, self.args.get(n.parse::<usize>().ok()?)?))
+            });
+            if let Some((end, arg)) = arg {
                 bytes += arg.write_to_string(source_map, dest);
+                template_slice = &template_slice[end + 1..];
             } else {
-                bytes += 2;
-                dest.push_str("{}");
+                bytes += 1;
+                dest.push('{');
+                template_slice = &template_slice[1..];
             }
-            arg_n += 1;
         }
         bytes += template_slice.len();
         dest.push_str(template_slice);
@@ -494,6 +545,9 @@ impl Message {
 pub enum TextFragment {
     Text(String),
     Code(Location),
+    /// A message nested inside of another, e.g. a description of something
+    /// which is used by several messages.
+    Message(Message),
 }
 
 impl From<Location> for TextFragment {
@@ -502,6 +556,12 @@ impl From<Location> for TextFragment {
     }
 }
 
+impl From<Message> for TextFragment {
+    fn from(message : Message) -> TextFragment {
+        TextFragment::Message(message)
+    }
+}
+
 impl<S : ToString> From<S> for TextFragment {
     fn from(s : S) -> TextFragment {
         TextFragment::Text(s.to_string())
@@ -525,6 +585,9 @@ impl TextFragment {
             TextFragment::Code(location) => {
                 location.write_to_string(source_map, dest)
             },
+            TextFragment::Message(message) => {
+                message.write_to_string(source_map, dest)
+            },
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,108 @@
+//! Checks the message catalogues against the keys used by the compiler.
+//!
+//! Every `msg!("key", ..)` in the source of `libcosyc` and `cosy` must have
+//! a message in the default catalogue, and every message in the default
+//! catalogue must be used. Translations can only contain keys and
+//! placeholders which also exist in the default catalogue.
+
+use std::fs;
+use std::collections::BTreeSet;
+use std::path::{ Path, PathBuf };
+
+use libcosyc::locale::{ self, Catalogue };
+
+const SOURCE_DIRS : &[&str] = &["src", "../cosy/src"];
+
+#[test]
+fn catalogues_parse() {
+    for (lang, src) in locale::SOURCES {
+        if let Err(err) = Catalogue::parse(src) {
+            panic!("failed to parse the `{}` catalogue: {}", lang, err);
+        }
+    }
+}
+
+#[test]
+fn used_keys_exist() {
+    let used = used_keys();
+    let catalogue = locale::default_catalogue();
+    let missing = used.iter()
+        .filter(|key| catalogue.get(key).is_none())
+        .collect::<Vec<_>>();
+    assert!(missing.is_empty(),
+        "keys missing from the `{}` catalogue: {:?}", locale::DEFAULT_LANGUAGE, missing);
+    let unused = catalogue.keys()
+        .filter(|key| !used.contains(*key))
+        .collect::<BTreeSet<_>>();
+    assert!(unused.is_empty(),
+        "keys in the `{}` catalogue which are never used: {:?}", locale::DEFAULT_LANGUAGE, unused);
+}
+
+#[test]
+fn translations_match_default() {
+    let default = locale::default_catalogue();
+    for lang in locale::languages().skip(1) {
+        let catalogue = locale::catalogue(lang).unwrap();
+        for key in catalogue.keys() {
+            let Some(template) = default.get(key) else {
+                panic!("`{}` catalogue has unknown key `{}`", lang, key);
+            };
+            let expected = placeholders(template);
+            let got = placeholders(catalogue.get(key).unwrap());
+            assert!(got.is_subset(&expected),
+                "`{}` catalogue uses different placeholders for `{}`", lang, key);
+        }
+    }
+}
+
+#[test]
+fn select_language() {
+    assert!(locale::set_language("en"));
+    assert!(locale::set_language("en_GB.UTF-8"));
+    assert!(locale::set_language("C"));
+    assert!(!locale::set_language("xx"));
+    assert_eq!(locale::language(), locale::DEFAULT_LANGUAGE);
+}
+
+/// Finds the key of every use of `msg!` in the compiler.
+fn used_keys() -> BTreeSet<String> {
+    let mut files = Vec::new();
+    for dir in SOURCE_DIRS {
+        find_sources(Path::new(dir), &mut files);
+    }
+    let mut keys = BTreeSet::new();
+    for file in files {
+        let src = fs::read_to_string(&file).unwrap();
+        for (start, _) in src.match_indices("msg!(") {
+            let rest = src[start + 5..].trim_start();
+            let Some(rest) = rest.strip_prefix('"') else { continue };
+            let Some(end) = rest.find('"') else { continue };
+            keys.insert(rest[..end].to_string());
+        }
+    }
+    keys
+}
+
+fn find_sources(dir : &Path, files : &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("failed to read `{}`: {}", dir.display(), err));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            find_sources(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// Returns the argument numbers used by the placeholders of a template.
+fn placeholders(template : &str) -> BTreeSet<usize> {
+    template.split('{')
+        .skip(1)
+        .filter_map(|part| {
+            let (inner, _) = part.split_once('}')?;
+            inner.trim().strip_prefix('$')?.parse().ok()
+        })
+        .collect()
+}
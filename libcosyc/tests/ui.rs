@@ -0,0 +1,218 @@
+//! Golden-file tests for diagnostics.
+//!
+//! Every `.cy` file under `tests/ui` is compiled through the front end, and
+//! its diagnostics are rendered without colour and compared against the
+//! `.stderr` file next to it. If a fixture reports nothing, then it has no
+//! `.stderr` file. Run with `BLESS=1` to update the snapshots instead.
+//!
+//! Fixtures must also annotate the line of each diagnostic they expect:
+//!
+//! ```cosy
+//! local x : int = true --~ ERROR mismatched types
+//! ```
+//!
+//! The kind is the severity in upper case, and the rest of the line must be
+//! contained in the message. Use `--~^` to refer to the line above, with one
+//! `^` per line. Every error, warning, and bug whose primary label is inside
+//! the fixture must be annotated; infos and helps are only checked if they
+//! are.
+
+use std::{ env, fs };
+use std::panic::{ self, AssertUnwindSafe };
+use std::path::{ Path, PathBuf };
+
+use libcosyc::src::{ SourceMap, SourceFile };
+use libcosyc::error::{ IssueManager, Severity, cli };
+use libcosyc::pretty::PrettyPrinter;
+use libcosyc::build::{ self, Config };
+
+const UI_DIR : &str = "tests/ui";
+
+/// Written in place of the cache directory, so snapshots which mention the
+/// prelude don't depend on where the tests were built.
+const CACHE_PLACEHOLDER : &str = "$CACHE";
+
+/// The kinds of diagnostic an annotation can expect.
+const KINDS : &[&str] = &["INFO", "HELP", "WARNING", "ERROR", "BUG"];
+
+#[test]
+fn ui() {
+    let bless = env::var_os("BLESS").is_some_and(|value| value != "0");
+    let cache_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ui");
+    let mut fixtures = Vec::new();
+    find_fixtures(Path::new(UI_DIR), &mut fixtures);
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "no fixtures found in `{}`", UI_DIR);
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_fixture(fixture, &cache_dir, bless)
+        }));
+        let errors = match result {
+            Ok(errors) => errors,
+            Err(_) => vec!["the compiler panicked".to_string()],
+        };
+        if !errors.is_empty() {
+            failures.push(format!("{}:\n  {}", fixture.display(), errors.join("\n  ")));
+        }
+    }
+    if !failures.is_empty() {
+        panic!("{} of {} ui test(s) failed\n\n{}\n",
+            failures.len(), fixtures.len(), failures.join("\n\n"));
+    }
+}
+
+fn find_fixtures(dir : &Path, fixtures : &mut Vec<PathBuf>) {
+    let entries = fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("failed to read `{}`: {}", dir.display(), err));
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            find_fixtures(&path, fixtures);
+        } else if path.extension().is_some_and(|ext| ext == "cy") {
+            fixtures.push(path);
+        }
+    }
+}
+
+/// Compiles a single fixture, returning a description of each way it
+/// differs from what was expected.
+fn run_fixture(path : &Path, cache_dir : &Path, bless : bool) -> Vec<String> {
+    let mut files = SourceMap::default();
+    let mut issues = IssueManager::default();
+    let hir = build::check_module(&mut files, &mut issues, cache_dir, path);
+    if let Some(hir) = hir && !issues.has_errors() {
+        build::build_test_casm(&mut issues, Config::Debug, &[hir]);
+    }
+    let mut errors = match files.load_file(path) {
+        Ok(file) => check_annotations(&file, &files, &issues),
+        Err(err) => vec![format!("failed to read the fixture: {}", err)],
+    };
+    let mut output = Vec::new();
+    cli::write_errors(&mut PrettyPrinter::new(&mut output, false), &files, &issues).unwrap();
+    let actual = String::from_utf8(output).unwrap()
+        .replace(&cache_dir.display().to_string(), CACHE_PLACEHOLDER);
+    let snapshot_path = path.with_extension("stderr");
+    let expected = fs::read_to_string(&snapshot_path)
+        .map(|src| src.replace("\r\n", "\n"))
+        .unwrap_or_default();
+    if actual == expected {
+        return errors;
+    }
+    if bless {
+        if actual.is_empty() {
+            let _ = fs::remove_file(&snapshot_path);
+        } else {
+            fs::write(&snapshot_path, &actual).unwrap();
+        }
+        return errors;
+    }
+    errors.push(format!(
+        "output differs from `{}`, run with `BLESS=1` to update it\n{}",
+        snapshot_path.display(), diff(&expected, &actual),
+    ));
+    errors
+}
+
+/// A diagnostic the fixture expects on a specific line.
+struct Annotation {
+    line : usize,
+    kind : String,
+    message : String,
+}
+
+fn parse_annotations(src : &str) -> Vec<Annotation> {
+    let mut annotations = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let Some(start) = line.find("--~") else { continue };
+        let rest = &line[start + 3..];
+        let above = rest.chars().take_while(|chr| *chr == '^').count();
+        let rest = rest[above..].trim();
+        let (kind, message) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        annotations.push(Annotation {
+            line : (i + 1).saturating_sub(above),
+            kind : kind.to_string(),
+            message : message.trim().to_string(),
+        });
+    }
+    annotations
+}
+
+/// Checks the diagnostics reported inside the fixture against its
+/// annotations.
+fn check_annotations(
+    file : &SourceFile,
+    files : &SourceMap,
+    issues : &IssueManager,
+) -> Vec<String> {
+    let mut annotations = parse_annotations(&file.src);
+    let mut errors = Vec::new();
+    for annotation in &annotations {
+        if !KINDS.contains(&annotation.kind.as_str()) {
+            errors.push(format!("line {}: unknown annotation kind `{}`",
+                annotation.line, annotation.kind));
+        }
+    }
+    for diag in &issues.errors {
+        let Some(label) = diag.primary_labels.first() else { continue };
+        if label.location.file_id != file.id {
+            continue;
+        }
+        let line = file.find_line(label.location.span.start);
+        let kind = diag.severity.as_str().to_uppercase();
+        let mut message = String::new();
+        if let Some(diag_message) = &diag.message {
+            diag_message.write_to_string(files, &mut message);
+        }
+        let found = annotations.iter().position(|annotation| {
+            annotation.line == line
+                && annotation.kind == kind
+                && message.contains(&annotation.message)
+        });
+        match found {
+            Some(i) => { annotations.remove(i); },
+            None if diag.severity >= Severity::Warning => {
+                errors.push(format!("line {}: unexpected {}: {}", line, kind, message));
+            },
+            None => (),
+        }
+    }
+    for annotation in annotations {
+        errors.push(format!("line {}: expected {} not reported: {}",
+            annotation.line, annotation.kind, annotation.message));
+    }
+    errors
+}
+
+/// Shows which lines of `actual` differ from `expected`.
+fn diff(expected : &str, actual : &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    // longest common subsequence, so inserted lines don't misalign the rest
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            out.push_str(&format!("   {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            out.push_str(&format!(" - {}\n", expected[i]));
+            i += 1;
+        } else {
+            out.push_str(&format!(" + {}\n", actual[j]));
+            j += 1;
+        }
+    }
+    out
+}